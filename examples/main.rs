@@ -4,7 +4,7 @@ use std::error::Error;
 use std::io::prelude::*;
 use std::time;
 
-use beanstalkc::Beanstalkc;
+use beanstalkc::{Beanstalkc, BeanstalkcError};
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
@@ -48,10 +48,15 @@ async fn main() -> Result<(), Box<dyn Error>> {
         conn.pause_tube("jobs", time::Duration::from_secs(10))
             .await?
     );
-    dbg!(
-        conn.pause_tube("not-found", time::Duration::from_secs(10))
-            .await?
-    );
+    match conn
+        .pause_tube("not-found", time::Duration::from_secs(10))
+        .await
+    {
+        Err(BeanstalkcError::TubeNotFound(name)) => println!("no tube {}", name),
+        other => other?,
+    }
+    dbg!(conn.tube_pause_remaining("jobs").await?);
+    dbg!(conn.resume_tube("jobs").await?);
 
     let mut job = conn.reserve().await?;
     dbg!(job.id());
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use futures::stream::{self, Stream};
+
+use crate::command::{self, Status};
+use crate::error::BeanstalkcResult;
+use crate::Beanstalkc;
+
+/// `AuditedJob` is a job found by [`Beanstalkc::audit`], with its stats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditedJob {
+    /// Job id.
+    pub id: u64,
+    /// Job body.
+    pub body: Vec<u8>,
+    /// Stats of the job, e.g. `tube`, `state` or `age`.
+    pub stats: HashMap<String, String>,
+}
+
+impl AuditedJob {
+    /// Return the tube containing the job.
+    pub fn tube(&self) -> Option<&str> {
+        self.stats.get("tube").map(String::as_str)
+    }
+
+    /// Return the state of the job: `ready`, `delayed`, `reserved` or `buried`.
+    pub fn state(&self) -> Option<&str> {
+        self.stats.get("state").map(String::as_str)
+    }
+}
+
+/// Progress of one audit connection.
+struct Cursor {
+    conn: Beanstalkc,
+    opened: bool,
+    next: Option<u64>,
+    step: u64,
+    last: u64,
+}
+
+impl Cursor {
+    /// Return the next existing job, `None` once past the last id. Jobs deleted between
+    /// their stats and their peek are skipped.
+    async fn next_job(&mut self) -> BeanstalkcResult<Option<AuditedJob>> {
+        if !self.opened {
+            self.conn.open().await?;
+            self.opened = true;
+        }
+        while let Some(id) = self.next.filter(|&id| id <= self.last) {
+            self.next = id.checked_add(self.step);
+
+            let stats = self
+                .conn
+                .send(command::stats_job(id).expect(Status::NotFound))
+                .await?;
+            if stats.status == Status::NotFound {
+                continue;
+            }
+            let peek = self
+                .conn
+                .send(command::peek_job(id).expect(Status::NotFound))
+                .await?;
+            if peek.status == Status::NotFound {
+                continue;
+            }
+            return Ok(Some(AuditedJob {
+                id,
+                stats: stats.body_as_map()?,
+                body: peek.body.unwrap_or_default(),
+            }));
+        }
+        Ok(None)
+    }
+}
+
+impl Beanstalkc {
+    /// Return a stream of the existing jobs whose id is within `ids`, with their stats, for
+    /// queue audit reports. Beanstalkd cannot list jobs, so each id is probed with
+    /// `stats-job` and `peek`, concurrently over `connections` connections duplicated from
+    /// this client. An error about a single job, e.g. a body over
+    /// [`Beanstalkc::max_body_size`], is yielded and the audit goes on with the next id,
+    /// while a connection error ends the ids left to the connection which failed.
+    ///
+    /// Job ids are allocated in increasing order by the server, so `ids` can run from the
+    /// oldest job id known, e.g. the head jobs of a [`TubeSnapshot`](crate::TubeSnapshot),
+    /// to the id of a job just put. Jobs are yielded in no particular order, and the ones
+    /// deleted while the audit runs may be missed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use futures::StreamExt;
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut jobs = Box::pin(conn.audit(1..=10_000, 8));
+    /// while let Some(job) = jobs.next().await {
+    ///     let job = job.unwrap();
+    ///     println!("{} {:?} {:?}", job.id, job.tube(), job.state());
+    /// }
+    /// }
+    /// ```
+    pub fn audit(
+        &self,
+        ids: RangeInclusive<u64>,
+        connections: usize,
+    ) -> impl Stream<Item = BeanstalkcResult<AuditedJob>> + 'static {
+        let (first, last) = ids.into_inner();
+        let connections = connections.max(1) as u64;
+        let cursors = (0..connections)
+            .filter_map(|offset| first.checked_add(offset))
+            .filter(|&next| next <= last)
+            .map(|next| Cursor {
+                conn: self.duplicate(),
+                opened: false,
+                next: Some(next),
+                step: connections,
+                last,
+            })
+            .map(|cursor| {
+                Box::pin(stream::unfold(Some(cursor), |cursor| async move {
+                    let mut cursor = cursor?;
+                    match cursor.next_job().await {
+                        Ok(Some(job)) => Some((Ok(job), Some(cursor))),
+                        Ok(None) => {
+                            cursor.conn.close().await;
+                            None
+                        }
+                        Err(err) if err.is_connection_error() => Some((Err(err), None)),
+                        Err(err) => Some((Err(err), Some(cursor))),
+                    }
+                }))
+            });
+        stream::select_all(cursors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use crate::error::BeanstalkcError;
+    use crate::fake::FakeServer;
+    use crate::OversizeAction;
+
+    #[tokio::test]
+    async fn test_audit_goes_on_after_job_error() {
+        let server = FakeServer::start().await;
+        server.put("default", b"first");
+        server.put("default", b"oversized");
+        server.put("default", b"third");
+        let conn = server
+            .client()
+            .max_body_size(5, OversizeAction::Bury)
+            .connect()
+            .await
+            .unwrap();
+
+        let results: Vec<_> = conn.audit(1..=3, 1).collect().await;
+        assert_eq!(3, results.len());
+        assert_eq!(b"first", results[0].as_ref().unwrap().body.as_slice());
+        assert!(matches!(
+            results[1],
+            Err(BeanstalkcError::BodyTooLarge { id: 2, .. })
+        ));
+        assert_eq!(3, results[2].as_ref().unwrap().id);
+    }
+
+    #[tokio::test]
+    async fn test_audit_strides_go_on_after_job_error() {
+        let server = FakeServer::start().await;
+        server.put("default", b"oversized");
+        for body in [b"two", b"six", b"ten"].iter() {
+            server.put("default", *body);
+        }
+        let conn = server
+            .client()
+            .max_body_size(5, OversizeAction::Bury)
+            .connect()
+            .await
+            .unwrap();
+
+        let results: Vec<_> = conn.audit(1..=5, 2).collect().await;
+        let mut ids: Vec<u64> = results
+            .iter()
+            .filter_map(|job| job.as_ref().ok().map(|job| job.id))
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(vec![2, 3, 4], ids);
+        assert_eq!(1, results.iter().filter(|job| job.is_err()).count());
+    }
+}
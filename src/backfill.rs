@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::command::{self, Status};
+use crate::config::REWRITE_TUBE_SUFFIX;
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::put::PutRequest;
+use crate::Beanstalkc;
+
+impl Beanstalkc {
+    /// Re-put the delayed jobs of tube `name` with parameters adjusted by `f`, e.g. to
+    /// re-prioritize an already scheduled backlog. Return the number of jobs rewritten.
+    ///
+    /// `f` receives the stats of each delayed job and a request holding its body, priority,
+    /// delay left and TTR, and returns the request to put instead.
+    ///
+    /// Beanstalkd can only peek the delayed job with the shortest delay left, so rewritten
+    /// jobs are first put into the holding tube `<name>.rewrite`, then moved back once every
+    /// delayed job has been rewritten. A job is always put before its previous copy is
+    /// deleted, so an interrupted rewrite may duplicate a job but never loses one: jobs
+    /// left in the holding tube are moved back by the next rewrite. Jobs get new ids, and
+    /// only the jobs delayed when the rewrite starts are rewritten. The previously used tube
+    /// is used again afterwards.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let count = conn
+    ///     .rewrite_delayed("emails", |_stats, request| request.priority(0))
+    ///     .await
+    ///     .unwrap();
+    /// }
+    /// ```
+    pub async fn rewrite_delayed<F>(&mut self, name: &str, mut f: F) -> BeanstalkcResult<usize>
+    where
+        F: FnMut(&HashMap<String, String>, PutRequest) -> PutRequest,
+    {
+        let holding = format!("{}{}", name, REWRITE_TUBE_SUFFIX);
+        let previous = self.enter_tube(name).await?;
+        let rewritten = async {
+            let count = self.rewrite_into(name, &holding, &mut f).await?;
+            self.move_jobs(&holding, name).await?;
+            Ok(count)
+        }
+        .await;
+        self.leave_tube(&previous, rewritten).await
+    }
+
+    /// Put the delayed jobs of tube `from` rewritten by `f` into tube `to`, deleting the
+    /// originals.
+    async fn rewrite_into<F>(&mut self, from: &str, to: &str, f: &mut F) -> BeanstalkcResult<usize>
+    where
+        F: FnMut(&HashMap<String, String>, PutRequest) -> PutRequest,
+    {
+        let delayed: u64 = self
+            .stats_tube(from)
+            .await?
+            .get("current-jobs-delayed")
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0);
+
+        let mut count = 0;
+        for _ in 0..delayed {
+            self.use_tube(from).await?;
+            let (id, body) = match self.peek_delayed_opt().await? {
+                Some(job) => (job.id(), job.body().to_vec()),
+                None => break,
+            };
+            if let Some(stats) = self.job_stats_opt(id).await? {
+                let request = f(&stats, current_request(body, &stats));
+                if self.replace(id, to, request).await? {
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Move the ready and delayed jobs of tube `from` into tube `to`, keeping their delay
+    /// left.
+    async fn move_jobs(&mut self, from: &str, to: &str) -> BeanstalkcResult<()> {
+        loop {
+            self.use_tube(from).await?;
+            let head = match self.peek_ready_opt().await? {
+                Some(job) => Some((job.id(), job.body().to_vec())),
+                None => self
+                    .peek_delayed_opt()
+                    .await?
+                    .map(|job| (job.id(), job.body().to_vec())),
+            };
+            let (id, body) = match head {
+                Some(head) => head,
+                None => return Ok(()),
+            };
+            if let Some(stats) = self.job_stats_opt(id).await? {
+                self.replace(id, to, current_request(body, &stats)).await?;
+            }
+        }
+    }
+
+    /// Put `request` into tube `to`, then delete job `id`. If the job was reserved or
+    /// deleted meanwhile, delete the new job instead. Return whether the job was replaced.
+    async fn replace(&mut self, id: u64, to: &str, request: PutRequest) -> BeanstalkcResult<bool> {
+        self.use_tube(to).await?;
+        let copy = self.put_request(&request).await?;
+        match self.delete(id).await {
+            Ok(()) => Ok(true),
+            Err(BeanstalkcError::CommandFailed(_)) => {
+                self.delete(copy).await?;
+                Ok(false)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Return the stats of job `id`, or `None` if it does not exist anymore.
+    async fn job_stats_opt(
+        &mut self,
+        id: u64,
+    ) -> BeanstalkcResult<Option<HashMap<String, String>>> {
+        let resp = self
+            .send(command::stats_job(id).expect(Status::NotFound))
+            .await?;
+        if resp.status == Status::NotFound {
+            return Ok(None);
+        }
+        resp.body_as_map().map(Some)
+    }
+}
+
+/// Return a request putting `body` with the priority, delay left and TTR from `stats`.
+fn current_request(body: Vec<u8>, stats: &HashMap<String, String>) -> PutRequest {
+    let stat = |name: &str| stats.get(name).and_then(|value| value.parse::<u64>().ok());
+    let mut request = PutRequest::new(body);
+    if let Some(priority) = stat("pri") {
+        request = request.priority(priority as u32);
+    }
+    if let Some(delay) = stat("time-left") {
+        request = request.delay(Duration::from_secs(delay));
+    }
+    if let Some(ttr) = stat("ttr") {
+        request = request.ttr(Duration::from_secs(ttr));
+    }
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+
+    #[tokio::test]
+    async fn test_rewrite_delayed() {
+        let server = FakeServer::start().await;
+        let mut conn = server.client().connect().await.unwrap();
+        conn.use_tube("emails").await.unwrap();
+        let delayed = conn
+            .put(
+                b"welcome",
+                5,
+                Duration::from_secs(100),
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        conn.use_tube("reports").await.unwrap();
+
+        let count = conn
+            .rewrite_delayed("emails", |_stats, request| request.priority(0))
+            .await
+            .unwrap();
+        assert_eq!(1, count);
+        assert_eq!(None, server.state(delayed));
+        let rewritten = server.jobs_in("delayed");
+        assert_eq!(1, rewritten.len());
+        let stats = conn.stats_job(rewritten[0]).await.unwrap();
+        assert_eq!("emails", stats["tube"]);
+        assert_eq!("0", stats["pri"]);
+        assert_eq!("60", stats["ttr"]);
+        assert_eq!("reports", conn.using().await.unwrap());
+    }
+
+    #[test]
+    fn test_current_request() {
+        let stats: HashMap<String, String> = vec![("pri", "7"), ("time-left", "30"), ("ttr", "60")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let request = current_request(b"Rust".to_vec(), &stats);
+        assert_eq!(b"Rust", request.body());
+        assert_eq!(Some(7), request.priority);
+        assert_eq!(Some(Duration::from_secs(30)), request.delay);
+        assert_eq!(Some(Duration::from_secs(60)), request.ttr);
+    }
+}
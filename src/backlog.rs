@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::config::DEFAULT_BACKLOG_CHECK_INTERVAL;
+
+/// What to do with a put while the backlog of its tube is over the limit.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BacklogAction {
+    /// Fail the put with `BeanstalkcError::BackpressureExceeded`.
+    #[default]
+    Reject,
+    /// Wait until the backlog is back under the limit, checking it every check interval.
+    Wait,
+}
+
+/// `BacklogLimit` guards puts against the backlog of their tube: once the tube holds
+/// `max_ready` ready jobs, puts are rejected or delayed until consumers catch up.
+///
+/// The `current-jobs-ready` stat of a tube is fetched at most once per check interval, and
+/// the jobs put since are added to it in the meantime.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use std::time::Duration;
+/// use beanstalkc::{BacklogAction, BacklogLimit, Beanstalkc, BeanstalkcError};
+///
+/// let mut conn = Beanstalkc::new()
+///        .put_backlog_limit(
+///            BacklogLimit::new(100_000)
+///                .check_interval(Duration::from_secs(10))
+///                .action(BacklogAction::Reject),
+///        )
+///        .connect()
+///        .await
+///        .unwrap();
+///
+/// match conn.put_default(b"event").await {
+///     Ok(id) => println!("put {}", id),
+///     Err(BeanstalkcError::BackpressureExceeded { tube, ready, .. }) => {
+///         eprintln!("{} is {} jobs behind", tube, ready)
+///     }
+///     Err(err) => panic!("{}", err),
+/// }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BacklogLimit {
+    pub(crate) max_ready: u64,
+    pub(crate) check_interval: Duration,
+    pub(crate) action: BacklogAction,
+}
+
+impl BacklogLimit {
+    /// Reject puts into tubes holding `max_ready` ready jobs or more.
+    pub fn new(max_ready: u64) -> Self {
+        BacklogLimit {
+            max_ready,
+            check_interval: DEFAULT_BACKLOG_CHECK_INTERVAL,
+            action: BacklogAction::default(),
+        }
+    }
+
+    /// Change how often the backlog of a tube is fetched from the server. Default check
+    /// interval is `5s`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero, which would fetch the backlog on every put and poll the
+    /// server without pause while waiting for it to drain.
+    pub fn check_interval(mut self, interval: Duration) -> Self {
+        assert!(
+            !interval.is_zero(),
+            "backlog check interval must be positive"
+        );
+        self.check_interval = interval;
+        self
+    }
+
+    /// Change what happens to puts over the limit. Default action is to reject them.
+    pub fn action(mut self, action: BacklogAction) -> Self {
+        self.action = action;
+        self
+    }
+}
+
+/// Backlog of the tubes jobs are put into, as last fetched and estimated since.
+#[derive(Debug, Default)]
+pub(crate) struct BacklogGuard {
+    limit: Option<BacklogLimit>,
+    tubes: HashMap<String, (Instant, u64)>,
+}
+
+impl BacklogGuard {
+    pub(crate) fn set_limit(&mut self, limit: BacklogLimit) {
+        self.limit = Some(limit);
+        self.tubes.clear();
+    }
+
+    /// Return a guard with the same limit, for a new connection.
+    pub(crate) fn fresh(&self) -> Self {
+        BacklogGuard {
+            limit: self.limit,
+            tubes: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn limit(&self) -> Option<BacklogLimit> {
+        self.limit
+    }
+
+    /// Return the estimated backlog of `tube`, unless it must be fetched again.
+    pub(crate) fn ready(&self, tube: &str) -> Option<u64> {
+        let check_interval = self.limit?.check_interval;
+        self.tubes
+            .get(tube)
+            .filter(|(fetched, _)| fetched.elapsed() < check_interval)
+            .map(|(_, ready)| *ready)
+    }
+
+    /// Record the backlog of `tube` fetched from the server.
+    pub(crate) fn fetched(&mut self, tube: &str, ready: u64) {
+        self.tubes.insert(tube.to_string(), (Instant::now(), ready));
+    }
+
+    /// Count `count` jobs put into `tube` since its backlog was fetched.
+    pub(crate) fn put(&mut self, tube: &str, count: usize) {
+        if let Some((_, ready)) = self.tubes.get_mut(tube) {
+            *ready += count as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backlog_guard() {
+        let mut guard = BacklogGuard::default();
+        guard.fetched("emails", 3);
+        assert_eq!(None, guard.ready("emails"));
+
+        guard.set_limit(BacklogLimit::new(10));
+        assert_eq!(None, guard.ready("emails"));
+        guard.fetched("emails", 3);
+        guard.put("emails", 2);
+        guard.put("reports", 2);
+        assert_eq!(Some(5), guard.ready("emails"));
+        assert_eq!(None, guard.ready("reports"));
+
+        guard.set_limit(BacklogLimit::new(10).check_interval(Duration::from_millis(1)));
+        guard.fetched("emails", 3);
+        std::thread::sleep(Duration::from_millis(2));
+        assert_eq!(None, guard.ready("emails"));
+    }
+
+    #[test]
+    #[should_panic(expected = "backlog check interval must be positive")]
+    fn test_zero_check_interval() {
+        BacklogLimit::new(10).check_interval(Duration::from_secs(0));
+    }
+}
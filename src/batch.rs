@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::error::BeanstalkcResult;
+use crate::job::Job;
+use crate::process::ReservedJob;
+use crate::Beanstalkc;
+
+/// `JobBatch` holds a group of jobs reserved together by
+/// [`Beanstalkc::reserve_many`](crate::Beanstalkc::reserve_many).
+///
+/// A job taken out with [`JobBatch::job`] is finalized on its own, and left out of the bulk
+/// operations of the batch. Bulk operations go on past the jobs which fail, and return the
+/// first error once every job has been handled.
+#[derive(Debug)]
+pub struct JobBatch<'a> {
+    conn: &'a mut Beanstalkc,
+    jobs: Vec<ReservedJob>,
+    taken: HashSet<u64>,
+}
+
+impl<'a> JobBatch<'a> {
+    pub(crate) fn new(conn: &'a mut Beanstalkc, jobs: Vec<ReservedJob>) -> Self {
+        JobBatch {
+            conn,
+            jobs,
+            taken: HashSet::new(),
+        }
+    }
+
+    /// Return the reserved jobs.
+    pub fn jobs(&self) -> &[ReservedJob] {
+        &self.jobs[..]
+    }
+
+    /// Return the number of reserved jobs.
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Return whether no job was reserved.
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Return the job at `index` for finalizing it individually. The job is left out of
+    /// the bulk operations of the batch from then on.
+    pub fn job(&mut self, index: usize) -> Option<Job<'_>> {
+        let job = self.jobs.get(index)?;
+        self.taken.insert(job.id());
+        Some(Job::new(self.conn, job.id(), job.body().to_vec(), true))
+    }
+
+    /// Return the ids of the jobs which were not taken out of the batch.
+    fn remaining(&self) -> Vec<u64> {
+        self.jobs
+            .iter()
+            .map(|job| job.id())
+            .filter(|id| !self.taken.contains(id))
+            .collect()
+    }
+
+    /// Delete all jobs of this batch.
+    pub async fn delete_all(&mut self) -> BeanstalkcResult<()> {
+        let mut result = Ok(());
+        for id in self.remaining() {
+            result = result.and(self.conn.delete(id).await);
+        }
+        result
+    }
+
+    /// Release all jobs of this batch with their current priority and the given delay.
+    pub async fn release_all(&mut self, delay: Duration) -> BeanstalkcResult<()> {
+        let mut result = Ok(());
+        for id in self.remaining() {
+            let mut job = Job::new(self.conn, id, vec![], true);
+            let priority = job.priority().await;
+            result = result.and(job.release(priority, delay).await);
+        }
+        result
+    }
+
+    /// Bury all jobs of this batch with their current priority.
+    pub async fn bury_all(&mut self) -> BeanstalkcResult<()> {
+        let mut result = Ok(());
+        for id in self.remaining() {
+            let buried = Job::new(self.conn, id, vec![], true).bury_default().await;
+            result = result.and(buried);
+        }
+        result
+    }
+
+    /// Touch all jobs of this batch, requesting more time to work on them.
+    pub async fn touch_all(&mut self) -> BeanstalkcResult<()> {
+        let mut result = Ok(());
+        for id in self.remaining() {
+            result = result.and(self.conn.touch(id).await);
+        }
+        result
+    }
+
+    /// Return the shortest time left before any job of this batch exceeds its TTR.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut batch = conn.reserve_many(100, Duration::from_secs(10)).await.unwrap();
+    /// // Insert jobs in bulk...
+    /// if batch.time_left().await.unwrap() < Duration::from_secs(5) {
+    ///     batch.touch_all().await.unwrap();
+    /// }
+    /// batch.delete_all().await.unwrap();
+    /// }
+    /// ```
+    pub async fn time_left(&mut self) -> BeanstalkcResult<Duration> {
+        let mut time_left = None;
+        for id in self.remaining() {
+            let stats = self.conn.stats_job(id).await?;
+            let secs: u64 = stats
+                .get("time-left")
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(0);
+            time_left = Some(time_left.map_or(secs, |t: u64| t.min(secs)));
+        }
+        Ok(Duration::from_secs(time_left.unwrap_or(0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fake::FakeServer;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_delete_all_skips_taken() {
+        let server = FakeServer::start().await;
+        let ids: Vec<u64> = (0..3).map(|_| server.put("default", b"job")).collect();
+        let mut conn = server.client().connect().await.unwrap();
+
+        let mut batch = conn.reserve_many(3, Duration::from_secs(0)).await.unwrap();
+        batch.job(1).unwrap().bury_default().await.unwrap();
+        batch.delete_all().await.unwrap();
+
+        assert_eq!(vec![ids[1]], server.jobs_in("buried"));
+        assert_eq!(None, server.state(ids[0]));
+        assert_eq!(None, server.state(ids[2]));
+    }
+}
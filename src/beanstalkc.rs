@@ -1,15 +1,44 @@
-use std::collections::HashMap;
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-use tokio::io::BufReader;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
 use tokio::net::TcpStream;
+use tokio::sync::watch;
 
+use crate::backlog::{BacklogAction, BacklogGuard, BacklogLimit};
+use crate::batch::JobBatch;
+use crate::circuit::CircuitBreaker;
 use crate::command;
+use crate::command::Status;
 use crate::config::*;
+use crate::dead_letter::DeadLetterPolicy;
+use crate::dedup::{DedupStore, MemoryDedupStore};
 use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::handshake::Handshake;
+use crate::heartbeat::Heartbeat;
+use crate::interceptor::CommandInterceptor;
 use crate::job::Job;
-use crate::request::Request;
-use crate::response::Response;
+use crate::latency::{LatencyRecorder, LatencyReport};
+use crate::listener::ConnectionListener;
+use crate::logging;
+use crate::long_delay::round_up;
+use crate::process::{Outcome, OversizeAction, PanicAction, ReservedJob};
+use crate::put::PutRequest;
+use crate::rate_limit::{PutLimiter, RateLimit};
+use crate::request::{Exchange, Request};
+use crate::resolver::Resolver;
+use crate::response::{desync, Response};
+use crate::retry::RetryPolicy;
+use crate::supervisor::ConnectionState;
+use crate::topology::RetryTopology;
+use crate::tube::Tube;
+use crate::tube_set::TubeSet;
 
 /// `Beanstalkc` provides beanstalkd client operations.
 #[derive(Debug)]
@@ -17,6 +46,45 @@ pub struct Beanstalkc {
     host: String,
     port: u16,
     connection_timeout: Option<Duration>,
+    cache_dns: bool,
+    resolved_addrs: Vec<SocketAddr>,
+    resolver: Option<Resolver>,
+    handshake: Option<Handshake>,
+    dead_letter_policy: Option<DeadLetterPolicy>,
+    retry_topology: Option<RetryTopology>,
+    panic_action: PanicAction,
+    touch_on_deadline_soon: bool,
+    assume_utf8: bool,
+    heartbeat: Option<Heartbeat>,
+    max_body_size: Option<usize>,
+    oversize_action: OversizeAction,
+    reserved_jobs: HashSet<u64>,
+    abandoned_jobs: Vec<u64>,
+    interceptors: Vec<Arc<dyn CommandInterceptor>>,
+    latencies: Option<Arc<LatencyRecorder>>,
+    listeners: Vec<Arc<dyn ConnectionListener>>,
+    debug_protocol: bool,
+    drain_on_desync: bool,
+    resync: bool,
+    exchange: Exchange,
+    retry_policy: Option<RetryPolicy>,
+    supervisor: Option<RetryPolicy>,
+    state: Arc<watch::Sender<ConnectionState>>,
+    circuit_breaker: Option<CircuitBreaker>,
+    idle_timeout: Option<Duration>,
+    default_priority: u32,
+    default_delay: Duration,
+    default_ttr: Duration,
+    put_limiter: PutLimiter,
+    backlog: BacklogGuard,
+    dedup_store: Arc<dyn DedupStore>,
+    dedup_ttl: Duration,
+    namespace: String,
+    default_tube: Option<String>,
+    watch_tubes: Option<Vec<String>>,
+    reserve_connections: usize,
+    last_used: Option<Instant>,
+    tube_state: TubeState,
     stream: Option<BufReader<TcpStream>>,
 }
 
@@ -28,6 +96,45 @@ impl Beanstalkc {
             host: DEFAULT_HOST.to_string(),
             port: DEFAULT_PORT,
             connection_timeout: DEFAULT_CONNECTION_TIMEOUT,
+            cache_dns: false,
+            resolved_addrs: vec![],
+            resolver: None,
+            handshake: None,
+            dead_letter_policy: None,
+            retry_topology: None,
+            panic_action: PanicAction::default(),
+            touch_on_deadline_soon: false,
+            assume_utf8: false,
+            heartbeat: None,
+            max_body_size: None,
+            oversize_action: OversizeAction::default(),
+            reserved_jobs: HashSet::new(),
+            abandoned_jobs: vec![],
+            interceptors: vec![],
+            latencies: None,
+            listeners: vec![],
+            debug_protocol: false,
+            drain_on_desync: false,
+            resync: false,
+            exchange: Exchange::default(),
+            retry_policy: None,
+            supervisor: None,
+            state: Arc::new(watch::channel(ConnectionState::default()).0),
+            circuit_breaker: None,
+            idle_timeout: None,
+            default_priority: DEFAULT_JOB_PRIORITY,
+            default_delay: DEFAULT_JOB_DELAY,
+            default_ttr: DEFAULT_JOB_TTR,
+            put_limiter: PutLimiter::default(),
+            backlog: BacklogGuard::default(),
+            dedup_store: Arc::new(MemoryDedupStore::new()),
+            dedup_ttl: DEFAULT_DEDUP_TTL,
+            namespace: String::new(),
+            default_tube: None,
+            watch_tubes: None,
+            reserve_connections: 0,
+            last_used: None,
+            tube_state: TubeState::default(),
             stream: None,
         }
     }
@@ -88,11 +195,1516 @@ impl Beanstalkc {
         self
     }
 
+    /// Reuse the addresses resolved by the first successful connection for every reconnect,
+    /// instead of resolving the host again each time. Disabled by default, so that a
+    /// reconnect follows DNS changes such as a failover.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .host("beanstalkd.internal")
+    ///        .cache_dns(true)
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn cache_dns(mut self, enabled: bool) -> Self {
+        self.cache_dns = enabled;
+        self
+    }
+
+    /// Use an async `resolver` to look up the server addresses on every connect and
+    /// reconnect instead of resolving `host:port`, for instance to discover beanstalkd
+    /// through Consul or Kubernetes endpoints. Addresses are tried in order.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::net::SocketAddr;
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .resolver(|| async {
+    ///            // Query the service registry here...
+    ///            Ok(vec![SocketAddr::from(([10, 0, 0, 7], 11300))])
+    ///        })
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn resolver<F, Fut>(mut self, resolver: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<Vec<SocketAddr>>> + Send + 'static,
+    {
+        self.resolver = Some(Resolver::new(resolver));
+        self
+    }
+
+    /// Run `handshake` on the raw stream of every connect and reconnect, before any command
+    /// is sent, for instance to authenticate to a proxy in front of beanstalkd. The
+    /// handshake returns the stream to use, or an error failing the connect.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use tokio::io::AsyncWriteExt;
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .on_connected(|mut stream| async move {
+    ///            stream.write_all(b"AUTH secret\r\n").await?;
+    ///            Ok(stream)
+    ///        })
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn on_connected<F, Fut>(mut self, handshake: F) -> Self
+    where
+        F: Fn(TcpStream) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<TcpStream>> + Send + 'static,
+    {
+        self.handshake = Some(Handshake::new(handshake));
+        self
+    }
+
+    /// Set the dead-letter policy applied by [`Job::fail`](crate::Job::fail).
+    /// Failed jobs are simply released when no policy is configured.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, DeadLetterPolicy};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .dead_letter_policy(DeadLetterPolicy::move_after(3, "jobs.dead"))
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn dead_letter_policy(mut self, policy: DeadLetterPolicy) -> Self {
+        self.dead_letter_policy = Some(policy);
+        self
+    }
+
+    /// Return the configured dead-letter policy.
+    pub(crate) fn get_dead_letter_policy(&self) -> Option<&DeadLetterPolicy> {
+        self.dead_letter_policy.as_ref()
+    }
+
+    /// Route the jobs failed through [`Job::fail`] in the tubes of `topology` to their next
+    /// retry tube, and watch the whole family as soon as the client connects, along with
+    /// the tubes set by [`Beanstalkc::watch_tubes`]. Jobs reserved from other tubes are
+    /// still handled by the dead-letter policy.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::{Beanstalkc, DeadLetterAction, RetryTopology};
+    ///
+    /// let topology = RetryTopology::new(
+    ///     "jobs",
+    ///     &[Duration::from_secs(60), Duration::from_secs(600)],
+    /// )
+    /// .exhausted(DeadLetterAction::Move("jobs.dead".to_string()));
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .retry_topology(topology)
+    ///        .connect().await
+    ///        .unwrap();
+    ///
+    /// let mut job = conn.reserve().await.unwrap();
+    /// job.fail().await.unwrap();
+    /// }
+    /// ```
+    pub fn retry_topology(mut self, topology: RetryTopology) -> Self {
+        let watched = self.watch_tubes.take().unwrap_or_default();
+        self.retry_topology = Some(topology);
+        self.watch_tubes(watched)
+    }
+
+    /// Return the configured retry topology.
+    pub(crate) fn get_retry_topology(&self) -> Option<&RetryTopology> {
+        self.retry_topology.as_ref()
+    }
+
+    /// Set how [`Job::run`] and [`Beanstalkc::process`] finalize a job whose handler panics.
+    /// Default panic action is `PanicAction::Fail`.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, PanicAction};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .panic_action(PanicAction::Bury)
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn panic_action(mut self, action: PanicAction) -> Self {
+        self.panic_action = action;
+        self
+    }
+
+    /// Return the configured panic action.
+    pub(crate) fn get_panic_action(&self) -> PanicAction {
+        self.panic_action
+    }
+
+    /// Skip job bodies larger than `size` bytes instead of buffering them, so that a job
+    /// close to the server `max-job-size` cannot exhaust the memory of a small worker. The
+    /// skipped body is drained from the connection, and the command fails with
+    /// `BeanstalkcError::BodyTooLarge`. A job reserved this way is finalized with `action`
+    /// first; [`Worker`](crate::Worker) and [`Beanstalkc::reserve_many`] then skip it and
+    /// go on with the next job. Bodies streamed with [`Beanstalkc::reserve_into`] or read
+    /// by [`Beanstalkc::reserve_buffered`] are limited too. Disabled by default.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, BeanstalkcError, OversizeAction};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .max_body_size(1 << 20, OversizeAction::Bury)
+    ///        .connect().await
+    ///        .unwrap();
+    ///
+    /// match conn.reserve().await {
+    ///     Ok(mut job) => job.delete().await.unwrap(),
+    ///     Err(BeanstalkcError::BodyTooLarge { id, size }) => eprintln!("buried {} of {} bytes", id, size),
+    ///     Err(err) => panic!("{}", err),
+    /// }
+    /// }
+    /// ```
+    pub fn max_body_size(mut self, size: usize, action: OversizeAction) -> Self {
+        self.max_body_size = Some(size);
+        self.oversize_action = action;
+        self
+    }
+
+    /// Return the configured oversize action.
+    pub(crate) fn get_oversize_action(&self) -> OversizeAction {
+        self.oversize_action
+    }
+
+    /// Touch the jobs reserved by this client and retry the reserve once when a reserve
+    /// replies with `DEADLINE_SOON`, instead of returning an error. Disabled by default.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .touch_on_deadline_soon(true)
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn touch_on_deadline_soon(mut self, enabled: bool) -> Self {
+        self.touch_on_deadline_soon = enabled;
+        self
+    }
+
+    /// Validate the body of every job reserved as UTF-8, for queues whose payloads are
+    /// guaranteed to be text: a job with an invalid body is buried, and the reserve fails
+    /// with `BeanstalkcError::NonUtf8Body` instead of handing the job to the worker.
+    /// [`Worker`](crate::Worker), [`Beanstalkc::reserve_many`] and
+    /// [`Beanstalkc::pump_into`] skip such jobs and go on with the next one. Bodies
+    /// streamed with [`Beanstalkc::reserve_into`] are not validated. Disabled by default.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, BeanstalkcError};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .assume_utf8(true)
+    ///        .connect().await
+    ///        .unwrap();
+    ///
+    /// match conn.reserve().await {
+    ///     Ok(mut job) => {
+    ///         println!("{}", job.body_lossy());
+    ///         job.delete().await.unwrap();
+    ///     }
+    ///     Err(BeanstalkcError::NonUtf8Body { id, error }) => eprintln!("buried {}: {}", id, error),
+    ///     Err(err) => panic!("{}", err),
+    /// }
+    /// }
+    /// ```
+    pub fn assume_utf8(mut self, enabled: bool) -> Self {
+        self.assume_utf8 = enabled;
+        self
+    }
+
+    /// Block at most `interval` on the wire per reserve attempt, and call `beat` between
+    /// attempts, so that a health check can tell a worker waiting on an empty queue from a
+    /// stuck one. Reserves without a timeout, or with a timeout longer than `interval`, are
+    /// then sent as a loop of `reserve-with-timeout`. `interval` is rounded up to whole
+    /// seconds, the precision of beanstalkd timeouts. Disabled by default.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let beats = Arc::new(AtomicU64::new(0));
+    /// let counter = Arc::clone(&beats);
+    /// let mut conn = Beanstalkc::new()
+    ///        .reserve_heartbeat(Duration::from_secs(5), move || {
+    ///            counter.fetch_add(1, Ordering::Relaxed);
+    ///        })
+    ///        .connect().await
+    ///        .unwrap();
+    ///
+    /// let mut job = conn.reserve().await.unwrap();
+    /// job.delete().await.unwrap();
+    /// }
+    /// ```
+    pub fn reserve_heartbeat<F>(mut self, interval: Duration, beat: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let interval = Duration::from_secs(interval.as_secs_f64().ceil().max(1.0) as u64);
+        self.heartbeat = Some(Heartbeat::new(interval, beat));
+        self
+    }
+
+    /// Register a [`CommandInterceptor`] called around every command sent by this client.
+    /// Interceptors are called in registration order.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, CommandInterceptor};
+    ///
+    /// #[derive(Debug)]
+    /// struct Noop;
+    /// impl CommandInterceptor for Noop {}
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .interceptor(Noop)
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn interceptor<I: CommandInterceptor + 'static>(mut self, interceptor: I) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Record the latency of every command answered by the server in an in-process
+    /// histogram per command kind, retrieved with [`Beanstalkc::latency_report`]. Connections
+    /// duplicated from this one, e.g. the reserve connections of a `SharedClient`, record
+    /// into the same histograms.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, CommandKind};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .record_latencies()
+    ///        .connect().await
+    ///        .unwrap();
+    ///
+    /// conn.put_default(b"Rust").await.unwrap();
+    /// let report = conn.latency_report().unwrap();
+    /// let put = report.get(CommandKind::Put).unwrap();
+    /// println!("put p50={:?} p99={:?}", put.p50(), put.p99());
+    /// }
+    /// ```
+    pub fn record_latencies(mut self) -> Self {
+        if self.latencies.is_none() {
+            let recorder = Arc::new(LatencyRecorder::default());
+            self.interceptors.push(recorder.clone());
+            self.latencies = Some(recorder);
+        }
+        self
+    }
+
+    /// Return the latency histograms recorded so far, or `None` unless enabled with
+    /// [`Beanstalkc::record_latencies`].
+    pub fn latency_report(&self) -> Option<LatencyReport> {
+        self.latencies.as_ref().map(|recorder| recorder.report())
+    }
+
+    /// Register a [`ConnectionListener`] notified when the connection is established, lost
+    /// or being replaced. Listeners are called in registration order.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, ConnectionListener};
+    ///
+    /// #[derive(Debug)]
+    /// struct Noop;
+    /// impl ConnectionListener for Noop {}
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .connection_listener(Noop)
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn connection_listener<L: ConnectionListener + 'static>(mut self, listener: L) -> Self {
+        self.listeners.push(Arc::new(listener));
+        self
+    }
+
+    /// Log every outbound command line and inbound response line with byte counts and
+    /// truncated, escaped bodies. Logs are emitted at debug level under the target
+    /// `beanstalkc::wire`, through `tracing` with the `tracing` feature, or through `log`
+    /// with the `log` feature otherwise. Disabled by default.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .debug_protocol(true)
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn debug_protocol(mut self, enabled: bool) -> Self {
+        self.debug_protocol = enabled;
+        self
+    }
+
+    /// Recover from a malformed response by discarding the bytes received after it, rather
+    /// than reconnecting, which releases the reserved jobs. The connection is kept only if
+    /// the discarded bytes end with a line break; the command fails with
+    /// `BeanstalkcError::ProtocolDesync` either way. Disabled by default.
+    ///
+    /// Without it, or when draining fails, the connection is closed and the next command
+    /// reconnects first.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .drain_on_desync(true)
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn drain_on_desync(mut self, enabled: bool) -> Self {
+        self.drain_on_desync = enabled;
+        self
+    }
+
+    /// Reconnect as soon as the connection breaks, trying up to the maximum attempts of
+    /// `policy` with its backoff and jitter, and restore the used and watched tubes. A
+    /// [`SharedClient`](crate::SharedClient) monitors its connection in the background,
+    /// pinging it every second while idle, and reconnects as soon as a ping or a command
+    /// reveals that it broke. Other clients only notice a broken connection when using it,
+    /// and reconnect before their next command, which fails if every attempt failed.
+    /// Observe the reconnections with [`Beanstalkc::watch_state`].
+    ///
+    /// Unlike [`Beanstalkc::retry_policy`], failed commands are not retried.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::{Beanstalkc, RetryPolicy};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .supervisor(RetryPolicy::new(10).backoff(Duration::from_millis(200), Duration::from_secs(30)))
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn supervisor(mut self, policy: RetryPolicy) -> Self {
+        self.supervisor = Some(policy);
+        self
+    }
+
+    /// Return a channel receiving the state of the connection whenever it changes.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, ConnectionState};
+    ///
+    /// let conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut state = conn.watch_state();
+    /// tokio::spawn(async move {
+    ///     while state.changed().await.is_ok() {
+    ///         if *state.borrow() == ConnectionState::Failed {
+    ///             eprintln!("beanstalkd is unreachable");
+    ///         }
+    ///     }
+    /// });
+    /// }
+    /// ```
+    pub fn watch_state(&self) -> watch::Receiver<ConnectionState> {
+        self.state.subscribe()
+    }
+
+    /// Retry idempotent commands failing with a transient I/O error according to `policy`,
+    /// reconnecting before each retry. See [`RetryPolicy`] for which commands are retried.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, RetryPolicy};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .retry_policy(RetryPolicy::new(3))
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Fail commands fast with `BeanstalkcError::CircuitOpen` while the server looks down.
+    /// See [`CircuitBreaker`] for when the circuit opens and closes.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::{Beanstalkc, CircuitBreaker};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .circuit_breaker(CircuitBreaker::new(5, Duration::from_secs(10)))
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(breaker);
+        self
+    }
+
+    /// Return whether the circuit breaker currently rejects commands.
+    pub fn is_circuit_open(&self) -> bool {
+        self.circuit_breaker
+            .as_ref()
+            .is_some_and(CircuitBreaker::is_open)
+    }
+
+    /// Check a connection which has been idle for longer than `timeout` before sending the
+    /// next command, replacing it with a new one if it does not answer a ping in time.
+    /// This recovers from connections silently dropped by firewalls, which would otherwise
+    /// make the next command hang. Disabled by default.
+    ///
+    /// The new connection uses and watches the same tubes as the dropped one.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .idle_timeout(Some(Duration::from_secs(300)))
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Change the priority of the jobs put with [`Beanstalkc::put_default`] or a
+    /// [`PutRequest`] without a priority, and released or buried with `release_default` or
+    /// `bury_default`. Defaults to `DEFAULT_JOB_PRIORITY`.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .default_priority(1024)
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn default_priority(mut self, priority: u32) -> Self {
+        self.default_priority = priority;
+        self
+    }
+
+    /// Change the delay of the jobs put with [`Beanstalkc::put_default`] or a [`PutRequest`]
+    /// without a delay, and released with `release_default`. Defaults to `0s`.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .default_delay(Duration::from_secs(5))
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn default_delay(mut self, delay: Duration) -> Self {
+        self.default_delay = delay;
+        self
+    }
+
+    /// Change the TTR of the jobs put with [`Beanstalkc::put_default`] or a [`PutRequest`]
+    /// without a TTR. Defaults to `2m`.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .default_ttr(Duration::from_secs(300))
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn default_ttr(mut self, ttr: Duration) -> Self {
+        self.default_ttr = ttr;
+        self
+    }
+
+    /// Limit the rate of puts into every tube without a limit of its own, see [`RateLimit`].
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, RateLimit};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .put_rate_limit(RateLimit::new(1000.0, 100))
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn put_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.put_limiter.set_default(limit);
+        self
+    }
+
+    /// Limit the rate of puts into the tube `name`, see [`RateLimit`].
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, RateLimit};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .tube_put_rate_limit("backfill", RateLimit::new(50.0, 10))
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn tube_put_rate_limit(mut self, name: &str, limit: RateLimit) -> Self {
+        self.put_limiter.set_tube(name, limit);
+        self
+    }
+
+    /// Guard puts against the backlog of the tube they go into, see [`BacklogLimit`].
+    /// Puts are checked after the rate limits.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{BacklogAction, BacklogLimit, Beanstalkc};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .put_backlog_limit(BacklogLimit::new(50_000).action(BacklogAction::Wait))
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn put_backlog_limit(mut self, limit: BacklogLimit) -> Self {
+        self.backlog.set_limit(limit);
+        self
+    }
+
+    /// Set the store remembering the keys of the jobs put with [`Beanstalkc::put_unique`],
+    /// and for how long a key prevents putting the same job again. Defaults to a
+    /// [`MemoryDedupStore`] and a TTL of `5m`.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use beanstalkc::{Beanstalkc, MemoryDedupStore};
+    ///
+    /// let store = Arc::new(MemoryDedupStore::new());
+    /// let mut conn = Beanstalkc::new()
+    ///        .dedup_store(store, Duration::from_secs(3600))
+    ///        .connect()
+    ///        .await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn dedup_store(mut self, store: Arc<dyn DedupStore>, ttl: Duration) -> Self {
+        self.dedup_store = store;
+        self.dedup_ttl = ttl;
+        self
+    }
+
+    /// Prepend `prefix` to every tube name, so several environments can share one server.
+    ///
+    /// Tube names passed to the client are namespaced before being sent, and tube names
+    /// returned by `tubes`, `using` and `watching` are stripped, listing only the tubes of
+    /// the namespace. The connection uses and watches the namespaced `default` tube instead
+    /// of `default`. Stats maps returned by the server are left as is.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .namespace("staging.")
+    ///        .connect()
+    ///        .await
+    ///        .unwrap();
+    ///
+    /// // Puts into the `staging.emails` tube.
+    /// conn.use_tube("emails").await.unwrap();
+    /// assert_eq!("emails", conn.using().await.unwrap());
+    /// }
+    /// ```
+    pub fn namespace(mut self, prefix: &str) -> Self {
+        self.namespace = prefix.to_string();
+        self
+    }
+
+    /// Use tube `name` as soon as the client connects, instead of `default`. Like the tubes
+    /// selected afterwards, it is used again after every reconnect.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .default_tube("emails")
+    ///        .watch_tubes(vec!["emails", "retries"])
+    ///        .connect()
+    ///        .await
+    ///        .unwrap();
+    ///
+    /// conn.put_default(b"Rust").await.unwrap();
+    /// let mut job = conn.reserve().await.unwrap();
+    /// job.delete().await.unwrap();
+    /// }
+    /// ```
+    pub fn default_tube(mut self, name: &str) -> Self {
+        self.default_tube = Some(name.to_string());
+        self
+    }
+
+    /// Watch `tubes`, and only them, as soon as the client connects, instead of `default`.
+    /// Like the tubes watched afterwards, they are watched again after every reconnect. An
+    /// empty list keeps watching `default`, since a connection must watch a tube. The tubes
+    /// of the [`Beanstalkc::retry_topology`] are watched as well, whichever is set first.
+    pub fn watch_tubes<I, S>(mut self, tubes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut tubes: TubeSet = tubes.into_iter().map(|t| t.as_ref().to_string()).collect();
+        if let Some(topology) = &self.retry_topology {
+            for tube in topology.tubes() {
+                tubes.insert(&tube);
+            }
+        }
+        self.watch_tubes = if tubes.is_empty() {
+            None
+        } else {
+            Some(tubes.into_vec())
+        };
+        self
+    }
+
+    /// Return the tubes to select on a new connection: the tubes set on the builder, or the
+    /// `default` tube.
+    fn initial_tube_state(&self) -> TubeState {
+        let using = match &self.default_tube {
+            Some(name) => Some(self.namespaced(name)),
+            None => self.namespaced_default(),
+        };
+        let watching = match &self.watch_tubes {
+            Some(names) => Some(names.iter().map(|name| self.namespaced(name)).collect()),
+            None => self.namespaced_default().map(|name| vec![name]),
+        };
+        TubeState { using, watching }
+    }
+
+    /// Return the namespaced `default` tube, if a namespace is configured.
+    fn namespaced_default(&self) -> Option<String> {
+        if self.namespace.is_empty() {
+            None
+        } else {
+            Some(self.namespaced(DEFAULT_TUBE))
+        }
+    }
+
+    /// Prepend the namespace to tube `name`.
+    fn namespaced(&self, name: &str) -> String {
+        format!("{}{}", self.namespace, name)
+    }
+
+    /// Strip the namespace from tube `name`.
+    pub(crate) fn strip_namespace<'a>(&self, name: &'a str) -> &'a str {
+        name.strip_prefix(self.namespace.as_str()).unwrap_or(name)
+    }
+
+    /// Strip the namespace from tube names, dropping the tubes outside of it.
+    fn strip_namespaces(&self, names: Vec<String>) -> Vec<String> {
+        names
+            .into_iter()
+            .filter_map(|name| {
+                name.strip_prefix(self.namespace.as_str())
+                    .map(str::to_string)
+            })
+            .collect()
+    }
+
+    /// Give a [`SharedClient`](crate::SharedClient) created from this client `count`
+    /// dedicated connections reserving jobs, so reserving never blocks the other commands.
+    /// The reserve connections watch the tubes watched by this client at that time.
+    /// Defaults to none.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, SharedClient};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .reserve_connections(4)
+    ///        .connect().await
+    ///        .unwrap();
+    /// conn.watch("jobs").await.unwrap();
+    ///
+    /// let client = SharedClient::new(conn);
+    /// }
+    /// ```
+    pub fn reserve_connections(mut self, count: usize) -> Self {
+        self.reserve_connections = count;
+        self
+    }
+
+    /// Return the number of dedicated reserve connections to open.
+    pub(crate) fn get_reserve_connections(&self) -> usize {
+        self.reserve_connections
+    }
+
+    /// Return the configured `host:port` of the server.
+    pub(crate) fn get_addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// Return an unconnected client with the same configuration, watching the same tubes.
+    pub(crate) fn duplicate(&self) -> Beanstalkc {
+        Beanstalkc {
+            host: self.host.clone(),
+            port: self.port,
+            connection_timeout: self.connection_timeout,
+            cache_dns: self.cache_dns,
+            resolved_addrs: self.resolved_addrs.clone(),
+            resolver: self.resolver.clone(),
+            handshake: self.handshake.clone(),
+            dead_letter_policy: self.dead_letter_policy.clone(),
+            retry_topology: self.retry_topology.clone(),
+            panic_action: self.panic_action,
+            touch_on_deadline_soon: self.touch_on_deadline_soon,
+            assume_utf8: self.assume_utf8,
+            heartbeat: self.heartbeat.clone(),
+            max_body_size: self.max_body_size,
+            oversize_action: self.oversize_action,
+            reserved_jobs: HashSet::new(),
+            abandoned_jobs: vec![],
+            interceptors: self.interceptors.clone(),
+            latencies: self.latencies.clone(),
+            listeners: self.listeners.clone(),
+            debug_protocol: self.debug_protocol,
+            drain_on_desync: self.drain_on_desync,
+            resync: false,
+            exchange: Exchange::default(),
+            retry_policy: self.retry_policy.clone(),
+            supervisor: self.supervisor.clone(),
+            state: Arc::new(watch::channel(ConnectionState::default()).0),
+            circuit_breaker: self.circuit_breaker.clone(),
+            idle_timeout: self.idle_timeout,
+            default_priority: self.default_priority,
+            default_delay: self.default_delay,
+            default_ttr: self.default_ttr,
+            put_limiter: PutLimiter::default(),
+            backlog: self.backlog.fresh(),
+            dedup_store: Arc::clone(&self.dedup_store),
+            dedup_ttl: self.dedup_ttl,
+            namespace: self.namespace.clone(),
+            default_tube: self.default_tube.clone(),
+            watch_tubes: self.watch_tubes.clone(),
+            reserve_connections: 0,
+            last_used: None,
+            tube_state: TubeState {
+                using: self.initial_tube_state().using,
+                watching: self.tube_state.watching.clone(),
+            },
+            stream: None,
+        }
+    }
+
     /// Connect to a running beanstal.awaitkd server.
     ///
-    /// # Examples
+    /// # Examples
+    ///
+    /// Basic usage
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    /// }
+    /// ```
+    ///
+    /// With custom configurations
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .host("127.0.0.1")
+    ///        .port(11300)
+    ///        .connection_timeout(Some(Duration::from_secs(5)))
+    ///        .connect().await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub async fn connect(mut self) -> BeanstalkcResult<Self> {
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker.check()?;
+        }
+        let connected = self.connect_initial().await;
+        self.record_guard(&connected);
+        connected.map(|()| self)
+    }
+
+    /// Connect, then select the tubes configured on the builder.
+    async fn connect_initial(&mut self) -> BeanstalkcResult<()> {
+        let addr = format!("{}:{}", self.host, self.port);
+        // let tcp_stream = match self.connection_timeout {
+        //     Some(timeout) => {
+        //         let addresses: Vec<_> = addr
+        //             .to_socket_addrs()
+        //             .unwrap_or_else(|_| panic!("failed to parse address: {}", addr))
+        //             .filter(|x| x.is_ipv4())
+        //             .collect();
+        //         // FIXME: maybe we should try every possible addresses?
+        //         TcpStream::connect_timeout(&addresses.first().unwrap(), timeout)?
+        //     }
+        //     None => TcpStream::connect(&addr).await?,
+        // };
+        let tcp_stream = self.open_stream(&addr).await?;
+        let addr = tcp_stream.peer_addr()?.to_string();
+        self.stream = Some(BufReader::new(tcp_stream));
+        self.resync = false;
+        self.exchange = Exchange::default();
+        self.tube_state = self.initial_tube_state();
+        self.restore_tubes().await?;
+        self.last_used = Some(Instant::now());
+        self.state.send_replace(ConnectionState::Connected);
+        for listener in &self.listeners {
+            listener.on_connect(&addr);
+        }
+        logging::connected(&addr);
+        Ok(())
+    }
+
+    /// Replace a broken connection with a new one, then select the tubes which were used
+    /// and watched by the previous connection again.
+    async fn reopen(&mut self, attempt: u32) -> BeanstalkcResult<()> {
+        self.disconnected(None);
+        self.reserved_jobs.clear();
+        self.abandoned_jobs.clear();
+        self.state
+            .send_replace(ConnectionState::Reconnecting { attempt });
+        for listener in &self.listeners {
+            listener.on_reconnect_attempt(attempt);
+        }
+        logging::reconnecting(attempt);
+        self.open().await
+    }
+
+    /// Replace a lost connection according to the supervisor policy, if any.
+    pub(crate) async fn supervise(&mut self) -> BeanstalkcResult<()> {
+        let policy = match &self.supervisor {
+            Some(policy) if self.stream.is_none() => policy.clone(),
+            _ => return Ok(()),
+        };
+
+        let mut attempt = 1;
+        loop {
+            match self.reopen(attempt).await {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt < policy.max_attempts && policy.is_retryable(&err) => {
+                    tokio::time::sleep(policy.delay(attempt)).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    logging::reconnect_failed(&err);
+                    self.state.send_replace(ConnectionState::Failed);
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// Return the tube used by the client.
+    pub(crate) fn current_tube(&self) -> &str {
+        let tube = self.tube_state.using.as_deref().unwrap_or(DEFAULT_TUBE);
+        self.strip_namespace(tube)
+    }
+
+    /// Return whether the client holds a connection.
+    pub(crate) fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Return whether the client reconnects according to a supervisor policy.
+    pub(crate) fn is_supervised(&self) -> bool {
+        self.supervisor.is_some()
+    }
+
+    /// Ping a connection idle for `interval` or more, then replace it according to the
+    /// supervisor policy if it is broken.
+    pub(crate) async fn monitor(&mut self, interval: Duration) {
+        let idle = match self.last_used {
+            Some(last_used) => last_used.elapsed() >= interval,
+            None => true,
+        };
+        if self.stream.is_some() && idle {
+            let ping = command::using();
+            if tokio::time::timeout(IDLE_PING_TIMEOUT, self.write_command(&ping))
+                .await
+                .is_err()
+            {
+                self.connection_failed(&BeanstalkcError::ConnectionError(
+                    "connection did not answer ping".to_string(),
+                ));
+            }
+        }
+        let _ = self.supervise().await;
+    }
+
+    /// Connect, then select the used and watched tubes recorded by this client. Fail fast
+    /// while the circuit breaker is open, and record the result into it.
+    pub(crate) async fn open(&mut self) -> BeanstalkcResult<()> {
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker.check()?;
+        }
+        let opened = self.open_connection().await;
+        self.record_guard(&opened);
+        opened
+    }
+
+    /// Connect, then select the used and watched tubes recorded by this client.
+    async fn open_connection(&mut self) -> BeanstalkcResult<()> {
+        let addr = format!("{}:{}", self.host, self.port);
+        let tcp_stream = self.open_stream(&addr).await?;
+        let addr = tcp_stream.peer_addr()?.to_string();
+        self.stream = Some(BufReader::new(tcp_stream));
+        self.resync = false;
+        self.exchange = Exchange::default();
+        self.restore_tubes().await?;
+        self.state.send_replace(ConnectionState::Connected);
+        for listener in &self.listeners {
+            listener.on_connect(&addr);
+        }
+        logging::connected(&addr);
+        Ok(())
+    }
+
+    /// Select the tubes recorded by this client on a new connection. The connection is
+    /// dropped if any fails, so that jobs never go to another tube than the one selected,
+    /// while the recorded tubes are kept for the next connection.
+    async fn restore_tubes(&mut self) -> BeanstalkcResult<()> {
+        for cmd in self.tube_state.restore() {
+            let restored = match self.write_command(&cmd).await {
+                Ok(resp) => check_status(&cmd, resp),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = restored {
+                self.stream = None;
+                self.exchange = Exchange::default();
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve `addr`, or call the resolver if any, unless cached addresses can be reused.
+    /// Then connect to the first address accepting the connection.
+    async fn open_stream(&mut self, addr: &str) -> BeanstalkcResult<TcpStream> {
+        let tcp_stream = if self.cache_dns && !self.resolved_addrs.is_empty() {
+            TcpStream::connect(&self.resolved_addrs[..]).await?
+        } else {
+            let addrs: Vec<SocketAddr> = match &self.resolver {
+                Some(resolver) => resolver.resolve().await?,
+                None => tokio::net::lookup_host(addr).await?.collect(),
+            };
+            let tcp_stream = TcpStream::connect(&addrs[..]).await?;
+            if self.cache_dns {
+                self.resolved_addrs = addrs;
+            }
+            tcp_stream
+        };
+
+        match &self.handshake {
+            Some(handshake) => Ok(handshake.run(tcp_stream).await?),
+            None => Ok(tcp_stream),
+        }
+    }
+
+    /// Drop the connection after `err` made it unusable. A desynchronized connection is
+    /// replaced before the next command.
+    fn connection_failed(&mut self, err: &BeanstalkcError) {
+        if let BeanstalkcError::ProtocolDesync(_) = err {
+            self.resync = true;
+        }
+        if self.stream.is_some() {
+            logging::disconnected(err);
+        }
+        self.disconnected(Some(err));
+    }
+
+    /// Drop the connection, notifying the listeners if it was still open.
+    fn disconnected(&mut self, reason: Option<&BeanstalkcError>) {
+        self.exchange = Exchange::default();
+        if self.stream.take().is_some() {
+            self.state.send_replace(ConnectionState::Disconnected);
+            for listener in &self.listeners {
+                listener.on_disconnect(reason);
+            }
+        }
+    }
+
+    /// Close connection to remote server.
+    #[allow(unused_must_use)]
+    pub(crate) async fn close(&mut self) {
+        if self.stream.is_some() {
+            self.send(command::quit()).await;
+        }
+        self.disconnected(None);
+    }
+
+    /// Re-connect to the beanstalkd server.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    /// let mut conn = conn.reconnect().await.unwrap();
+    /// }
+    /// ```
+    pub async fn reconnect(mut self) -> BeanstalkcResult<Self> {
+        self.close().await;
+        for listener in &self.listeners {
+            listener.on_reconnect_attempt(1);
+        }
+        logging::reconnecting(1);
+        self.connect().await
+    }
+
+    /// Put a job into the current tube with the default priority, delay and TTR configured
+    /// on the builder. Return job id.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let job_id = conn.put_default(b"Rust").await.unwrap();
+    /// }
+    /// ```
+    pub async fn put_default(&mut self, body: &[u8]) -> BeanstalkcResult<u64> {
+        let (priority, delay, ttr) = self.put_defaults();
+        self.put(body, priority, delay, ttr).await
+    }
+
+    /// Put a job into the current tube, to become ready at `at`, with the default priority
+    /// and TTR configured on the builder. Return job id.
+    ///
+    /// The delay is rounded up to whole seconds, so the job never becomes ready before
+    /// `at`, and a job due in the past is ready at once. Fail with
+    /// `BeanstalkcError::InvalidSchedule` if `at` is further than the longest delay
+    /// beanstalkd accepts, about 136 years: see [`LongDelay`](crate::LongDelay) for jobs
+    /// due later.
+    ///
+    /// `at` is a `SystemTime` rather than an `Instant` since jobs are usually due at a wall
+    /// clock time, e.g. from a calendar or another process, which an `Instant` cannot
+    /// represent. The delay is computed against the system clock when the job is put: use
+    /// [`Beanstalkc::put_after`] for a delay measured with a monotonic clock.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// // 2030-01-02 03:00 UTC.
+    /// let at = UNIX_EPOCH + Duration::from_secs(1_893_553_200);
+    /// let job_id = conn.put_at(b"nightly-report", at).await.unwrap();
+    /// }
+    /// ```
+    pub async fn put_at(&mut self, body: &[u8], at: SystemTime) -> BeanstalkcResult<u64> {
+        let delay = at.duration_since(SystemTime::now()).unwrap_or_default();
+        self.put_after(body, delay).await
+    }
+
+    /// Put a job into the current tube, to become ready once `delay` has elapsed, with the
+    /// default priority and TTR configured on the builder. Return job id.
+    ///
+    /// Like [`Beanstalkc::put_at`], the delay is rounded up to whole seconds, and fails
+    /// with `BeanstalkcError::InvalidSchedule` if it is longer than beanstalkd accepts.
+    ///
+    /// # Example:
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let job_id = conn.put_after(b"reminder", Duration::from_millis(1500)).await.unwrap();
+    /// }
+    /// ```
+    pub async fn put_after(&mut self, body: &[u8], delay: Duration) -> BeanstalkcResult<u64> {
+        let delay = job_delay(delay)?;
+        let (priority, _, ttr) = self.put_defaults();
+        self.put(body, priority, delay, ttr).await
+    }
+
+    /// Return the priority, delay and TTR used by `put_default`.
+    pub(crate) fn put_defaults(&self) -> (u32, Duration, Duration) {
+        (self.default_priority, self.default_delay, self.default_ttr)
+    }
+
+    /// Return the priority, delay and TTR of `request`, falling back to `put_defaults`.
+    pub(crate) fn put_options(&self, request: &PutRequest) -> (u32, Duration, Duration) {
+        (
+            request.priority.unwrap_or(self.default_priority),
+            request.delay.unwrap_or(self.default_delay),
+            request.ttr.unwrap_or(self.default_ttr),
+        )
+    }
+
+    /// Put `request` into the current tube and return the job id.
+    pub(crate) async fn put_request(&mut self, request: &PutRequest) -> BeanstalkcResult<u64> {
+        let (priority, delay, ttr) = self.put_options(request);
+        self.put(&request.body, priority, delay, ttr).await
+    }
+
+    /// Return the priority and delay used by `release_default`.
+    pub(crate) fn release_defaults(&self) -> (u32, Duration) {
+        (self.default_priority, self.default_delay)
+    }
+
+    /// Put a job into the current tube and return the job id.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let job_id = conn.put(
+    ///        b"Rust",
+    ///        0,
+    ///        Duration::from_secs(1),
+    ///        Duration::from_secs(10),
+    ///    );
+    /// }
+    /// ```
+    pub async fn put(
+        &mut self,
+        body: &[u8],
+        priority: u32,
+        delay: Duration,
+        ttr: Duration,
+    ) -> BeanstalkcResult<u64> {
+        self.throttle_puts(1).await?;
+        self.send(command::put(body, priority, delay, ttr))
+            .await
+            .and_then(|r| r.job_id())
+    }
+
+    /// Put a job into the current tube, copying its body of `len` bytes from `reader` in
+    /// chunks, so that large bodies need not be loaded in memory. Return the job id.
+    ///
+    /// The connection is closed if `reader` fails or ends before `len` bytes. Interceptors
+    /// see the command with an empty body.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut file = tokio::fs::File::open("payload.bin").await.unwrap();
+    /// let len = file.metadata().await.unwrap().len();
+    /// let job_id = conn
+    ///     .put_from_reader(&mut file, len, 0, Duration::from_secs(0), Duration::from_secs(60))
+    ///     .await
+    ///     .unwrap();
+    /// }
+    /// ```
+    pub async fn put_from_reader<R>(
+        &mut self,
+        reader: &mut R,
+        len: u64,
+        priority: u32,
+        delay: Duration,
+        ttr: Duration,
+    ) -> BeanstalkcResult<u64>
+    where
+        R: AsyncRead + Unpin + ?Sized,
+    {
+        self.throttle_puts(1).await?;
+        let mut cmd = command::put(&[], priority, delay, ttr);
+        let resp = self
+            .send_streamed(&mut cmd, Some((reader, len)), None::<&mut tokio::io::Sink>)
+            .await?;
+        check_status(&cmd, resp)?.job_id()
+    }
+
+    /// Put a job into the current tube unless a job with the same `key` was put recently,
+    /// according to the [`DedupStore`] of this client. Return the job id, or `None` if the
+    /// put was skipped. Keys are not scoped to the tube.
+    ///
+    /// When the put fails, its error is returned and the key is removed so the job can be
+    /// put again, unless the connection failed: the job may have been enqueued anyway, so
+    /// the key is kept until it expires.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, PutRequest};
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let request = PutRequest::new(b"send-invoice-42".to_vec());
+    /// assert!(conn.put_unique("invoice-42", request.clone()).await.unwrap().is_some());
+    /// assert!(conn.put_unique("invoice-42", request).await.unwrap().is_none());
+    /// }
+    /// ```
+    pub async fn put_unique(
+        &mut self,
+        key: &str,
+        request: PutRequest,
+    ) -> BeanstalkcResult<Option<u64>> {
+        let store = Arc::clone(&self.dedup_store);
+        if !store.insert(key, self.dedup_ttl).await? {
+            return Ok(None);
+        }
+        let put = self.put_request(&request).await;
+        match &put {
+            Err(err) if !err.is_connection_error() => {
+                if let Err(err) = store.remove(key).await {
+                    logging::dedup_remove_failed(key, &err);
+                }
+            }
+            _ => {}
+        }
+        put.map(Some)
+    }
+
+    /// Reserve a job from one of those watched tubes. Return a `Job` object if it succeeds.
+    ///
+    /// # Cancellation
+    ///
+    /// The future can be dropped at any point, for instance by `tokio::time::timeout` or
+    /// `select!`. If the request was already written, the connection is marked dirty: before
+    /// the next command, the response is read and the job it reserved, if any, is released.
+    /// When the response does not arrive quickly, the connection is replaced instead, and
+    /// the server releases the job once it sees the connection closed. This applies to every
+    /// command, including [`Beanstalkc::reserve_with_timeout`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut job = conn.reserve().await.unwrap();
+    /// // Execute job...
+    /// dbg!(job.id());
+    /// dbg!(job.body());
+    ///
+    /// job.delete().await.unwrap();
+    /// }
+    /// ```
+    pub async fn reserve(&mut self) -> BeanstalkcResult<Job<'_>> {
+        let (id, body) = self.reserve_status(None).await?.map_err(command_failed)?;
+        Ok(Job::new(self, id, body, true))
+    }
+
+    /// Reserve a job from one of those watched tubes, copying its body into `writer` in
+    /// chunks instead of buffering it, so that large bodies need not fit in memory. Return
+    /// the job, without its body: [`Job::run`] and moving the job into another tube, e.g.
+    /// by [`Job::fail`] with a retry topology, fail with `BeanstalkcError::BodyNotLoaded`.
+    ///
+    /// The reserve waits for a job in a single command: it does not beat the heartbeat, and
+    /// fails on `DEADLINE_SOON` even with [`Beanstalkc::touch_on_deadline_soon`].
+    ///
+    /// The TTR of the job runs while its body is downloaded. Since the server answers the
+    /// commands of a connection in order, the job cannot be touched before its body has
+    /// been read: give jobs with large bodies a TTR which covers their download.
+    ///
+    /// The connection is closed if `writer` fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut file = tokio::fs::File::create("payload.bin").await.unwrap();
+    /// let mut job = conn.reserve_into(&mut file).await.unwrap();
+    /// // Execute job from the file...
+    /// job.delete().await.unwrap();
+    /// }
+    /// ```
+    pub async fn reserve_into<W>(&mut self, writer: &mut W) -> BeanstalkcResult<Job<'_>>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let id = self.reserve_streamed(writer).await?;
+        Ok(Job::streamed(self, id, true))
+    }
+
+    /// Reserve a job from one of those watched tubes, reading its body into `buf` instead of
+    /// a new allocation. The returned job borrows its body from `buf`, which is cleared
+    /// first: reusing one buffer across jobs avoids allocating per job once it has grown to
+    /// the size of the largest body.
+    ///
+    /// Like [`Beanstalkc::reserve_into`], the reserve waits for a job in a single command,
+    /// without heartbeat or `DEADLINE_SOON` handling.
     ///
-    /// Basic usage
+    /// # Example
     ///
     /// ```no_run
     /// #[tokio::main]
@@ -100,51 +1712,81 @@ impl Beanstalkc {
     /// use beanstalkc::Beanstalkc;
     ///
     /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut buf = Vec::with_capacity(64 * 1024);
+    /// loop {
+    ///     let mut job = conn.reserve_buffered(&mut buf).await.unwrap();
+    ///     dbg!(job.body().len());
+    ///     job.delete().await.unwrap();
+    /// }
     /// }
     /// ```
+    pub async fn reserve_buffered<'a>(
+        &'a mut self,
+        buf: &'a mut Vec<u8>,
+    ) -> BeanstalkcResult<Job<'a>> {
+        buf.clear();
+        let id = self.reserve_streamed(&mut *buf).await?;
+        if self.assume_utf8 {
+            if let Err(err) = check_utf8(id, buf) {
+                Job::new(self, id, vec![], true).bury_default().await?;
+                return Err(err);
+            }
+        }
+        Ok(Job::borrowed(self, id, buf, true))
+    }
+
+    /// Reserve a job, copying its body into `writer`. Return the job id.
+    async fn reserve_streamed<W>(&mut self, writer: &mut W) -> BeanstalkcResult<u64>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let mut cmd = command::reserve(None);
+        let resp = self
+            .send_streamed(&mut cmd, None::<(&mut tokio::io::Empty, u64)>, Some(writer))
+            .await;
+        if let Err(BeanstalkcError::BodyTooLarge { id, .. }) = resp {
+            Job::new(self, id, vec![], true)
+                .finalize_oversized()
+                .await?;
+        }
+        let id = check_status(&cmd, resp?)?.job_id()?;
+        if self.touch_on_deadline_soon {
+            self.reserved_jobs.insert(id);
+        }
+        Ok(id)
+    }
+
+    /// Reserve a job with given timeout from one of those watched tubes.
+    /// Return a `Job` object if it succeeds.
     ///
-    /// With custom configurations
+    /// # Example
     ///
     /// ```no_run
+    /// use beanstalkc::Beanstalkc;
     /// #[tokio::main]
     /// async fn main() {
     /// use std::time::Duration;
-    /// use beanstalkc::Beanstalkc;
     ///
-    /// let mut conn = Beanstalkc::new()
-    ///        .host("127.0.0.1")
-    ///        .port(11300)
-    ///        .connection_timeout(Some(Duration::from_secs(5)))
-    ///        .connect().await
-    ///        .unwrap();
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut job = conn.reserve_with_timeout(Duration::from_secs(10)).await.unwrap();
+    /// // Execute job...
+    /// dbg!(job.id());
+    /// dbg!(job.body());
+    ///
+    /// job.delete().await.unwrap();
     /// }
     /// ```
-    pub async fn connect(mut self) -> BeanstalkcResult<Self> {
-        let addr = format!("{}:{}", self.host, self.port);
-        // let tcp_stream = match self.connection_timeout {
-        //     Some(timeout) => {
-        //         let addresses: Vec<_> = addr
-        //             .to_socket_addrs()
-        //             .unwrap_or_else(|_| panic!("failed to parse address: {}", addr))
-        //             .filter(|x| x.is_ipv4())
-        //             .collect();
-        //         // FIXME: maybe we should try every possible addresses?
-        //         TcpStream::connect_timeout(&addresses.first().unwrap(), timeout)?
-        //     }
-        //     None => TcpStream::connect(&addr).await?,
-        // };
-        let tcp_stream = TcpStream::connect(&addr).await?;
-        self.stream = Some(BufReader::new(tcp_stream));
-        Ok(self)
-    }
-
-    /// Close connection to remote server.
-    #[allow(unused_must_use)]
-    async fn close(&mut self) {
-        self.send(command::quit()).await;
+    pub async fn reserve_with_timeout(&mut self, timeout: Duration) -> BeanstalkcResult<Job<'_>> {
+        let (id, body) = self
+            .reserve_status(Some(timeout))
+            .await?
+            .map_err(command_failed)?;
+        Ok(Job::new(self, id, body, true))
     }
 
-    /// Re-connect to the beanstalkd server.
+    /// Reserve a job without blocking. Return `None` when no job is ready in the watched tubes.
     ///
     /// # Example
     ///
@@ -154,128 +1796,294 @@ impl Beanstalkc {
     /// use beanstalkc::Beanstalkc;
     ///
     /// let mut conn = Beanstalkc::new().connect().await.unwrap();
-    /// let mut conn = conn.reconnect().await.unwrap();
+    ///
+    /// while let Some(mut job) = conn.try_reserve().await.unwrap() {
+    ///     // Execute job...
+    ///     job.delete().await.unwrap();
+    /// }
     /// }
     /// ```
-    pub async fn reconnect(mut self) -> BeanstalkcResult<Self> {
-        self.close().await;
-        self.connect().await
+    pub async fn try_reserve(&mut self) -> BeanstalkcResult<Option<Job<'_>>> {
+        match self.reserve_status(Some(Duration::from_secs(0))).await? {
+            Ok((id, body)) => Ok(Some(Job::new(self, id, body, true))),
+            Err(Status::TimedOut) => Ok(None),
+            Err(status) => Err(command_failed(status)),
+        }
     }
 
-    /// Put a job into the current tube with default configs. Return job id.
+    /// Reserve up to `n` jobs. The first reserve waits for at most `timeout`, the following
+    /// ones take only jobs which are ready right away. Return an empty batch on timeout.
     ///
-    /// # Example:
+    /// # Example
     ///
     /// ```no_run
     /// #[tokio::main]
     /// async fn main() {
+    /// use std::time::Duration;
     /// use beanstalkc::Beanstalkc;
     ///
     /// let mut conn = Beanstalkc::new().connect().await.unwrap();
     ///
-    /// let job_id = conn.put_default(b"Rust").await.unwrap();
+    /// let mut batch = conn.reserve_many(100, Duration::from_secs(10)).await.unwrap();
+    /// for job in batch.jobs() {
+    ///     dbg!(job.id());
+    /// }
+    /// batch.delete_all().await.unwrap();
     /// }
     /// ```
-    pub async fn put_default(&mut self, body: &[u8]) -> BeanstalkcResult<u64> {
-        self.put(
-            body,
-            DEFAULT_JOB_PRIORITY,
-            DEFAULT_JOB_DELAY,
-            DEFAULT_JOB_TTR,
-        )
-        .await
+    pub async fn reserve_many(
+        &mut self,
+        n: usize,
+        timeout: Duration,
+    ) -> BeanstalkcResult<JobBatch<'_>> {
+        let mut jobs = Vec::with_capacity(n);
+        let mut wait = timeout;
+        while jobs.len() < n {
+            let reserved = match self.reserve_status(Some(wait)).await {
+                Err(err) if err.is_job_error() => {
+                    logging::job_skipped(&err);
+                    continue;
+                }
+                reserved => reserved?,
+            };
+            match reserved {
+                Ok((id, body)) => jobs.push(ReservedJob::new(id, body)),
+                Err(Status::DeadlineSoon) if jobs.is_empty() => {
+                    return Err(command_failed(Status::DeadlineSoon));
+                }
+                Err(_) => break,
+            }
+            wait = Duration::from_secs(0);
+        }
+        Ok(JobBatch::new(self, jobs))
     }
 
-    /// Put a job into the current tube and return the job id.
+    /// Reserve a job until `shutdown` completes. Return `None` if `shutdown` completes first.
+    ///
+    /// Since a pending reserve cannot be aborted on the wire, the client reserves in short
+    /// slices and checks `shutdown` between them. A job which is reserved while `shutdown`
+    /// completes is released rather than leaked until its TTR expires.
     ///
     /// # Example
     ///
     /// ```no_run
-    /// use std::time::Duration;
     /// #[tokio::main]
     /// async fn main() {
     /// use beanstalkc::Beanstalkc;
     ///
     /// let mut conn = Beanstalkc::new().connect().await.unwrap();
     ///
-    /// let job_id = conn.put(
-    ///        b"Rust",
-    ///        0,
-    ///        Duration::from_secs(1),
-    ///        Duration::from_secs(10),
-    ///    );
+    /// match conn.reserve_until(tokio::signal::ctrl_c()).await.unwrap() {
+    ///     Some(mut job) => job.delete().await.unwrap(),
+    ///     None => println!("shutting down"),
+    /// }
     /// }
     /// ```
-    pub async fn put(
+    pub async fn reserve_until<S: Future>(
         &mut self,
-        body: &[u8],
-        priority: u32,
-        delay: Duration,
-        ttr: Duration,
-    ) -> BeanstalkcResult<u64> {
-        self.send(command::put(body, priority, delay, ttr))
-            .await
-            .and_then(|r| r.job_id())
+        shutdown: S,
+    ) -> BeanstalkcResult<Option<Job<'_>>> {
+        self.reserve_cancellable(None, shutdown).await
     }
 
-    /// Reserve a job from one of those watched tubes. Return a `Job` object if it succeeds.
+    /// Reserve a job with given timeout until `shutdown` completes. Return `None` if
+    /// `shutdown` completes first. See [`Beanstalkc::reserve_until`] for details.
     ///
     /// # Example
     ///
     /// ```no_run
     /// #[tokio::main]
     /// async fn main() {
+    /// use std::time::Duration;
     /// use beanstalkc::Beanstalkc;
     ///
     /// let mut conn = Beanstalkc::new().connect().await.unwrap();
     ///
-    /// let mut job = conn.reserve().await.unwrap();
-    /// // Execute job...
-    /// dbg!(job.id());
-    /// dbg!(job.body());
-    ///
-    /// job.delete().await.unwrap();
+    /// let job = conn
+    ///     .reserve_with_timeout_until(Duration::from_secs(10), tokio::signal::ctrl_c())
+    ///     .await
+    ///     .unwrap();
     /// }
     /// ```
-    pub async fn reserve(&mut self) -> BeanstalkcResult<Job> {
-        let resp = self.send(command::reserve(None)).await?;
-        Ok(Job::new(
-            self,
-            resp.job_id()?,
-            resp.body.unwrap_or_default(),
-            true,
-        ))
+    pub async fn reserve_with_timeout_until<S: Future>(
+        &mut self,
+        timeout: Duration,
+        shutdown: S,
+    ) -> BeanstalkcResult<Option<Job<'_>>> {
+        self.reserve_cancellable(Some(timeout), shutdown).await
     }
 
-    /// Reserve a job with given timeout from one of those watched tubes.
-    /// Return a `Job` object if it succeeds.
+    async fn reserve_cancellable<S: Future>(
+        &mut self,
+        timeout: Option<Duration>,
+        shutdown: S,
+    ) -> BeanstalkcResult<Option<Job<'_>>> {
+        tokio::pin!(shutdown);
+        let deadline = timeout.map(|t| Instant::now() + t);
+
+        loop {
+            if (&mut shutdown).now_or_never().is_some() {
+                return Ok(None);
+            }
+
+            // The server takes timeouts in whole seconds: round the time left up, so that
+            // less than a second left does not turn into a loop of zero timeouts.
+            let slice = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    Duration::from_secs(left.as_secs_f64().ceil() as u64)
+                        .min(CANCELLABLE_RESERVE_SLICE)
+                }
+                None => CANCELLABLE_RESERVE_SLICE,
+            };
+            let (id, body) = match self.reserve_status(Some(slice)).await? {
+                Ok(job) => job,
+                Err(Status::TimedOut) if deadline.map_or(true, |d| Instant::now() < d) => continue,
+                Err(status) => return Err(command_failed(status)),
+            };
+
+            let mut job = Job::new(self, id, body, true);
+            if (&mut shutdown).now_or_never().is_some() {
+                job.release_default().await?;
+                return Ok(None);
+            }
+            return Ok(Some(job));
+        }
+    }
+
+    /// Reserve a job, returning the status instead of an error when the server replies with
+    /// `TIMED_OUT` or `DEADLINE_SOON`. With a heartbeat, reserve in attempts of at most its
+    /// interval and beat between them.
+    pub(crate) async fn reserve_status(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> BeanstalkcResult<Result<(u64, Vec<u8>), Status>> {
+        let heartbeat = match &self.heartbeat {
+            Some(heartbeat) if timeout.map_or(true, |t| t > heartbeat.interval()) => {
+                heartbeat.clone()
+            }
+            _ => return self.reserve_attempt(timeout).await,
+        };
+        let deadline = timeout.map(|t| Instant::now() + t);
+
+        loop {
+            let slice = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    Duration::from_secs(left.as_secs_f64().ceil() as u64).min(heartbeat.interval())
+                }
+                None => heartbeat.interval(),
+            };
+            match self.reserve_attempt(Some(slice)).await? {
+                Err(Status::TimedOut) if deadline.map_or(true, |d| Instant::now() < d) => {
+                    heartbeat.beat();
+                }
+                result => return Ok(result),
+            }
+        }
+    }
+
+    /// Send a single reserve, see [`Beanstalkc::reserve_status`].
+    async fn reserve_attempt(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> BeanstalkcResult<Result<(u64, Vec<u8>), Status>> {
+        let mut cmd = command::reserve(timeout);
+        let mut resp = self.send_reserve(&mut cmd).await?;
+        if resp.status == Status::DeadlineSoon
+            && self.touch_on_deadline_soon
+            && !self.reserved_jobs.is_empty()
+        {
+            self.touch_reserved().await?;
+            resp = self.send_reserve(&mut cmd).await?;
+        }
+        if resp.status == Status::TimedOut || resp.status == Status::DeadlineSoon {
+            return Ok(Err(resp.status));
+        }
+
+        let resp = check_status(&cmd, resp)?;
+        let id = resp.job_id()?;
+        if self.touch_on_deadline_soon {
+            self.reserved_jobs.insert(id);
+        }
+        Ok(Ok((id, resp.body.unwrap_or_default())))
+    }
+
+    /// Send a reserve command, finalizing the reserved job with the oversize action if its
+    /// body was skipped for being too large, or burying it if its body should be UTF-8 and
+    /// is not.
+    async fn send_reserve(&mut self, cmd: &mut command::Command<'_>) -> BeanstalkcResult<Response> {
+        let resp = match self.send_raw(cmd).await {
+            Ok(resp) if self.assume_utf8 && resp.status == Status::Reserved => {
+                check_utf8(resp.job_id()?, resp.body.as_deref().unwrap_or_default()).map(|()| resp)
+            }
+            resp => resp,
+        };
+        match resp {
+            Err(BeanstalkcError::BodyTooLarge { id, .. }) => {
+                Job::new(self, id, vec![], true)
+                    .finalize_oversized()
+                    .await?
+            }
+            Err(BeanstalkcError::NonUtf8Body { id, .. }) => {
+                Job::new(self, id, vec![], true).bury_default().await?
+            }
+            _ => {}
+        }
+        resp
+    }
+
+    /// Touch every job tracked as reserved by this client, forgetting the ones which are
+    /// not reserved anymore.
+    async fn touch_reserved(&mut self) -> BeanstalkcResult<()> {
+        let ids: Vec<u64> = self.reserved_jobs.iter().copied().collect();
+        for id in ids {
+            match self.touch(id).await {
+                Ok(()) => {}
+                Err(BeanstalkcError::CommandFailed(_)) => {
+                    self.reserved_jobs.remove(&id);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reserve a job and run `handler` against it, finalizing the job from the handler
+    /// result. See [`Job::run`] for how results map to commands and how panics are handled.
     ///
     /// # Example
     ///
     /// ```no_run
-    /// use beanstalkc::Beanstalkc;
     /// #[tokio::main]
     /// async fn main() {
     /// use std::time::Duration;
+    /// use beanstalkc::{Beanstalkc, Outcome};
     ///
     /// let mut conn = Beanstalkc::new().connect().await.unwrap();
     ///
-    /// let mut job = conn.reserve_with_timeout(Duration::from_secs(10)).await.unwrap();
-    /// // Execute job...
-    /// dbg!(job.id());
-    /// dbg!(job.body());
-    ///
-    /// job.delete().await.unwrap();
+    /// loop {
+    ///     let result = conn
+    ///         .process(|job| async move {
+    ///             if job.body().is_empty() {
+    ///                 return Ok(Outcome::Bury);
+    ///             }
+    ///             // Execute job...
+    ///             Ok::<_, String>(Outcome::Delete)
+    ///         })
+    ///         .await
+    ///         .unwrap();
+    ///     dbg!(result);
+    /// }
     /// }
     /// ```
-    pub async fn reserve_with_timeout(&mut self, timeout: Duration) -> BeanstalkcResult<Job> {
-        let resp = self.send(command::reserve(Some(timeout))).await?;
-        Ok(Job::new(
-            self,
-            resp.job_id()?,
-            resp.body.unwrap_or_default(),
-            true,
-        ))
+    pub async fn process<F, Fut, E>(&mut self, handler: F) -> BeanstalkcResult<Result<Outcome, E>>
+    where
+        F: FnOnce(ReservedJob) -> Fut,
+        Fut: Future<Output = Result<Outcome, E>>,
+    {
+        let mut job = self.reserve().await?;
+        job.run(handler).await
     }
 
     /// Kick at most `bound` jobs into the ready queue.
@@ -298,6 +2106,28 @@ impl Beanstalkc {
             .and_then(|r| r.get_int_param(0))
     }
 
+    /// Kick at most `bound` jobs of tube `name` into the ready queue. The tube is used for
+    /// the kick only: the previously used tube is used again afterwards, even if the kick
+    /// fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let kicked = conn.kick_tube("emails", 100).await.unwrap();
+    /// assert_eq!("default", conn.using().await.unwrap());
+    /// }
+    /// ```
+    pub async fn kick_tube(&mut self, name: &str, bound: u32) -> BeanstalkcResult<u64> {
+        self.with_tube(name, move |c| async move { c.kick(bound).await }.boxed())
+            .await
+    }
+
     /// Kick a specific job into the ready queue.
     ///
     /// # Example
@@ -331,10 +2161,74 @@ impl Beanstalkc {
     /// assert_eq!(1, job.id());
     /// }
     /// ```
-    pub async fn peek(&mut self, job_id: u64) -> BeanstalkcResult<Job> {
+    pub async fn peek(&mut self, job_id: u64) -> BeanstalkcResult<Job<'_>> {
         self.do_peek(command::peek_job(job_id)).await
     }
 
+    /// Return a specific job, copying its body into `writer` in chunks instead of
+    /// buffering it. The job is returned without its body. See
+    /// [`Beanstalkc::reserve_into`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut body = vec![];
+    /// conn.peek_into(1, &mut body).await.unwrap();
+    /// }
+    /// ```
+    pub async fn peek_into<W>(&mut self, job_id: u64, writer: &mut W) -> BeanstalkcResult<Job<'_>>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let id = self.peek_streamed(job_id, writer).await?;
+        Ok(Job::streamed(self, id, false))
+    }
+
+    /// Return a specific job, reading its body into `buf` instead of a new allocation. See
+    /// [`Beanstalkc::reserve_buffered`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut buf = vec![];
+    /// let job = conn.peek_buffered(1, &mut buf).await.unwrap();
+    /// dbg!(job.body());
+    /// }
+    /// ```
+    pub async fn peek_buffered<'a>(
+        &'a mut self,
+        job_id: u64,
+        buf: &'a mut Vec<u8>,
+    ) -> BeanstalkcResult<Job<'a>> {
+        buf.clear();
+        let id = self.peek_streamed(job_id, &mut *buf).await?;
+        Ok(Job::borrowed(self, id, buf, false))
+    }
+
+    /// Peek a specific job, copying its body into `writer`. Return the job id.
+    async fn peek_streamed<W>(&mut self, job_id: u64, writer: &mut W) -> BeanstalkcResult<u64>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let mut cmd = command::peek_job(job_id);
+        let resp = self
+            .send_streamed(&mut cmd, None::<(&mut tokio::io::Empty, u64)>, Some(writer))
+            .await?;
+        check_status(&cmd, resp)?.job_id()
+    }
+
     /// Return the next ready job.
     ///
     /// # Example
@@ -351,7 +2245,7 @@ impl Beanstalkc {
     /// dbg!(job.body());
     /// }
     /// ```
-    pub async fn peek_ready(&mut self) -> BeanstalkcResult<Job> {
+    pub async fn peek_ready(&mut self) -> BeanstalkcResult<Job<'_>> {
         self.do_peek(command::peek_ready()).await
     }
 
@@ -371,11 +2265,109 @@ impl Beanstalkc {
     /// dbg!(job.body());
     /// }
     /// ```
-    pub async fn peek_delayed(&mut self) -> BeanstalkcResult<Job> {
+    pub async fn peek_delayed(&mut self) -> BeanstalkcResult<Job<'_>> {
         self.do_peek(command::peek_delayed()).await
     }
 
-    /// Return the next job in the list of buried jobs.
+    /// Return the next job in the list of buried jobs.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut job = conn.peek_buried().await.unwrap();
+    /// dbg!(job.id());
+    /// dbg!(job.body());
+    /// }
+    /// ```
+    pub async fn peek_buried(&mut self) -> BeanstalkcResult<Job<'_>> {
+        self.do_peek(command::peek_buried()).await
+    }
+
+    pub async fn do_peek(&mut self, cmd: command::Command<'_>) -> BeanstalkcResult<Job<'_>> {
+        let resp = self.send(cmd).await?;
+        Ok(Job::new(
+            self,
+            resp.job_id()?,
+            resp.body.unwrap_or_default(),
+            false,
+        ))
+    }
+
+    /// Return a specific job, or `None` if it does not exist.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// if conn.peek_opt(1).await.unwrap().is_none() {
+    ///     println!("job 1 is gone");
+    /// }
+    /// }
+    /// ```
+    pub async fn peek_opt(&mut self, job_id: u64) -> BeanstalkcResult<Option<Job<'_>>> {
+        self.do_peek_opt(command::peek_job(job_id)).await
+    }
+
+    /// Return the next ready job, or `None` if there is no ready job.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// while let Some(job) = conn.peek_ready_opt().await.unwrap() {
+    ///     let id = job.id();
+    ///     conn.delete(id).await.unwrap();
+    /// }
+    /// }
+    /// ```
+    pub async fn peek_ready_opt(&mut self) -> BeanstalkcResult<Option<Job<'_>>> {
+        self.do_peek_opt(command::peek_ready()).await
+    }
+
+    /// Return the delayed job with the shortest delay left, or `None` if there is no
+    /// delayed job.
+    pub async fn peek_delayed_opt(&mut self) -> BeanstalkcResult<Option<Job<'_>>> {
+        self.do_peek_opt(command::peek_delayed()).await
+    }
+
+    /// Return the next job in the list of buried jobs, or `None` if there is no buried job.
+    pub async fn peek_buried_opt(&mut self) -> BeanstalkcResult<Option<Job<'_>>> {
+        self.do_peek_opt(command::peek_buried()).await
+    }
+
+    async fn do_peek_opt(
+        &mut self,
+        cmd: command::Command<'_>,
+    ) -> BeanstalkcResult<Option<Job<'_>>> {
+        let resp = self.send(cmd.expect(Status::NotFound)).await?;
+        if resp.status == Status::NotFound {
+            return Ok(None);
+        }
+        Ok(Some(Job::new(
+            self,
+            resp.job_id()?,
+            resp.body.unwrap_or_default(),
+            false,
+        )))
+    }
+
+    /// Return a list of all existing tubes.
     ///
     /// # Example
     ///
@@ -386,26 +2378,16 @@ impl Beanstalkc {
     ///
     /// let mut conn = Beanstalkc::new().connect().await.unwrap();
     ///
-    /// let mut job = conn.peek_buried().await.unwrap();
-    /// dbg!(job.id());
-    /// dbg!(job.body());
+    /// let tubes = conn.tubes().await.unwrap();
+    /// assert!(tubes.contains("default"));
     /// }
     /// ```
-    pub async fn peek_buried(&mut self) -> BeanstalkcResult<Job> {
-        self.do_peek(command::peek_buried()).await
-    }
-
-    pub async fn do_peek(&mut self, cmd: command::Command<'_>) -> BeanstalkcResult<Job> {
-        let resp = self.send(cmd).await?;
-        Ok(Job::new(
-            self,
-            resp.job_id()?,
-            resp.body.unwrap_or_default(),
-            false,
-        ))
+    pub async fn tubes(&mut self) -> BeanstalkcResult<TubeSet> {
+        let tubes = self.send(command::tubes()).await?.body_as_vec()?;
+        Ok(self.strip_namespaces(tubes).into())
     }
 
-    /// Return a list of all existing tubes.
+    /// Return a handle running commands scoped to tube `name`.
     ///
     /// # Example
     ///
@@ -416,12 +2398,11 @@ impl Beanstalkc {
     ///
     /// let mut conn = Beanstalkc::new().connect().await.unwrap();
     ///
-    /// let tubes = conn.tubes().await.unwrap();
-    /// assert!(tubes.contains(&String::from("default")));
+    /// conn.tube("emails").put_default(b"hello").await.unwrap();
     /// }
     /// ```
-    pub async fn tubes(&mut self) -> BeanstalkcResult<Vec<String>> {
-        self.send(command::tubes()).await?.body_as_vec()
+    pub fn tube(&mut self, name: &str) -> Tube<'_> {
+        Tube::new(self, name)
     }
 
     /// Return the tube currently being used.
@@ -440,9 +2421,8 @@ impl Beanstalkc {
     /// }
     /// ```
     pub async fn using(&mut self) -> BeanstalkcResult<String> {
-        self.send(command::using())
-            .await
-            .and_then(|r| r.get_param(0))
+        let tube = self.send(command::using()).await?.get_param(0)?;
+        Ok(self.strip_namespace(&tube).to_string())
     }
 
     /// Use a given tube.
@@ -461,9 +2441,67 @@ impl Beanstalkc {
     /// }
     /// ```
     pub async fn use_tube(&mut self, name: &str) -> BeanstalkcResult<String> {
-        self.send(command::use_tube(name))
-            .await
-            .and_then(|r| r.get_param(0))
+        let tube = self
+            .send(command::use_tube(&self.namespaced(name)))
+            .await?
+            .get_param(0)?;
+        Ok(self.strip_namespace(&tube).to_string())
+    }
+
+    /// Use tube `name` while running `f`, then use the previously used tube again, even if
+    /// `f` failed. Return the result of `f`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    /// use futures::FutureExt;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    /// conn.use_tube("jobs").await.unwrap();
+    ///
+    /// let job_id = conn
+    ///     .with_tube("reports", |c| async move { c.put_default(b"daily").await }.boxed())
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!("jobs", conn.using().await.unwrap());
+    /// }
+    /// ```
+    pub async fn with_tube<T, F>(&mut self, name: &str, f: F) -> BeanstalkcResult<T>
+    where
+        F: for<'c> FnOnce(&'c mut Beanstalkc) -> BoxFuture<'c, BeanstalkcResult<T>>,
+    {
+        let previous = self.enter_tube(name).await?;
+        let result = f(self).await;
+        self.leave_tube(&previous, result).await
+    }
+
+    /// Use tube `name` for [`Beanstalkc::with_tube`], returning the tube to use again
+    /// afterwards.
+    pub(crate) async fn enter_tube(&mut self, name: &str) -> BeanstalkcResult<String> {
+        let previous = self.current_tube().to_string();
+        if previous != name {
+            self.use_tube(name).await?;
+        }
+        Ok(previous)
+    }
+
+    /// Use tube `previous` again once `result` is known, like [`Beanstalkc::with_tube`],
+    /// for callers whose closure borrows its environment.
+    pub(crate) async fn leave_tube<T>(
+        &mut self,
+        previous: &str,
+        result: BeanstalkcResult<T>,
+    ) -> BeanstalkcResult<T> {
+        if self.current_tube() != previous {
+            let restored = self.use_tube(previous).await;
+            if result.is_ok() {
+                restored?;
+            }
+        }
+        result
     }
 
     /// Return a list of tubes currently being watched.
@@ -478,11 +2516,12 @@ impl Beanstalkc {
     /// let mut conn = Beanstalkc::new().connect().await.unwrap();
     ///
     /// let tubes = conn.watching().await.unwrap();
-    /// assert_eq!(vec!["default".to_string()], tubes);
+    /// assert_eq!(vec!["default"], tubes.iter().collect::<Vec<_>>());
     /// }
     /// ```
-    pub async fn watching(&mut self) -> BeanstalkcResult<Vec<String>> {
-        self.send(command::watching()).await?.body_as_vec()
+    pub async fn watching(&mut self) -> BeanstalkcResult<TubeSet> {
+        let tubes = self.send(command::watching()).await?.body_as_vec()?;
+        Ok(self.strip_namespaces(tubes).into())
     }
 
     /// Watch a specific tube.
@@ -501,7 +2540,7 @@ impl Beanstalkc {
     /// }
     /// ```
     pub async fn watch(&mut self, name: &str) -> BeanstalkcResult<u64> {
-        self.send(command::watch(name))
+        self.send(command::watch(&self.namespaced(name)))
             .await
             .and_then(|r| r.get_int_param(0))
     }
@@ -520,7 +2559,7 @@ impl Beanstalkc {
     /// }
     /// ```
     pub async fn ignore(&mut self, name: &str) -> BeanstalkcResult<u64> {
-        self.send(command::ignore(name))
+        self.send(command::ignore(&self.namespaced(name)))
             .await
             .and_then(|r| r.get_int_param(0))
     }
@@ -543,7 +2582,8 @@ impl Beanstalkc {
         self.send(command::stats()).await?.body_as_map()
     }
 
-    /// Return a dict of statistical information about the specified tube.
+    /// Return a dict of statistical information about the specified tube. Fail with
+    /// `BeanstalkcError::TubeNotFound` if the tube does not exist.
     ///
     /// # Example
     ///
@@ -558,10 +2598,13 @@ impl Beanstalkc {
     /// }
     /// ```
     pub async fn stats_tube(&mut self, name: &str) -> BeanstalkcResult<HashMap<String, String>> {
-        self.send(command::stats_tube(name)).await?.body_as_map()
+        self.send_tube(command::stats_tube(&self.namespaced(name)), name)
+            .await?
+            .body_as_map()
     }
 
-    /// Pause the specific tube for `delay` time.
+    /// Pause the specific tube for `delay` time. Fail with `BeanstalkcError::TubeNotFound`
+    /// if the tube does not exist.
     ///
     /// # Example
     ///
@@ -576,11 +2619,50 @@ impl Beanstalkc {
     /// }
     /// ```
     pub async fn pause_tube(&mut self, name: &str, delay: Duration) -> BeanstalkcResult<()> {
-        self.send(command::pause_tube(name, delay))
+        self.send_tube(command::pause_tube(&self.namespaced(name), delay), name)
             .await
             .map(|_| ())
     }
 
+    /// Resume a paused tube, so its jobs can be reserved again.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    /// conn.resume_tube("default").await.unwrap();
+    /// }
+    /// ```
+    pub async fn resume_tube(&mut self, name: &str) -> BeanstalkcResult<()> {
+        self.pause_tube(name, Duration::from_secs(0)).await
+    }
+
+    /// Return how long the specific tube stays paused, zero if it is not paused.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    /// dbg!(conn.tube_pause_remaining("default").await.unwrap());
+    /// }
+    /// ```
+    pub async fn tube_pause_remaining(&mut self, name: &str) -> BeanstalkcResult<Duration> {
+        let stats = self
+            .send_tube(command::stats_tube(&self.namespaced(name)), name)
+            .await?
+            .body_as_map()?;
+        let left = stats.get("pause-time-left").map_or("0", String::as_str);
+        Ok(Duration::from_secs(left.parse()?))
+    }
+
     /// Delete job by job id.
     ///
     /// # Examples
@@ -600,10 +2682,44 @@ impl Beanstalkc {
     /// }
     /// ```
     pub async fn delete(&mut self, job_id: u64) -> BeanstalkcResult<()> {
+        self.reserved_jobs.remove(&job_id);
         self.send(command::delete(job_id)).await.map(|_| ())
     }
 
-    /// Release a reserved job back into the ready queue with default priority and delay.
+    /// Delete jobs by job ids, writing the commands in batches before reading their
+    /// responses instead of waiting for each one. Return the result of each delete in
+    /// order, or the connection error which interrupted them.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let ids: Vec<u64> = (1..=5000).collect();
+    /// let deleted = conn.delete_many(&ids).await.unwrap();
+    /// println!("{} deleted", deleted.iter().filter(|r| r.is_ok()).count());
+    /// }
+    /// ```
+    pub async fn delete_many(
+        &mut self,
+        job_ids: &[u64],
+    ) -> BeanstalkcResult<Vec<BeanstalkcResult<()>>> {
+        for job_id in job_ids {
+            self.reserved_jobs.remove(job_id);
+        }
+        let cmds = job_ids
+            .iter()
+            .map(|&job_id| command::delete(job_id))
+            .collect();
+        self.send_many(cmds).await
+    }
+
+    /// Release a reserved job back into the ready queue with the default priority and delay
+    /// configured on the builder.
     ///
     /// # Example
     ///
@@ -618,8 +2734,8 @@ impl Beanstalkc {
     /// }
     /// ```
     pub async fn release_default(&mut self, job_id: u64) -> BeanstalkcResult<()> {
-        self.release(job_id, DEFAULT_JOB_PRIORITY, DEFAULT_JOB_DELAY)
-            .await
+        let (priority, delay) = self.release_defaults();
+        self.release(job_id, priority, delay).await
     }
 
     /// Release a reserved job back into the ready queue.
@@ -643,12 +2759,13 @@ impl Beanstalkc {
         priority: u32,
         delay: Duration,
     ) -> BeanstalkcResult<()> {
+        self.reserved_jobs.remove(&job_id);
         self.send(command::release(job_id, priority, delay))
             .await
             .map(|_| ())
     }
 
-    /// Bury a specific job with default priority.
+    /// Bury a specific job with the default priority configured on the builder.
     ///
     /// # Example
     ///
@@ -663,7 +2780,7 @@ impl Beanstalkc {
     /// }
     /// ```
     pub async fn bury_default(&mut self, job_id: u64) -> BeanstalkcResult<()> {
-        self.bury(job_id, DEFAULT_JOB_PRIORITY).await
+        self.bury(job_id, self.default_priority).await
     }
 
     /// Bury a specific job.
@@ -681,9 +2798,27 @@ impl Beanstalkc {
     /// }
     /// ```
     pub async fn bury(&mut self, job_id: u64, priority: u32) -> BeanstalkcResult<()> {
+        self.reserved_jobs.remove(&job_id);
         self.send(command::bury(job_id, priority)).await.map(|_| ())
     }
 
+    /// Bury jobs given as `(job_id, priority)` pairs, writing the commands in batches like
+    /// [`Beanstalkc::delete_many`]. Only jobs reserved through this connection can be
+    /// buried.
+    pub async fn bury_many(
+        &mut self,
+        jobs: &[(u64, u32)],
+    ) -> BeanstalkcResult<Vec<BeanstalkcResult<()>>> {
+        for (job_id, _) in jobs {
+            self.reserved_jobs.remove(job_id);
+        }
+        let cmds = jobs
+            .iter()
+            .map(|&(job_id, priority)| command::bury(job_id, priority))
+            .collect();
+        self.send_many(cmds).await
+    }
+
     /// Touch a job by `job_id`. Allowing the worker to request more time on a reserved
     /// job before it expires.
     ///
@@ -722,29 +2857,602 @@ impl Beanstalkc {
         self.send(command::stats_job(job_id)).await?.body_as_map()
     }
 
-    async fn send(&mut self, cmd: command::Command<'_>) -> BeanstalkcResult<Response> {
+    pub(crate) async fn send(
+        &mut self,
+        mut cmd: command::Command<'_>,
+    ) -> BeanstalkcResult<Response> {
+        let resp = self.send_raw(&mut cmd).await?;
+        check_status(&cmd, resp)
+    }
+
+    /// Send a command about tube `name`, failing with `BeanstalkcError::TubeNotFound` if
+    /// the tube does not exist.
+    async fn send_tube(
+        &mut self,
+        mut cmd: command::Command<'_>,
+        name: &str,
+    ) -> BeanstalkcResult<Response> {
+        let resp = self.send_raw(&mut cmd).await?;
+        if resp.status == Status::NotFound {
+            return Err(BeanstalkcError::TubeNotFound(name.to_string()));
+        }
+        check_status(&cmd, resp)
+    }
+
+    /// Send a command through the interceptors and return the response without checking
+    /// its status.
+    async fn send_raw(&mut self, cmd: &mut command::Command<'_>) -> BeanstalkcResult<Response> {
+        for interceptor in &self.interceptors {
+            interceptor.before(cmd);
+        }
+
+        let start = Instant::now();
+        let resp = self.write_guarded(cmd).await;
+        if let Ok(resp) = &resp {
+            self.tube_state.update(cmd, resp.status);
+        }
+
+        if !self.interceptors.is_empty() {
+            let result = resp.as_ref().map(|r| r.status).map_err(Clone::clone);
+            for interceptor in &self.interceptors {
+                interceptor.after(cmd, &result, start.elapsed());
+            }
+        }
+        resp
+    }
+
+    /// Send a command through the interceptors, copying its body from `body` if any, and
+    /// the body of its response into `sink` if any. Return the response without checking
+    /// its status. Commands sent this way are never retried.
+    async fn send_streamed<R, W>(
+        &mut self,
+        cmd: &mut command::Command<'_>,
+        body: Option<(&mut R, u64)>,
+        sink: Option<&mut W>,
+    ) -> BeanstalkcResult<Response>
+    where
+        R: AsyncRead + Unpin + ?Sized,
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        for interceptor in &self.interceptors {
+            interceptor.before(cmd);
+        }
+
+        let start = Instant::now();
+        let resp = match self.check_guard().await {
+            Ok(()) => {
+                let resp = self.write_streamed(cmd, body, sink).await;
+                self.record_guard(&resp);
+                resp
+            }
+            Err(err) => Err(err),
+        };
+
+        if !self.interceptors.is_empty() {
+            let result = resp.as_ref().map(|r| r.status).map_err(Clone::clone);
+            for interceptor in &self.interceptors {
+                interceptor.after(cmd, &result, start.elapsed());
+            }
+        }
+        resp
+    }
+
+    /// Put jobs in a single write, then read their ids in order.
+    pub(crate) async fn put_pipelined(
+        &mut self,
+        requests: &[PutRequest],
+    ) -> BeanstalkcResult<Vec<BeanstalkcResult<u64>>> {
+        self.throttle_puts(requests.len()).await?;
+        let cmds = requests
+            .iter()
+            .map(|r| {
+                let (priority, delay, ttr) = self.put_options(r);
+                command::put(&r.body, priority, delay, ttr)
+            })
+            .collect();
+        Ok(self
+            .send_pipelined(cmds)
+            .await?
+            .into_iter()
+            .map(|resp| resp?.job_id())
+            .collect())
+    }
+
+    /// Send commands without a response body in batches of `MAX_PIPELINED_COMMANDS`, so
+    /// that the server never blocks on responses not read yet.
+    async fn send_many(
+        &mut self,
+        cmds: Vec<command::Command<'_>>,
+    ) -> BeanstalkcResult<Vec<BeanstalkcResult<()>>> {
+        let mut results = Vec::with_capacity(cmds.len());
+        let mut cmds = cmds.into_iter().peekable();
+        while cmds.peek().is_some() {
+            let batch = cmds.by_ref().take(MAX_PIPELINED_COMMANDS).collect();
+            let resps = self.send_pipelined(batch).await?;
+            results.extend(resps.into_iter().map(|resp| resp.map(|_| ())));
+        }
+        Ok(results)
+    }
+
+    /// Send commands in a single write, then read their responses in order and check
+    /// their status.
+    async fn send_pipelined(
+        &mut self,
+        mut cmds: Vec<command::Command<'_>>,
+    ) -> BeanstalkcResult<Vec<BeanstalkcResult<Response>>> {
+        for cmd in &mut cmds {
+            for interceptor in &self.interceptors {
+                interceptor.before(cmd);
+            }
+        }
+
+        let start = Instant::now();
+        let resps = self.check_guard().await;
+        let resps = match resps {
+            Ok(()) => {
+                let resps = self.write_pipelined(&cmds).await;
+                self.record_guard(&resps);
+                resps
+            }
+            Err(err) => Err(err),
+        };
+
+        for (i, cmd) in cmds.iter().enumerate() {
+            let result = match &resps {
+                Ok(resps) => Ok(resps[i].status),
+                Err(err) => Err(err.clone()),
+            };
+            for interceptor in &self.interceptors {
+                interceptor.after(cmd, &result, start.elapsed());
+            }
+        }
+
+        Ok(cmds
+            .iter()
+            .zip(resps?)
+            .map(|(cmd, resp)| check_status(cmd, resp))
+            .collect())
+    }
+
+    /// Wait until `count` jobs can be put into the current tube under its rate limit.
+    async fn throttle_puts(&mut self, count: usize) -> BeanstalkcResult<()> {
+        let tube = self.tube_state.using.as_deref().unwrap_or(DEFAULT_TUBE);
+        let tube = tube
+            .strip_prefix(self.namespace.as_str())
+            .unwrap_or(tube)
+            .to_string();
+        let wait = self.put_limiter.acquire(&tube, count);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        self.check_backlog(&tube, count).await
+    }
+
+    /// Wait until the backlog of `tube` is under the configured limit, or fail with
+    /// `BeanstalkcError::BackpressureExceeded` if puts over the limit are rejected.
+    async fn check_backlog(&mut self, tube: &str, count: usize) -> BeanstalkcResult<()> {
+        let limit = match self.backlog.limit() {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+        loop {
+            let ready = match self.backlog.ready(tube) {
+                Some(ready) => ready,
+                None => {
+                    let ready = self.fetch_ready(tube).await?;
+                    self.backlog.fetched(tube, ready);
+                    ready
+                }
+            };
+            if ready < limit.max_ready {
+                self.backlog.put(tube, count);
+                return Ok(());
+            }
+            match limit.action {
+                BacklogAction::Reject => {
+                    return Err(BeanstalkcError::BackpressureExceeded {
+                        tube: tube.to_string(),
+                        ready,
+                        limit: limit.max_ready,
+                    })
+                }
+                BacklogAction::Wait => tokio::time::sleep(limit.check_interval).await,
+            }
+        }
+    }
+
+    /// Return the number of ready jobs in `tube`, which has none if it does not exist.
+    async fn fetch_ready(&mut self, tube: &str) -> BeanstalkcResult<u64> {
+        let cmd = command::stats_tube(&self.namespaced(tube));
+        let stats = match self.send_tube(cmd, tube).await {
+            Ok(resp) => resp.body_as_map()?,
+            Err(BeanstalkcError::TubeNotFound(_)) => return Ok(0),
+            Err(err) => return Err(err),
+        };
+        Ok(stats
+            .get("current-jobs-ready")
+            .and_then(|ready| ready.parse().ok())
+            .unwrap_or(0))
+    }
+
+    /// Write a command through the circuit breaker, if any. The server closes the
+    /// connection on `quit` without answering, which is not recorded as a failure.
+    async fn write_guarded(&mut self, cmd: &command::Command<'_>) -> BeanstalkcResult<Response> {
+        self.check_guard().await?;
+        let resp = self.write_with_retry(cmd).await;
+        if cmd.kind() != command::CommandKind::Quit {
+            self.record_guard(&resp);
+        }
+        resp
+    }
+
+    /// Fail fast while the circuit is open, then recycle the connection if it is idle.
+    /// Reconnects record their own result into the circuit breaker.
+    async fn check_guard(&mut self) -> BeanstalkcResult<()> {
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker.check()?;
+        }
+        self.recover_cancelled().await;
+        if self.resync && self.stream.is_none() && self.supervisor.is_none() {
+            self.reopen(1).await?;
+        }
+        self.supervise().await?;
+        self.recycle_idle().await?;
+        if !self.abandoned_jobs.is_empty() {
+            self.release_abandoned().await;
+        }
+        Ok(())
+    }
+
+    /// Read the responses owed to commands whose future was dropped before reading them,
+    /// and release the jobs they reserved. A connection left with a message cut short, or
+    /// still waiting for a response, is dropped instead and replaced before the next command:
+    /// the server then releases the jobs it had reserved.
+    async fn recover_cancelled(&mut self) {
+        let stream = match self.stream.as_mut() {
+            Some(stream) if self.exchange.pending() > 0 || self.exchange.is_torn() => stream,
+            _ => return,
+        };
+
+        let pending = self.exchange.pending();
+        let torn = self.exchange.is_torn();
+        let mut request = Request::new(stream, &mut self.exchange)
+            .debug(self.debug_protocol)
+            .max_body(self.max_body_size);
+        let orphans: Option<Vec<u64>> = async {
+            if torn {
+                return None;
+            }
+            let mut orphans = vec![];
+            for _ in 0..pending {
+                let read = tokio::time::timeout(ORPHAN_RESPONSE_TIMEOUT, request.read()).await;
+                let resp = read.ok()?.ok()?;
+                if resp.status == Status::Reserved {
+                    orphans.push(resp.job_id().ok()?);
+                }
+            }
+            Some(orphans)
+        }
+        .await;
+
+        match orphans {
+            Some(orphans) => self.abandoned_jobs.extend(orphans),
+            None => self.connection_failed(&BeanstalkcError::ProtocolDesync(
+                "command cancelled before its response was read".to_string(),
+            )),
+        }
+    }
+
+    /// Record a reserved job given up without being finalized, to release it before the
+    /// next command.
+    pub(crate) fn abandon_job(&mut self, job_id: u64) {
+        if self.stream.is_some() {
+            self.abandoned_jobs.push(job_id);
+        }
+    }
+
+    /// Release the abandoned jobs with their priority. Failures are ignored, the jobs are
+    /// then released by the server once their TTR expires.
+    pub(crate) async fn release_abandoned(&mut self) {
+        let (default_priority, delay) = self.release_defaults();
+        for id in std::mem::take(&mut self.abandoned_jobs) {
+            self.reserved_jobs.remove(&id);
+            let priority = match self.write_command(&command::stats_job(id)).await {
+                Ok(resp) => resp
+                    .body_as_map()
+                    .ok()
+                    .and_then(|stats| stats.get("pri")?.parse().ok())
+                    .unwrap_or(default_priority),
+                Err(_) => default_priority,
+            };
+            let _ = self
+                .write_command(&command::release(id, priority, delay))
+                .await;
+        }
+    }
+
+    /// Record the result of a write into the circuit breaker, if any.
+    fn record_guard<T>(&mut self, result: &BeanstalkcResult<T>) {
+        if let Some(breaker) = &mut self.circuit_breaker {
+            breaker.record(result);
+        }
+    }
+
+    /// Ping a connection idle for longer than the idle timeout, and reconnect if it does not
+    /// answer in time.
+    async fn recycle_idle(&mut self) -> BeanstalkcResult<()> {
+        let idle = match (self.idle_timeout, self.last_used) {
+            (Some(timeout), Some(last_used)) => last_used.elapsed() >= timeout,
+            _ => false,
+        };
+        if !idle || self.stream.is_none() {
+            return Ok(());
+        }
+
+        let ping = command::using();
+        match tokio::time::timeout(IDLE_PING_TIMEOUT, self.write_command(&ping)).await {
+            Ok(Ok(_)) => return Ok(()),
+            Ok(Err(_)) => {}
+            Err(_) => self.disconnected(Some(&BeanstalkcError::ConnectionError(
+                "idle connection did not answer ping".to_string(),
+            ))),
+        }
+        self.reopen(1).await
+    }
+
+    /// Write a command, reconnecting and writing it again while the retry policy allows.
+    async fn write_with_retry(&mut self, cmd: &command::Command<'_>) -> BeanstalkcResult<Response> {
+        let policy = match &self.retry_policy {
+            Some(policy) if policy.applies_to(cmd.kind()) => policy.clone(),
+            _ => return self.write_command(cmd).await,
+        };
+
+        let mut attempt = 1;
+        loop {
+            let resp = if attempt == 1 {
+                self.write_command(cmd).await
+            } else {
+                match self.reopen(attempt - 1).await {
+                    Ok(()) => self.write_command(cmd).await,
+                    Err(err) => Err(err),
+                }
+            };
+            match resp {
+                Err(err) if attempt < policy.max_attempts && policy.is_retryable(&err) => {
+                    tokio::time::sleep(policy.delay(attempt)).await;
+                    attempt += 1;
+                }
+                resp => return resp,
+            }
+        }
+    }
+
+    /// Write a command with its body copied from `body`, then read its response with its
+    /// body copied into `sink`.
+    async fn write_streamed<R, W>(
+        &mut self,
+        cmd: &command::Command<'_>,
+        body: Option<(&mut R, u64)>,
+        sink: Option<&mut W>,
+    ) -> BeanstalkcResult<Response>
+    where
+        R: AsyncRead + Unpin + ?Sized,
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let stream = match self.stream.as_mut() {
+            Some(stream) => stream,
+            None => {
+                return Err(BeanstalkcError::ConnectionError(
+                    "invalid connection".to_string(),
+                ))
+            }
+        };
+
+        let mut request = Request::new(stream, &mut self.exchange)
+            .debug(self.debug_protocol)
+            .max_body(self.max_body_size);
+        let resp: BeanstalkcResult<Response> = async {
+            match body {
+                Some((reader, len)) => {
+                    let head = cmd.build_head(len);
+                    request.write_from(head.as_bytes(), reader, len).await?
+                }
+                None => request.write(&[cmd.build().as_bytes()]).await?,
+            }
+            let resp = match sink {
+                Some(writer) => request.read_into(writer).await?,
+                None => request.read().await?,
+            };
+            check_sequence(cmd, resp)
+        }
+        .await;
+
+        match &resp {
+            Ok(_) => self.last_used = Some(Instant::now()),
+            Err(err) if err.is_connection_error() => self.connection_failed(err),
+            Err(_) => {}
+        }
+        resp
+    }
+
+    /// Write commands at once, then read their responses in order.
+    async fn write_pipelined(
+        &mut self,
+        cmds: &[command::Command<'_>],
+    ) -> BeanstalkcResult<Vec<Response>> {
+        let stream = match self.stream.as_mut() {
+            Some(stream) => stream,
+            None => {
+                return Err(BeanstalkcError::ConnectionError(
+                    "invalid connection".to_string(),
+                ))
+            }
+        };
+
+        let messages: Vec<String> = cmds.iter().map(|cmd| cmd.build()).collect();
+        let messages: Vec<&[u8]> = messages.iter().map(|m| m.as_bytes()).collect();
+        let mut request = Request::new(stream, &mut self.exchange)
+            .debug(self.debug_protocol)
+            .max_body(self.max_body_size);
+        let resps: BeanstalkcResult<Vec<Response>> = async {
+            request.write(&messages).await?;
+            let mut resps = Vec::with_capacity(cmds.len());
+            for cmd in cmds {
+                resps.push(check_sequence(cmd, request.read().await?)?);
+            }
+            Ok(resps)
+        }
+        .await;
+
+        match &resps {
+            Ok(_) => self.last_used = Some(Instant::now()),
+            Err(err) if err.is_connection_error() => self.connection_failed(err),
+            Err(_) => {}
+        }
+        resps
+    }
+
+    async fn write_command(&mut self, cmd: &command::Command<'_>) -> BeanstalkcResult<Response> {
         if self.stream.is_none() {
             return Err(BeanstalkcError::ConnectionError(
                 "invalid connection".to_string(),
             ));
         }
 
-        let mut request = Request::new(self.stream.as_mut().unwrap());
-        let resp = request.send(cmd.build().as_bytes()).await?;
+        let stream = self.stream.as_mut().unwrap();
+        let mut request = Request::new(stream, &mut self.exchange)
+            .debug(self.debug_protocol)
+            .max_body(self.max_body_size);
+        let resp = request.send(cmd.build().as_bytes()).await;
+        let drained = match &resp {
+            Err(BeanstalkcError::ProtocolDesync(_)) if self.drain_on_desync => {
+                request.drain().await
+            }
+            _ => false,
+        };
+        let resp = resp.and_then(|resp| check_sequence(cmd, resp));
+        match &resp {
+            Ok(_) => self.last_used = Some(Instant::now()),
+            Err(_) if drained => {}
+            Err(err) if err.is_connection_error() && cmd.kind() != command::CommandKind::Quit => {
+                self.connection_failed(err);
+            }
+            Err(_) => {}
+        }
+        resp
+    }
+}
 
-        if cmd.expected_ok_status.contains(&resp.status) {
-            Ok(resp)
-        } else if cmd.expected_error_status.contains(&resp.status) {
-            Err(BeanstalkcError::CommandFailed(format!("{:?}", resp.status)))
-        } else {
-            Err(BeanstalkcError::UnexpectedResponse(format!(
-                "{:?}",
-                resp.status
-            )))
+/// Tubes selected on the current connection, kept to restore them after a reconnect.
+#[derive(Debug, Default, Clone)]
+struct TubeState {
+    using: Option<String>,
+    watching: Option<Vec<String>>,
+}
+
+impl TubeState {
+    /// Record the effect of a successful `use`, `watch` or `ignore` command.
+    fn update(&mut self, cmd: &command::Command<'_>, status: Status) {
+        let name = match cmd.args().first() {
+            Some(name) => name.clone(),
+            None => return,
+        };
+        match (cmd.kind(), status) {
+            (command::CommandKind::Use, Status::Using) => self.using = Some(name),
+            (command::CommandKind::Watch, Status::Watching) => {
+                let watching = self.watching_mut();
+                if !watching.contains(&name) {
+                    watching.push(name);
+                }
+            }
+            (command::CommandKind::Ignore, Status::Watching) => {
+                self.watching_mut().retain(|x| *x != name);
+            }
+            _ => {}
+        }
+    }
+
+    fn watching_mut(&mut self) -> &mut Vec<String> {
+        self.watching
+            .get_or_insert_with(|| vec![DEFAULT_TUBE.to_string()])
+    }
+
+    /// Return the commands selecting these tubes on a new connection.
+    fn restore(&self) -> Vec<command::Command<'static>> {
+        let mut cmds = vec![];
+        if let Some(name) = &self.using {
+            cmds.push(command::use_tube(name));
+        }
+        if let Some(watching) = &self.watching {
+            for name in watching.iter().filter(|x| *x != DEFAULT_TUBE) {
+                cmds.push(command::watch(name));
+            }
+            if !watching.iter().any(|x| x == DEFAULT_TUBE) {
+                cmds.push(command::ignore(DEFAULT_TUBE));
+            }
         }
+        cmds
     }
 }
 
+/// Return the error for a failed command status.
+pub(crate) fn command_failed(status: Status) -> BeanstalkcError {
+    BeanstalkcError::CommandFailed(format!("{:?}", status))
+}
+
+/// Fail with `BeanstalkcError::ProtocolDesync` if `resp` cannot answer `cmd`, meaning that
+/// responses no longer match the commands issued on the connection. The connection is then
+/// poisoned: it is closed, and replaced before the next command.
+fn check_sequence(cmd: &command::Command<'_>, resp: Response) -> BeanstalkcResult<Response> {
+    if cmd.is_answered_by(resp.status) {
+        return Ok(resp);
+    }
+    Err(desync(&format!(
+        "{:?} response to {}",
+        resp.status,
+        cmd.name()
+    )))
+}
+
+/// Round `delay` up to whole seconds, failing with `BeanstalkcError::InvalidSchedule` if it
+/// is longer than the delays beanstalkd accepts.
+fn job_delay(delay: Duration) -> BeanstalkcResult<Duration> {
+    let delay = round_up(delay);
+    if delay > MAX_JOB_DELAY {
+        return Err(BeanstalkcError::InvalidSchedule(format!(
+            "delay of {}s exceeds the maximum of {}s",
+            delay.as_secs(),
+            MAX_JOB_DELAY.as_secs()
+        )));
+    }
+    Ok(delay)
+}
+
+/// Fail with `BeanstalkcError::NonUtf8Body` if the body of job `id` is not valid UTF-8.
+fn check_utf8(id: u64, body: &[u8]) -> BeanstalkcResult<()> {
+    std::str::from_utf8(body)
+        .map(|_| ())
+        .map_err(|error| BeanstalkcError::NonUtf8Body { id, error })
+}
+
+/// Map the response status to an error unless it is expected by the command.
+fn check_status(cmd: &command::Command<'_>, resp: Response) -> BeanstalkcResult<Response> {
+    if cmd.expected_ok_status.contains(&resp.status) {
+        return Ok(resp);
+    }
+    let err = if cmd.expected_error_status.contains(&resp.status) {
+        command_failed(resp.status)
+    } else {
+        BeanstalkcError::UnexpectedStatus {
+            command: cmd.kind(),
+            status: resp.status,
+            params: resp.params,
+        }
+    };
+    logging::command_failed(cmd, &err);
+    Err(err)
+}
+
 // TODO: Document that self.close should be explicitly called if desired
 // impl Drop for Beanstalkc {
 //     fn drop(&mut self) {
@@ -757,3 +3465,272 @@ impl Default for Beanstalkc {
         Beanstalkc::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+
+    #[tokio::test]
+    async fn test_open_restore_failed() {
+        let server = FakeServer::start().await;
+        let mut conn = server.client().connect().await.unwrap();
+        conn.use_tube("emails").await.unwrap();
+
+        server.reply("use emails", "BAD_FORMAT");
+        assert!(conn.open().await.is_err());
+        assert!(!conn.is_connected());
+        assert_eq!("emails", conn.current_tube());
+        assert!(conn.put_default(b"hello").await.is_err());
+        assert!(server.jobs_in("ready").is_empty());
+    }
+
+    struct FailingRemove;
+
+    impl DedupStore for FailingRemove {
+        fn insert<'a>(
+            &'a self,
+            _key: &'a str,
+            _ttl: Duration,
+        ) -> BoxFuture<'a, BeanstalkcResult<bool>> {
+            futures::future::ready(Ok(true)).boxed()
+        }
+
+        fn remove<'a>(&'a self, _key: &'a str) -> BoxFuture<'a, BeanstalkcResult<()>> {
+            let err = BeanstalkcError::ConnectionError("store is down".to_string());
+            futures::future::ready(Err(err)).boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_put_unique() {
+        let server = FakeServer::start().await;
+        let mut conn = server.client().connect().await.unwrap();
+        let request = PutRequest::new(b"job".to_vec());
+
+        let id = conn.put_unique("a", request.clone()).await.unwrap();
+        assert!(id.is_some());
+        assert_eq!(None, conn.put_unique("a", request.clone()).await.unwrap());
+
+        server.reply("put 2147483648 0 120 4", "JOB_TOO_BIG");
+        let request = PutRequest::new(b"next".to_vec());
+        for _ in 0..2 {
+            assert!(matches!(
+                conn.put_unique("b", request.clone()).await,
+                Err(BeanstalkcError::CommandFailed(_))
+            ));
+        }
+
+        server.reply("put 2147483648 0 120 4", "GARBAGE");
+        assert!(conn.put_unique("c", request.clone()).await.is_err());
+        assert_eq!(None, conn.put_unique("c", request).await.unwrap());
+        assert_eq!(
+            3,
+            server
+                .commands()
+                .iter()
+                .filter(|c| *c == "put 2147483648 0 120 4")
+                .count()
+        );
+        assert_eq!(vec![id.unwrap()], server.jobs_in("ready"));
+    }
+
+    #[tokio::test]
+    async fn test_put_unique_keeps_put_error() {
+        let server = FakeServer::start().await;
+        server.reply("put 2147483648 0 120 3", "JOB_TOO_BIG");
+        let mut conn = server
+            .client()
+            .dedup_store(Arc::new(FailingRemove), Duration::from_secs(60))
+            .connect()
+            .await
+            .unwrap();
+
+        let put = conn.put_unique("a", PutRequest::new(b"job".to_vec())).await;
+        assert_eq!("Command failed: JobTooBig", put.unwrap_err().to_string());
+    }
+
+    #[tokio::test]
+    async fn test_reserve_into_long_line() {
+        let server = FakeServer::start().await;
+        server.reply("reserve", &"x".repeat(4096));
+        let mut conn = server.client().connect().await.unwrap();
+
+        let mut body = vec![];
+        assert!(matches!(
+            conn.reserve_into(&mut body).await.map(|job| job.id()),
+            Err(BeanstalkcError::ProtocolDesync(reason)) if reason == "response line too long"
+        ));
+    }
+
+    #[test]
+    fn test_job_delay() {
+        assert_eq!(
+            Duration::from_secs(0),
+            job_delay(Duration::from_secs(0)).unwrap()
+        );
+        assert_eq!(
+            Duration::from_secs(1),
+            job_delay(Duration::from_nanos(1)).unwrap()
+        );
+        assert_eq!(
+            Duration::from_secs(2),
+            job_delay(Duration::from_millis(1500)).unwrap()
+        );
+        assert_eq!(
+            Duration::from_secs(2),
+            job_delay(Duration::from_secs(2)).unwrap()
+        );
+        assert_eq!(MAX_JOB_DELAY, job_delay(MAX_JOB_DELAY).unwrap());
+        assert!(matches!(
+            job_delay(MAX_JOB_DELAY + Duration::from_nanos(1)),
+            Err(BeanstalkcError::InvalidSchedule(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_at() {
+        let server = FakeServer::start().await;
+        let mut conn = server.client().connect().await.unwrap();
+        conn.put_at(b"late", SystemTime::UNIX_EPOCH).await.unwrap();
+        conn.put_after(b"soon", Duration::from_millis(1500))
+            .await
+            .unwrap();
+        let at = SystemTime::now() + MAX_JOB_DELAY + Duration::from_secs(10);
+        assert!(matches!(
+            conn.put_at(b"never", at).await,
+            Err(BeanstalkcError::InvalidSchedule(_))
+        ));
+        assert_eq!(
+            vec!["put 2147483648 0 120 4", "put 2147483648 2 120 4"],
+            server.commands()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kick_tube() {
+        let server = FakeServer::start().await;
+        server.reply("kick 10", "KICKED 2");
+        let mut conn = server.client().connect().await.unwrap();
+        assert_eq!(2, conn.kick_tube("emails", 10).await.unwrap());
+        assert_eq!(2, conn.kick_tube("default", 10).await.unwrap());
+        assert_eq!(
+            vec!["use emails", "kick 10", "use default", "kick 10"],
+            server.commands()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_configured_defaults() {
+        let server = FakeServer::start().await;
+        let mut conn = server
+            .client()
+            .default_priority(5)
+            .default_delay(Duration::from_secs(2))
+            .default_ttr(Duration::from_secs(30))
+            .connect()
+            .await
+            .unwrap();
+        let request = PutRequest::new(b"job".to_vec()).delay(Duration::from_secs(0));
+        conn.put_request(&request).await.unwrap();
+        conn.release_default(1).await.unwrap_err();
+        conn.bury_default(1).await.unwrap_err();
+        assert_eq!(
+            vec!["put 5 0 30 3", "release 1 5 2", "bury 1 5"],
+            server.commands()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_guards_connects() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut conn = Beanstalkc::new()
+            .host("127.0.0.1")
+            .port(port)
+            .circuit_breaker(CircuitBreaker::new(1, Duration::from_secs(60)));
+        assert!(conn.open().await.unwrap_err().is_connection_error());
+        assert!(matches!(
+            conn.open().await,
+            Err(BeanstalkcError::CircuitOpen(_))
+        ));
+
+        let server = FakeServer::start().await;
+        let mut conn = server
+            .client()
+            .circuit_breaker(CircuitBreaker::new(1, Duration::from_secs(60)))
+            .connect()
+            .await
+            .unwrap();
+        conn.close().await;
+        assert!(!conn.is_circuit_open());
+    }
+
+    #[tokio::test]
+    async fn test_reserve_buffered_max_body_size() {
+        let server = FakeServer::start().await;
+        let oversized = server.put("default", b"oversized");
+        let small = server.put("default", b"tiny");
+        let mut conn = server
+            .client()
+            .max_body_size(4, OversizeAction::Bury)
+            .connect()
+            .await
+            .unwrap();
+
+        let mut buf = vec![];
+        assert!(matches!(
+            conn.peek_buffered(oversized, &mut buf).await,
+            Err(BeanstalkcError::BodyTooLarge { size: 9, .. })
+        ));
+        assert!(matches!(
+            conn.reserve_buffered(&mut buf).await,
+            Err(BeanstalkcError::BodyTooLarge { id, .. }) if id == oversized
+        ));
+        assert_eq!(Some("buried"), server.state(oversized));
+
+        let job = conn.reserve_buffered(&mut buf).await.unwrap();
+        assert_eq!((small, &b"tiny"[..]), (job.id(), job.body()));
+    }
+
+    #[tokio::test]
+    async fn test_reserve_with_timeout_until_rounds_up() {
+        let server = FakeServer::start().await;
+        let mut conn = server.client().connect().await.unwrap();
+
+        let job = conn
+            .reserve_with_timeout_until(
+                Duration::from_millis(1500),
+                futures::future::pending::<()>(),
+            )
+            .await;
+        assert!(matches!(job, Err(BeanstalkcError::CommandFailed(_))));
+        let reserves = server
+            .commands()
+            .into_iter()
+            .filter(|cmd| cmd.starts_with("reserve"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec!["reserve-with-timeout 1", "reserve-with-timeout 1"],
+            reserves
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reserve_many_skips_non_utf8() {
+        let server = FakeServer::start().await;
+        let invalid = server.put("default", &[b'a', 0xff]);
+        let valid = server.put("default", b"text");
+        let mut conn = server.client().assume_utf8(true).connect().await.unwrap();
+
+        let batch = conn.reserve_many(2, Duration::from_secs(0)).await.unwrap();
+        assert_eq!(
+            vec![valid],
+            batch.jobs().iter().map(|job| job.id()).collect::<Vec<_>>()
+        );
+        assert_eq!(Some("buried"), server.state(invalid));
+    }
+}
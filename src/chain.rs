@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use futures::FutureExt;
+
+use crate::envelope::Envelope;
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::put::PutRequest;
+use crate::Beanstalkc;
+
+/// Header of a chained job listing the steps left after it.
+const NEXT_HEADER: &str = "x-chain-next";
+
+/// Start an empty [`Chain`].
+pub fn chain() -> Chain {
+    Chain::default()
+}
+
+/// `Chain` runs jobs one after another, each step in its own tube.
+///
+/// Only the first step is put by [`Chain::enqueue`]. Each job is wrapped into an
+/// [`Envelope`] listing the steps left after it, and the next step is put when the job is
+/// deleted by [`Worker`](crate::Worker), [`Job::run`](crate::Job::run) or
+/// [`Beanstalkc::process`] with `Outcome::Delete`. Handlers read the body of their step
+/// with [`Envelope::decode`].
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use beanstalkc::{chain, Beanstalkc, Envelope, Outcome, Worker};
+///
+/// let mut conn = Beanstalkc::new().connect().await.unwrap();
+///
+/// chain()
+///     .then("resize", b"photo-42".to_vec())
+///     .then("upload", b"photo-42-small".to_vec())
+///     .enqueue(&mut conn)
+///     .await
+///     .unwrap();
+///
+/// Worker::new(conn)
+///     .watch("resize")
+///     .watch("upload")
+///     .run(|job| async move {
+///         let envelope = Envelope::decode(job.body()).map_err(|e| e.to_string())?;
+///         dbg!(envelope.body());
+///         Ok::<_, String>(Outcome::Delete)
+///     })
+///     .await
+///     .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chain {
+    steps: Vec<(String, PutRequest)>,
+}
+
+impl Chain {
+    /// Append a step putting `request` into `tube`.
+    pub fn then<R: Into<PutRequest>>(mut self, tube: &str, request: R) -> Self {
+        self.steps.push((tube.to_string(), request.into()));
+        self
+    }
+
+    /// Put the first step through `conn` and return its job id, or `None` if the chain is
+    /// empty. The tube used by `conn` is left unchanged.
+    pub async fn enqueue(self, conn: &mut Beanstalkc) -> BeanstalkcResult<Option<u64>> {
+        let mut steps = self.steps;
+        if steps.is_empty() {
+            return Ok(None);
+        }
+        let (tube, request) = steps.remove(0);
+        put_step(conn, tube, request, &steps).await.map(Some)
+    }
+}
+
+/// Put the step following the chained job `body`, if any.
+pub(crate) async fn advance(conn: &mut Beanstalkc, body: &[u8]) -> BeanstalkcResult<()> {
+    let next = match Envelope::decode(body) {
+        Ok(envelope) => match envelope.get_header(NEXT_HEADER) {
+            Some(next) => decode_steps(next)?,
+            None => return Ok(()),
+        },
+        Err(_) => return Ok(()),
+    };
+    let mut steps = next.into_iter();
+    if let Some((tube, request)) = steps.next() {
+        let rest: Vec<_> = steps.collect();
+        put_step(conn, tube, request, &rest).await?;
+    }
+    Ok(())
+}
+
+/// Put a step into `tube`, recording the steps left after it.
+async fn put_step(
+    conn: &mut Beanstalkc,
+    tube: String,
+    request: PutRequest,
+    rest: &[(String, PutRequest)],
+) -> BeanstalkcResult<u64> {
+    let mut envelope = Envelope::new(request.body.clone());
+    if !rest.is_empty() {
+        envelope.set_header(NEXT_HEADER, &encode_steps(rest));
+    }
+    let body = envelope.encode();
+    let (priority, delay, ttr) = conn.put_options(&request);
+    conn.with_tube(&tube, move |c| {
+        async move { c.put(&body, priority, delay, ttr).await }.boxed()
+    })
+    .await
+}
+
+/// Encode steps as `tube priority delay ttr hex-body`, separated by commas, with `-` for
+/// the options left to the defaults of the client putting the step.
+fn encode_steps(steps: &[(String, PutRequest)]) -> String {
+    let option = |value: Option<u64>| value.map_or("-".to_string(), |value| value.to_string());
+    let steps: Vec<String> = steps
+        .iter()
+        .map(|(tube, request)| {
+            let body: String = request.body.iter().map(|b| format!("{:02x}", b)).collect();
+            format!(
+                "{} {} {} {} {}",
+                tube,
+                option(request.priority.map(u64::from)),
+                option(request.delay.map(|delay| delay.as_secs())),
+                option(request.ttr.map(|ttr| ttr.as_secs())),
+                body
+            )
+        })
+        .collect();
+    steps.join(",")
+}
+
+fn decode_steps(steps: &str) -> BeanstalkcResult<Vec<(String, PutRequest)>> {
+    steps
+        .split(',')
+        .map(|step| {
+            let fields: Vec<&str> = step.split(' ').collect();
+            if fields.len() != 5 || !fields[4].is_ascii() || fields[4].len() % 2 != 0 {
+                return Err(BeanstalkcError::UnexpectedResponse(format!(
+                    "malformed chain step: {}",
+                    step
+                )));
+            }
+            let body = (0..fields[4].len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&fields[4][i..i + 2], 16))
+                .collect::<Result<Vec<u8>, _>>()?;
+            let mut request = PutRequest::new(body);
+            if fields[1] != "-" {
+                request = request.priority(fields[1].parse()?);
+            }
+            if fields[2] != "-" {
+                request = request.delay(Duration::from_secs(fields[2].parse()?));
+            }
+            if fields[3] != "-" {
+                request = request.ttr(Duration::from_secs(fields[3].parse()?));
+            }
+            Ok((fields[0].to_string(), request))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps() {
+        let steps = chain()
+            .then("resize", b"a,b c".to_vec())
+            .then("upload", PutRequest::new(vec![]).priority(1))
+            .steps;
+        let encoded = encode_steps(&steps);
+        assert_eq!("resize - - - 612c622063,upload 1 - - ", encoded);
+        assert_eq!(steps, decode_steps(&encoded).unwrap());
+        assert_eq!(
+            vec![(
+                "upload".to_string(),
+                PutRequest::new(vec![0x61])
+                    .priority(1)
+                    .delay(Duration::from_secs(0))
+                    .ttr(Duration::from_secs(120))
+            )],
+            decode_steps("upload 1 0 120 61").unwrap()
+        );
+
+        assert!(decode_steps("resize 1 0 120").is_err());
+        assert!(decode_steps("resize 1 0 120 6").is_err());
+        assert!(decode_steps("resize 1 0 120 é").is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+
+/// `CircuitBreaker` stops a client from hammering an unavailable server.
+///
+/// After `failure_threshold` consecutive connection errors, from commands or from attempts
+/// to connect, the circuit opens and every command and connect fails fast with
+/// `BeanstalkcError::CircuitOpen` without touching the network.
+/// Once `cooldown` has elapsed the next command is sent as a probe: the circuit closes
+/// again if it succeeds, and re-opens for another `cooldown` if it fails.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use std::time::Duration;
+/// use beanstalkc::{Beanstalkc, CircuitBreaker};
+///
+/// let mut conn = Beanstalkc::new()
+///        .circuit_breaker(CircuitBreaker::new(5, Duration::from_secs(10)))
+///        .connect()
+///        .await
+///        .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    failures: u32,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Open the circuit after `failure_threshold` consecutive failures, for `cooldown`.
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            failures: 0,
+            opened_at: None,
+        }
+    }
+
+    /// Return whether commands are currently rejected.
+    pub fn is_open(&self) -> bool {
+        self.opened_at
+            .is_some_and(|opened_at| opened_at.elapsed() < self.cooldown)
+    }
+
+    /// Fail fast while the circuit is open.
+    pub(crate) fn check(&self) -> BeanstalkcResult<()> {
+        let elapsed = self.opened_at.map(|opened_at| opened_at.elapsed());
+        match elapsed {
+            Some(elapsed) if elapsed < self.cooldown => Err(BeanstalkcError::CircuitOpen(format!(
+                "{} consecutive failures, retry in {:?}",
+                self.failures,
+                self.cooldown.saturating_sub(elapsed)
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record the result of a command sent through the circuit.
+    pub(crate) fn record<T>(&mut self, result: &BeanstalkcResult<T>) {
+        match result {
+            Err(err) if err.is_connection_error() => {
+                self.failures = self.failures.saturating_add(1);
+                if self.failures >= self.failure_threshold {
+                    self.opened_at = Some(Instant::now());
+                }
+            }
+            Err(BeanstalkcError::CircuitOpen(_)) => {}
+            _ => {
+                self.failures = 0;
+                self.opened_at = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure() -> BeanstalkcResult<()> {
+        Err(BeanstalkcError::ConnectionError("refused".to_string()))
+    }
+
+    #[test]
+    fn test_open_after_threshold() {
+        let mut breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        breaker.record(&failure());
+        assert!(breaker.check().is_ok());
+        breaker.record(&Err::<(), _>(BeanstalkcError::CommandFailed(
+            "NotFound".to_string(),
+        )));
+        breaker.record(&failure());
+        assert!(breaker.check().is_ok());
+        breaker.record(&failure());
+        assert!(breaker.is_open());
+        let rejected = breaker.check();
+        assert!(matches!(rejected, Err(BeanstalkcError::CircuitOpen(_))));
+        breaker.record(&rejected);
+        assert!(breaker.is_open());
+    }
+
+    #[test]
+    fn test_half_open_probe() {
+        let mut breaker = CircuitBreaker::new(1, Duration::from_secs(0));
+        breaker.record(&failure());
+        assert!(breaker.check().is_ok());
+        breaker.record(&failure());
+        assert!(breaker.opened_at.is_some());
+        breaker.record(&Ok(()));
+        assert_eq!(0, breaker.failures);
+        assert!(breaker.opened_at.is_none());
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use futures::future::join_all;
+
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::response::numeric;
+use crate::Beanstalkc;
+
+/// `ServerStats` holds the main server stats of one server, or of several servers merged
+/// together: counts are summed, while `uptime` and `max-job-size` keep the largest value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct ServerStats {
+    /// `current-jobs-urgent`: ready jobs with a priority under 1024.
+    pub current_jobs_urgent: u64,
+    /// `current-jobs-ready`: ready jobs.
+    pub current_jobs_ready: u64,
+    /// `current-jobs-reserved`: jobs reserved by a client.
+    pub current_jobs_reserved: u64,
+    /// `current-jobs-delayed`: delayed jobs.
+    pub current_jobs_delayed: u64,
+    /// `current-jobs-buried`: buried jobs.
+    pub current_jobs_buried: u64,
+    /// `total-jobs`: jobs created since the server started.
+    pub total_jobs: u64,
+    /// `job-timeouts`: jobs released after their TTR ran out.
+    pub job_timeouts: u64,
+    /// `current-tubes`: existing tubes.
+    pub current_tubes: u64,
+    /// `current-connections`: open connections.
+    pub current_connections: u64,
+    /// `current-producers`: open connections which put a job.
+    pub current_producers: u64,
+    /// `current-workers`: open connections which reserved a job.
+    pub current_workers: u64,
+    /// `current-waiting`: open connections waiting in a reserve.
+    pub current_waiting: u64,
+    /// `total-connections`: connections opened since the server started.
+    pub total_connections: u64,
+    /// `uptime`: seconds since the server started.
+    pub uptime: u64,
+    /// `max-job-size`: largest job body accepted, in bytes.
+    pub max_job_size: u64,
+}
+
+impl ServerStats {
+    fn from_stats(stats: &HashMap<String, u64>) -> Self {
+        let stat = |name: &str| stats.get(name).copied().unwrap_or(0);
+        ServerStats {
+            current_jobs_urgent: stat("current-jobs-urgent"),
+            current_jobs_ready: stat("current-jobs-ready"),
+            current_jobs_reserved: stat("current-jobs-reserved"),
+            current_jobs_delayed: stat("current-jobs-delayed"),
+            current_jobs_buried: stat("current-jobs-buried"),
+            total_jobs: stat("total-jobs"),
+            job_timeouts: stat("job-timeouts"),
+            current_tubes: stat("current-tubes"),
+            current_connections: stat("current-connections"),
+            current_producers: stat("current-producers"),
+            current_workers: stat("current-workers"),
+            current_waiting: stat("current-waiting"),
+            total_connections: stat("total-connections"),
+            uptime: stat("uptime"),
+            max_job_size: stat("max-job-size"),
+        }
+    }
+
+    fn merge(&mut self, other: &ServerStats) {
+        self.current_jobs_urgent += other.current_jobs_urgent;
+        self.current_jobs_ready += other.current_jobs_ready;
+        self.current_jobs_reserved += other.current_jobs_reserved;
+        self.current_jobs_delayed += other.current_jobs_delayed;
+        self.current_jobs_buried += other.current_jobs_buried;
+        self.total_jobs += other.total_jobs;
+        self.job_timeouts += other.job_timeouts;
+        self.current_tubes += other.current_tubes;
+        self.current_connections += other.current_connections;
+        self.current_producers += other.current_producers;
+        self.current_workers += other.current_workers;
+        self.current_waiting += other.current_waiting;
+        self.total_connections += other.total_connections;
+        self.uptime = self.uptime.max(other.uptime);
+        self.max_job_size = self.max_job_size.max(other.max_job_size);
+    }
+}
+
+/// `TubeStats` holds the main stats of a tube on one server, or on several servers merged
+/// together: counts are summed, while `pause` and `pause-time-left` keep the largest value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct TubeStats {
+    /// `current-jobs-urgent`: ready jobs with a priority under 1024.
+    pub current_jobs_urgent: u64,
+    /// `current-jobs-ready`: ready jobs.
+    pub current_jobs_ready: u64,
+    /// `current-jobs-reserved`: jobs reserved by a client.
+    pub current_jobs_reserved: u64,
+    /// `current-jobs-delayed`: delayed jobs.
+    pub current_jobs_delayed: u64,
+    /// `current-jobs-buried`: buried jobs.
+    pub current_jobs_buried: u64,
+    /// `total-jobs`: jobs created in the tube.
+    pub total_jobs: u64,
+    /// `current-using`: open connections using the tube.
+    pub current_using: u64,
+    /// `current-watching`: open connections watching the tube.
+    pub current_watching: u64,
+    /// `current-waiting`: open connections waiting in a reserve on the tube.
+    pub current_waiting: u64,
+    /// `cmd-delete`: jobs deleted from the tube.
+    pub cmd_delete: u64,
+    /// `cmd-pause-tube`: times the tube was paused.
+    pub cmd_pause_tube: u64,
+    /// `pause`: seconds the tube was last paused for.
+    pub pause: u64,
+    /// `pause-time-left`: seconds until the tube is resumed.
+    pub pause_time_left: u64,
+}
+
+impl TubeStats {
+    fn from_stats(stats: &HashMap<String, u64>) -> Self {
+        let stat = |name: &str| stats.get(name).copied().unwrap_or(0);
+        TubeStats {
+            current_jobs_urgent: stat("current-jobs-urgent"),
+            current_jobs_ready: stat("current-jobs-ready"),
+            current_jobs_reserved: stat("current-jobs-reserved"),
+            current_jobs_delayed: stat("current-jobs-delayed"),
+            current_jobs_buried: stat("current-jobs-buried"),
+            total_jobs: stat("total-jobs"),
+            current_using: stat("current-using"),
+            current_watching: stat("current-watching"),
+            current_waiting: stat("current-waiting"),
+            cmd_delete: stat("cmd-delete"),
+            cmd_pause_tube: stat("cmd-pause-tube"),
+            pause: stat("pause"),
+            pause_time_left: stat("pause-time-left"),
+        }
+    }
+
+    fn merge(&mut self, other: &TubeStats) {
+        self.current_jobs_urgent += other.current_jobs_urgent;
+        self.current_jobs_ready += other.current_jobs_ready;
+        self.current_jobs_reserved += other.current_jobs_reserved;
+        self.current_jobs_delayed += other.current_jobs_delayed;
+        self.current_jobs_buried += other.current_jobs_buried;
+        self.total_jobs += other.total_jobs;
+        self.current_using += other.current_using;
+        self.current_watching += other.current_watching;
+        self.current_waiting += other.current_waiting;
+        self.cmd_delete += other.cmd_delete;
+        self.cmd_pause_tube += other.cmd_pause_tube;
+        self.pause = self.pause.max(other.pause);
+        self.pause_time_left = self.pause_time_left.max(other.pause_time_left);
+    }
+}
+
+/// `StatsReport` holds the server stats and per-tube stats of one server, or of several
+/// servers merged together.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct StatsReport {
+    /// Server stats.
+    pub server: ServerStats,
+    /// Stats of each tube, by tube name.
+    pub tubes: HashMap<String, TubeStats>,
+}
+
+impl StatsReport {
+    /// Return the stats of tube `name`, if it exists.
+    pub fn tube(&self, name: &str) -> Option<&TubeStats> {
+        self.tubes.get(name)
+    }
+
+    /// Merge `other` into this report.
+    fn merge(&mut self, other: &StatsReport) {
+        self.server.merge(&other.server);
+        for (tube, stats) in &other.tubes {
+            self.tubes.entry(tube.clone()).or_default().merge(stats);
+        }
+    }
+}
+
+/// Stats of one server of a cluster.
+#[derive(Debug, Clone)]
+pub struct HostStats {
+    /// Configured `host:port` of the server.
+    pub addr: String,
+    /// Stats of the server, or the error which prevented fetching them.
+    pub stats: BeanstalkcResult<StatsReport>,
+}
+
+/// `ClusterStats` is the combined report returned by [`aggregate_stats`].
+#[derive(Debug, Clone)]
+pub struct ClusterStats {
+    /// Stats of every server which answered, merged.
+    pub total: StatsReport,
+    /// Stats of each server, in the order of the connections given.
+    pub hosts: Vec<HostStats>,
+}
+
+impl ClusterStats {
+    /// Return whether the stats of every server could be fetched.
+    pub fn is_complete(&self) -> bool {
+        self.hosts.iter().all(|host| host.stats.is_ok())
+    }
+}
+
+/// Fetch the server and tube stats of every server of a fleet concurrently, and merge them
+/// into one report keeping the stats of each server. A server failing to answer is left
+/// out of the total, its error being reported in its breakdown.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use beanstalkc::{aggregate_stats, Beanstalkc};
+///
+/// let mut conns = vec![
+///     Beanstalkc::new().host("10.0.0.1").connect().await.unwrap(),
+///     Beanstalkc::new().host("10.0.0.2").connect().await.unwrap(),
+/// ];
+///
+/// let stats = aggregate_stats(&mut conns).await;
+/// dbg!(stats.total.server.current_jobs_ready);
+/// dbg!(stats.total.tube("emails").map(|tube| tube.current_jobs_ready));
+/// for host in &stats.hosts {
+///     dbg!(&host.addr, host.stats.is_ok());
+/// }
+/// }
+/// ```
+pub async fn aggregate_stats(conns: &mut [Beanstalkc]) -> ClusterStats {
+    let hosts: Vec<HostStats> = join_all(conns.iter_mut().map(|conn| async move {
+        HostStats {
+            addr: conn.get_addr(),
+            stats: host_stats(conn).await,
+        }
+    }))
+    .await;
+
+    let mut total = StatsReport::default();
+    for report in hosts.iter().filter_map(|host| host.stats.as_ref().ok()) {
+        total.merge(report);
+    }
+    ClusterStats { total, hosts }
+}
+
+/// Fetch the server stats and the stats of every tube of one server, skipping the tubes
+/// which disappeared in the meantime.
+async fn host_stats(conn: &mut Beanstalkc) -> BeanstalkcResult<StatsReport> {
+    let server = ServerStats::from_stats(&numeric(conn.stats().await?));
+    let mut tubes = HashMap::new();
+    for tube in conn.tubes().await? {
+        match conn.stats_tube(&tube).await {
+            Ok(stats) => {
+                tubes.insert(tube, TubeStats::from_stats(&numeric(stats)));
+            }
+            Err(BeanstalkcError::TubeNotFound(_)) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(StatsReport { server, tubes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+
+    fn stats(pairs: &[(&str, u64)]) -> HashMap<String, u64> {
+        pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    fn report(server: &[(&str, u64)], tubes: &[(&str, &[(&str, u64)])]) -> StatsReport {
+        StatsReport {
+            server: ServerStats::from_stats(&stats(server)),
+            tubes: tubes
+                .iter()
+                .map(|(tube, pairs)| (tube.to_string(), TubeStats::from_stats(&stats(pairs))))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut total = StatsReport::default();
+        total.merge(&report(
+            &[("current-jobs-ready", 3), ("uptime", 10), ("pid", 1)],
+            &[("emails", &[("current-jobs-ready", 2)])],
+        ));
+        total.merge(&report(
+            &[("current-jobs-ready", 4), ("uptime", 5), ("pid", 2)],
+            &[
+                ("emails", &[("current-jobs-ready", 1)]),
+                ("reports", &[("pause", 30)]),
+            ],
+        ));
+
+        assert_eq!(7, total.server.current_jobs_ready);
+        assert_eq!(10, total.server.uptime);
+        assert_eq!(3, total.tube("emails").unwrap().current_jobs_ready);
+        assert_eq!(30, total.tube("reports").unwrap().pause);
+        assert_eq!(None, total.tube("missing"));
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_stats_skips_missing_tubes() {
+        let server = FakeServer::start().await;
+        server.reply("stats", "OK 26\r\n---\ncurrent-jobs-ready: 1\n");
+        server.reply("list-tubes", "OK 24\r\n---\n- default\n- deleted\n");
+        server.put("default", b"job");
+        let mut conns = vec![server.client().connect().await.unwrap()];
+
+        let stats = aggregate_stats(&mut conns).await;
+        assert!(stats.is_complete());
+        assert_eq!(1, stats.total.server.current_jobs_ready);
+        assert_eq!(1, stats.total.tube("default").unwrap().current_jobs_ready);
+        assert_eq!(None, stats.total.tube("deleted"));
+    }
+}
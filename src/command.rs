@@ -1,3 +1,6 @@
+//! Constructors of beanstalkd commands, to send raw commands through the
+//! `tower::Service` implementation of [`SharedClient`](crate::SharedClient).
+use std::borrow::Cow;
 use std::fmt;
 use std::str::FromStr;
 use std::string::ToString;
@@ -5,7 +8,8 @@ use std::time::Duration;
 
 use crate::error::BeanstalkcError;
 
-#[derive(Debug)]
+/// `CommandKind` identifies a beanstalkd command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CommandKind {
     Put,
     PeekJob,
@@ -65,7 +69,8 @@ impl fmt::Display for CommandKind {
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// `Status` is the first word of a beanstalkd response line.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Status {
     Ok,
     Found,
@@ -128,13 +133,23 @@ impl FromStr for Status {
     }
 }
 
+/// Statuses the server may reply to any command.
+const GENERIC_STATUSES: [Status; 5] = [
+    Status::OutOfMemory,
+    Status::InternalError,
+    Status::BadFormat,
+    Status::UnknownCommand,
+    Status::ExpectedCRLF,
+];
+
+/// `Command` is a beanstalkd command about to be sent by a client.
 #[derive(Debug)]
 pub struct Command<'a> {
     kind: CommandKind,
     args: Vec<String>,
-    body: Option<&'a [u8]>,
-    pub expected_ok_status: Vec<Status>,
-    pub expected_error_status: Vec<Status>,
+    body: Option<Cow<'a, [u8]>>,
+    pub(crate) expected_ok_status: Vec<Status>,
+    pub(crate) expected_error_status: Vec<Status>,
 }
 
 impl<'a> Command<'a> {
@@ -148,12 +163,108 @@ impl<'a> Command<'a> {
         Command {
             kind,
             args,
-            body,
+            body: body.map(Cow::Borrowed),
             expected_ok_status: exp_ok_status,
             expected_error_status: exp_error_status,
         }
     }
 
+    /// Return the command kind.
+    pub fn kind(&self) -> CommandKind {
+        self.kind
+    }
+
+    /// Return the command name as sent on the wire, e.g. `reserve-with-timeout`.
+    pub fn name(&self) -> String {
+        self.kind.to_string()
+    }
+
+    /// Return the command arguments.
+    pub fn args(&self) -> &[String] {
+        &self.args[..]
+    }
+
+    /// Return the command arguments for rewriting, e.g. to rename tubes.
+    pub fn args_mut(&mut self) -> &mut Vec<String> {
+        &mut self.args
+    }
+
+    /// Return the job body sent along with the command.
+    pub fn body(&self) -> Option<&[u8]> {
+        self.body.as_deref()
+    }
+
+    /// Copy the borrowed job body, if any, so that the command can outlive it.
+    pub fn into_owned(self) -> Command<'static> {
+        Command {
+            kind: self.kind,
+            args: self.args,
+            body: self.body.map(|b| Cow::Owned(b.into_owned())),
+            expected_ok_status: self.expected_ok_status,
+            expected_error_status: self.expected_error_status,
+        }
+    }
+
+    /// Treat `status` as a successful response, e.g. to get `NOT_FOUND` back as a response
+    /// instead of an error when sending raw commands.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beanstalkc::{command, Status};
+    ///
+    /// let cmd = command::peek_job(1).expect(Status::NotFound);
+    /// assert!(cmd.expected_statuses().contains(&Status::NotFound));
+    /// ```
+    pub fn expect(mut self, status: Status) -> Self {
+        self.expected_error_status.retain(|s| *s != status);
+        if !self.expected_ok_status.contains(&status) {
+            self.expected_ok_status.push(status);
+        }
+        self
+    }
+
+    /// Treat `status` as a failure, reported as `BeanstalkcError::CommandFailed` rather than
+    /// `BeanstalkcError::UnexpectedStatus`.
+    pub fn expect_error(mut self, status: Status) -> Self {
+        self.expected_ok_status.retain(|s| *s != status);
+        if !self.expected_error_status.contains(&status) {
+            self.expected_error_status.push(status);
+        }
+        self
+    }
+
+    /// Return the statuses treated as a successful response.
+    pub fn expected_statuses(&self) -> &[Status] {
+        &self.expected_ok_status[..]
+    }
+
+    /// Return the statuses treated as a failure.
+    pub fn expected_error_statuses(&self) -> &[Status] {
+        &self.expected_error_status[..]
+    }
+
+    /// Return whether `status` can answer this command, as opposed to a different command.
+    pub(crate) fn is_answered_by(&self, status: Status) -> bool {
+        self.expected_ok_status.contains(&status)
+            || self.expected_error_status.contains(&status)
+            || GENERIC_STATUSES.contains(&status)
+    }
+
+    /// Return the command line announcing a body of `len` bytes, sent separately.
+    pub(crate) fn build_head(&self, len: u64) -> String {
+        let mut head = self.kind.to_string();
+        for arg in &self.args {
+            head.push(' ');
+            head.push_str(arg);
+        }
+        head.push(' ');
+        head.push_str(&len.to_string());
+        head.push_str("\r\n");
+        head
+    }
+
+    /// Return the command line and body as sent on the wire.
     pub fn build(&self) -> String {
         const SPACE: &str = " ";
         const LINE_BREAK: &str = "\r\n";
@@ -164,7 +275,7 @@ impl<'a> Command<'a> {
             cmd = cmd + SPACE + self.args.clone().join(SPACE).as_str();
         }
 
-        if let Some(body) = self.body {
+        if let Some(body) = &self.body {
             cmd = cmd + SPACE + body.len().to_string().as_str() + LINE_BREAK;
 
             let utf8body = String::from_utf8_lossy(body);
@@ -177,7 +288,7 @@ impl<'a> Command<'a> {
 }
 
 // Construct commands
-pub fn put(body: &[u8], priority: u32, delay: Duration, ttr: Duration) -> Command {
+pub fn put(body: &[u8], priority: u32, delay: Duration, ttr: Duration) -> Command<'_> {
     Command::new(
         CommandKind::Put,
         vec![
@@ -407,7 +518,8 @@ mod tests {
             Duration::from_secs(10),
             Duration::from_secs(100),
         );
-        assert_eq!(cmd.build().as_str(), "put 0 10 100 4\r\nRust\r\n")
+        assert_eq!(cmd.build().as_str(), "put 0 10 100 4\r\nRust\r\n");
+        assert_eq!(cmd.build_head(4).as_str(), "put 0 10 100 4\r\n");
     }
 
     #[test]
@@ -527,6 +639,36 @@ mod tests {
         assert_eq!(cmd.build().as_str(), "stats-job 100\r\n");
     }
 
+    #[test]
+    fn test_args_mut() {
+        let mut cmd = watch("jobs");
+        cmd.args_mut()[0].insert_str(0, "staging.");
+        assert_eq!(CommandKind::Watch, cmd.kind());
+        assert_eq!("watch", cmd.name());
+        assert_eq!(cmd.build().as_str(), "watch staging.jobs\r\n");
+    }
+
+    #[test]
+    fn test_expect() {
+        let cmd = peek_job(1).expect(Status::NotFound);
+        assert_eq!(&[Status::Found, Status::NotFound], cmd.expected_statuses());
+        assert!(cmd.expected_error_statuses().is_empty());
+
+        let cmd = cmd.expect_error(Status::NotFound);
+        assert_eq!(&[Status::Found], cmd.expected_statuses());
+        assert_eq!(&[Status::NotFound], cmd.expected_error_statuses());
+    }
+
+    #[test]
+    fn test_is_answered_by() {
+        let cmd = delete(1).expect(Status::NotFound);
+        assert!(cmd.is_answered_by(Status::Deleted));
+        assert!(cmd.is_answered_by(Status::NotFound));
+        assert!(cmd.is_answered_by(Status::InternalError));
+        assert!(!cmd.is_answered_by(Status::Reserved));
+        assert!(!cmd.is_answered_by(Status::Using));
+    }
+
     #[test]
     fn test_quit() {
         let cmd = quit();
@@ -3,7 +3,30 @@ use std::time::Duration;
 /// Default configurations for Beanstalkd client.
 pub const DEFAULT_HOST: &str = "localhost";
 pub const DEFAULT_PORT: u16 = 11300;
+pub const DEFAULT_TUBE: &str = "default";
 pub const DEFAULT_CONNECTION_TIMEOUT: Option<Duration> = Some(Duration::from_secs(120));
 pub const DEFAULT_JOB_PRIORITY: u32 = 1 << 31;
 pub const DEFAULT_JOB_TTR: Duration = Duration::from_secs(120);
 pub const DEFAULT_JOB_DELAY: Duration = Duration::from_secs(0);
+pub const DEFAULT_WORKER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+pub const DEFAULT_WORKER_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+pub const CANCELLABLE_RESERVE_SLICE: Duration = Duration::from_secs(1);
+pub const IDLE_PING_TIMEOUT: Duration = Duration::from_secs(5);
+pub const SUPERVISOR_PING_INTERVAL: Duration = Duration::from_secs(1);
+pub const DEFAULT_RETRY_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+pub const DEFAULT_RETRY_MAX_BACKOFF: Duration = Duration::from_secs(5);
+pub const SCHEDULER_MAX_SLEEP: Duration = Duration::from_secs(1);
+pub const DEFAULT_SCHEDULED_TUBE: &str = "scheduled";
+pub const LONG_DELAY_HOP: Duration = Duration::from_secs(3600);
+pub const DEFAULT_DEDUP_TTL: Duration = Duration::from_secs(300);
+pub const DESYNC_DRAIN_TIMEOUT: Duration = Duration::from_millis(50);
+pub const MAX_PIPELINED_COMMANDS: usize = 1000;
+pub const MAX_RESPONSE_LINE: usize = 1024;
+pub const MAX_JOB_BODY: usize = 1 << 30;
+pub const MAX_YAML_BODY: usize = 16 << 20;
+pub const ORPHAN_RESPONSE_TIMEOUT: Duration = Duration::from_millis(100);
+pub const REWRITE_TUBE_SUFFIX: &str = ".rewrite";
+pub const RETRY_TUBE_INFIX: &str = ".retry.";
+pub const MAX_JOB_DELAY: Duration = Duration::from_secs(u32::MAX as u64);
+pub const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(86_400);
+pub const DEFAULT_BACKLOG_CHECK_INTERVAL: Duration = Duration::from_secs(5);
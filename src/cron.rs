@@ -0,0 +1,216 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+
+/// How far ahead to look for the next match before giving up, e.g. for `0 0 30 2 *`.
+const MAX_LOOKAHEAD_DAYS: i64 = 5 * 366;
+
+/// A five field cron expression (`minute hour day-of-month month day-of-week`) evaluated
+/// in UTC.
+///
+/// Each field is `*`, a number, a range `a-b`, any of them followed by a step `/n`, or a
+/// comma separated list of those. Day of week is `0` (or `7`) for Sunday to `6`. Like in
+/// Vixie cron, when both day fields are restricted a day matching either one matches.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    pub(crate) fn parse(expr: &str) -> BeanstalkcResult<Cron> {
+        let expr = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            expr => expr,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(invalid(expr, "expected 5 fields"));
+        }
+
+        let weekdays = parse_field(fields[4], 0, 7).ok_or_else(|| invalid(expr, fields[4]))?;
+        // Sunday is both 0 and 7.
+        let weekdays = (weekdays | weekdays >> 7) & 0x7f;
+        Ok(Cron {
+            minutes: parse_field(fields[0], 0, 59).ok_or_else(|| invalid(expr, fields[0]))?,
+            hours: parse_field(fields[1], 0, 23).ok_or_else(|| invalid(expr, fields[1]))?,
+            days: parse_field(fields[2], 1, 31).ok_or_else(|| invalid(expr, fields[2]))?,
+            months: parse_field(fields[3], 1, 12).ok_or_else(|| invalid(expr, fields[3]))?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+
+    /// Return the first matching minute strictly after `time`.
+    pub(crate) fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let start = time.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+        let mut t = (start / 60 + 1) * 60;
+        let limit = start + MAX_LOOKAHEAD_DAYS * 86400;
+        while t <= limit {
+            let days = t.div_euclid(86400);
+            let (year, month, day) = civil_from_days(days);
+            if !has(self.months, month) {
+                t = days_from_civil(year, month + 1, 1) * 86400;
+                continue;
+            }
+            if !self.matches_day(day, (days + 4).rem_euclid(7) as u32) {
+                t = (days + 1) * 86400;
+                continue;
+            }
+            let seconds = t.rem_euclid(86400);
+            if !has(self.hours, (seconds / 3600) as u32) {
+                t = (t / 3600 + 1) * 3600;
+                continue;
+            }
+            if !has(self.minutes, (seconds % 3600 / 60) as u32) {
+                t += 60;
+                continue;
+            }
+            return Some(UNIX_EPOCH + Duration::from_secs(t as u64));
+        }
+        None
+    }
+
+    fn matches_day(&self, day: u32, weekday: u32) -> bool {
+        let day_match = has(self.days, day);
+        let weekday_match = has(self.weekdays, weekday);
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday_match,
+            (false, true) => day_match,
+            (false, false) => day_match || weekday_match,
+        }
+    }
+}
+
+fn has(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+/// Parse a field into a bit set of the values between `min` and `max` it matches.
+fn parse_field(field: &str, min: u32, max: u32) -> Option<u64> {
+    let mut set = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0)?),
+            None => (part, 1),
+        };
+        let (low, high) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((low, high)) => (low.parse().ok()?, high.parse().ok()?),
+                None if step > 1 => (range.parse().ok()?, max),
+                None => (range.parse().ok()?, range.parse().ok()?),
+            },
+        };
+        if low < min || high > max || low > high {
+            return None;
+        }
+        for value in (low..=high).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Some(set)
+}
+
+fn invalid(expr: &str, reason: &str) -> BeanstalkcError {
+    BeanstalkcError::InvalidSchedule(format!("{:?}: {}", expr, reason))
+}
+
+/// Convert days since the Unix epoch to a `(year, month, day)` date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Convert a date to days since the Unix epoch. `month` may be 13 for January of the next
+/// year.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let (year, month) = if month > 12 {
+        (year + 1, month - 12)
+    } else {
+        (year, month)
+    };
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(days: i64, hour: i64, minute: i64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs((days * 86400 + hour * 3600 + minute * 60) as u64)
+    }
+
+    #[test]
+    fn test_civil() {
+        assert_eq!((1970, 1, 1), civil_from_days(0));
+        assert_eq!((2000, 2, 29), civil_from_days(11016));
+        assert_eq!(11016, days_from_civil(2000, 2, 29));
+        assert_eq!(days_from_civil(2025, 1, 1), days_from_civil(2024, 13, 1));
+    }
+
+    #[test]
+    fn test_parse() {
+        let cron = Cron::parse("*/15 9-17 * * 1-5").unwrap();
+        assert_eq!(1 | 1 << 15 | 1 << 30 | 1 << 45, cron.minutes);
+        assert_eq!(0b11_1110, cron.weekdays);
+        assert_eq!(
+            Cron::parse("0 0 * * 0").unwrap(),
+            Cron::parse("0 0 * * 7").unwrap()
+        );
+        assert_eq!(
+            Cron::parse("@daily").unwrap(),
+            Cron::parse("0 0 * * *").unwrap()
+        );
+
+        assert!(Cron::parse("* * * *").is_err());
+        assert!(Cron::parse("60 * * * *").is_err());
+        assert!(Cron::parse("*/0 * * * *").is_err());
+        assert!(Cron::parse("5-1 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 2000-02-29 is a Tuesday.
+        let day = days_from_civil(2000, 2, 29);
+        let cron = Cron::parse("*/15 * * * *").unwrap();
+        assert_eq!(Some(at(day, 10, 15)), cron.next_after(at(day, 10, 0)));
+        assert_eq!(Some(at(day + 1, 0, 0)), cron.next_after(at(day, 23, 50)));
+
+        let cron = Cron::parse("30 9 * * 1").unwrap();
+        assert_eq!(Some(at(day + 6, 9, 30)), cron.next_after(at(day, 9, 30)));
+
+        let cron = Cron::parse("0 0 1 3 *").unwrap();
+        assert_eq!(Some(at(day + 1, 0, 0)), cron.next_after(at(day, 0, 0)));
+
+        assert_eq!(
+            None,
+            Cron::parse("0 0 30 2 *").unwrap().next_after(at(day, 0, 0))
+        );
+    }
+}
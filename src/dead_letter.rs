@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use crate::config::DEFAULT_JOB_DELAY;
+
+/// What to do with a job once it has failed too many times.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeadLetterAction {
+    /// Bury the job so that it stays on the server for manual inspection.
+    Bury,
+    /// Re-put the job body into the given tube and delete the original job.
+    Move(String),
+}
+
+/// `DeadLetterPolicy` centralizes poison-message handling.
+///
+/// When a failed job is released through [`Job::fail`](crate::Job::fail) and its
+/// `releases` counter has reached `max_retries`, the job is dead-lettered according
+/// to `action` instead of being released again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadLetterPolicy {
+    pub(crate) max_retries: u64,
+    pub(crate) retry_delay: Duration,
+    pub(crate) action: DeadLetterAction,
+}
+
+impl DeadLetterPolicy {
+    /// Bury jobs which have already been released `max_retries` times.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, DeadLetterPolicy};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .dead_letter_policy(DeadLetterPolicy::bury_after(5))
+    ///        .connect()
+    ///        .await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn bury_after(max_retries: u64) -> Self {
+        DeadLetterPolicy {
+            max_retries,
+            retry_delay: DEFAULT_JOB_DELAY,
+            action: DeadLetterAction::Bury,
+        }
+    }
+
+    /// Move jobs which have already been released `max_retries` times into the `tube`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, DeadLetterPolicy};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .dead_letter_policy(DeadLetterPolicy::move_after(5, "jobs.dead"))
+    ///        .connect()
+    ///        .await
+    ///        .unwrap();
+    /// }
+    /// ```
+    pub fn move_after(max_retries: u64, tube: &str) -> Self {
+        DeadLetterPolicy {
+            max_retries,
+            retry_delay: DEFAULT_JOB_DELAY,
+            action: DeadLetterAction::Move(tube.to_string()),
+        }
+    }
+
+    /// Change the delay used when a failed job is released for another attempt.
+    /// Default retry delay is `0s`.
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Return whether a job released `releases` times should be dead-lettered.
+    pub fn should_dead_letter(&self, releases: u64) -> bool {
+        releases >= self.max_retries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bury_after() {
+        let policy = DeadLetterPolicy::bury_after(3);
+        assert_eq!(&DeadLetterAction::Bury, &policy.action);
+        assert!(!policy.should_dead_letter(2));
+        assert!(policy.should_dead_letter(3));
+        assert!(policy.should_dead_letter(4));
+    }
+
+    #[test]
+    fn test_move_after() {
+        let policy = DeadLetterPolicy::move_after(0, "dead").retry_delay(Duration::from_secs(5));
+        assert_eq!(&DeadLetterAction::Move("dead".to_string()), &policy.action);
+        assert_eq!(Duration::from_secs(5), policy.retry_delay);
+        assert!(policy.should_dead_letter(0));
+    }
+}
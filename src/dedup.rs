@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures::future::{self, BoxFuture};
+use futures::FutureExt;
+
+use crate::error::BeanstalkcResult;
+
+/// Expired keys are pruned once the store grows past this many keys.
+const MIN_PRUNE_LEN: usize = 1024;
+
+/// `DedupStore` remembers the keys of the jobs recently put by
+/// [`Beanstalkc::put_unique`](crate::Beanstalkc::put_unique).
+///
+/// Stores shared between processes, e.g. on Redis with `SET key 1 NX PX ttl`, make
+/// deduplication work across producers. Register one with
+/// [`Beanstalkc::dedup_store`](crate::Beanstalkc::dedup_store).
+pub trait DedupStore: Send + Sync {
+    /// Remember `key` for `ttl` unless it is remembered already. Return whether the key is
+    /// new.
+    fn insert<'a>(&'a self, key: &'a str, ttl: Duration) -> BoxFuture<'a, BeanstalkcResult<bool>>;
+
+    /// Forget `key`, so the job can be put again after its put failed.
+    fn remove<'a>(&'a self, key: &'a str) -> BoxFuture<'a, BeanstalkcResult<()>>;
+}
+
+impl fmt::Debug for dyn DedupStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DedupStore")
+    }
+}
+
+/// `MemoryDedupStore` is a [`DedupStore`] keeping keys in memory, only deduplicating the
+/// puts of the clients sharing it. It is the default store.
+#[derive(Debug, Default)]
+pub struct MemoryDedupStore {
+    inner: Mutex<Keys>,
+}
+
+#[derive(Debug, Default)]
+struct Keys {
+    expires: HashMap<String, Instant>,
+    prune_len: usize,
+}
+
+impl MemoryDedupStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        MemoryDedupStore::default()
+    }
+
+    fn insert_at(&self, key: &str, ttl: Duration, now: Instant) -> bool {
+        let mut keys = self.inner.lock().unwrap();
+        if keys.expires.len() >= keys.prune_len.max(MIN_PRUNE_LEN) {
+            keys.expires.retain(|_, expires| *expires > now);
+            keys.prune_len = keys.expires.len() * 2;
+        }
+        match keys.expires.get(key) {
+            Some(expires) if *expires > now => false,
+            _ => {
+                keys.expires.insert(key.to_string(), now + ttl);
+                true
+            }
+        }
+    }
+}
+
+impl DedupStore for MemoryDedupStore {
+    fn insert<'a>(&'a self, key: &'a str, ttl: Duration) -> BoxFuture<'a, BeanstalkcResult<bool>> {
+        future::ready(Ok(self.insert_at(key, ttl, Instant::now()))).boxed()
+    }
+
+    fn remove<'a>(&'a self, key: &'a str) -> BoxFuture<'a, BeanstalkcResult<()>> {
+        self.inner.lock().unwrap().expires.remove(key);
+        future::ready(Ok(())).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_dedup_store() {
+        let store = MemoryDedupStore::new();
+        let now = Instant::now();
+        let ttl = Duration::from_secs(60);
+        assert!(store.insert_at("a", ttl, now));
+        assert!(!store.insert_at("a", ttl, now + Duration::from_secs(59)));
+        assert!(store.insert_at("a", ttl, now + Duration::from_secs(60)));
+
+        let store = MemoryDedupStore::new();
+        for i in 0..MIN_PRUNE_LEN {
+            store.insert_at(&i.to_string(), ttl, now);
+        }
+        store.insert_at("b", ttl, now + Duration::from_secs(60));
+        assert_eq!(1, store.inner.lock().unwrap().expires.len());
+    }
+}
@@ -0,0 +1,268 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::Beanstalkc;
+
+/// First line of an encoded envelope.
+const MAGIC: &[u8] = b"beanstalkc-envelope/1\r\n";
+
+/// Line break ending each line of the envelope head.
+const CRLF: &[u8] = b"\r\n";
+
+/// `Envelope` wraps a job body together with headers carrying metadata.
+///
+/// The encoded envelope is a text head followed by the raw body, like an HTTP message:
+///
+/// ```text
+/// beanstalkc-envelope/1\r\n
+/// <name>: <value>\r\n
+/// ...
+/// \r\n
+/// <body>
+/// ```
+///
+/// Header names are lowercase ASCII without `:`, values are UTF-8 without line breaks, and
+/// the body runs until the end of the job. Any producer or consumer able to split lines can
+/// read and write this format. A job not starting with the first line above is decoded as
+/// an envelope without headers, so plain jobs can be mixed with enveloped ones.
+///
+/// The crate reads and writes the following headers, any other header is left to the
+/// application:
+///
+/// | Header | Value |
+/// |--------|-------|
+/// | `content-type` | Media type of the body, e.g. `application/json` |
+/// | `enqueued-at` | When the job was put, in milliseconds since the Unix epoch |
+/// | `retry-count` | How many times the job has been retried |
+/// | `trace-id` | Identifier of the trace the job belongs to |
+///
+/// # Example
+///
+/// ```
+/// use beanstalkc::Envelope;
+///
+/// let envelope = Envelope::new(b"Rust".to_vec()).header("content-type", "text/plain");
+/// let data = envelope.encode();
+///
+/// let decoded = Envelope::decode(&data).unwrap();
+/// assert_eq!(Some("text/plain"), decoded.get_header("content-type"));
+/// assert_eq!(b"Rust", decoded.body());
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Envelope {
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Envelope {
+    /// Header holding the media type of the body.
+    pub const CONTENT_TYPE: &'static str = "content-type";
+    /// Header holding when the job was put, in milliseconds since the Unix epoch.
+    pub const ENQUEUED_AT: &'static str = "enqueued-at";
+    /// Header holding how many times the job has been retried.
+    pub const RETRY_COUNT: &'static str = "retry-count";
+    /// Header holding the identifier of the trace the job belongs to.
+    pub const TRACE_ID: &'static str = "trace-id";
+
+    /// Wrap `body` without any header.
+    pub fn new(body: Vec<u8>) -> Self {
+        Envelope {
+            headers: vec![],
+            body,
+        }
+    }
+
+    /// Set header `name` to `value`, replacing any previous value. The name is lowercased,
+    /// and `:` in the name as well as line breaks are replaced by spaces.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.set_header(name, value);
+        self
+    }
+
+    /// Set header `name` to `value` in place, see [`Envelope::header`].
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        let name: String = name
+            .trim()
+            .chars()
+            .map(|c| match c {
+                ':' | '\r' | '\n' => ' ',
+                c => c.to_ascii_lowercase(),
+            })
+            .collect();
+        let value = value.replace(['\r', '\n'], " ");
+        match self.headers.iter_mut().find(|(n, _)| *n == name) {
+            Some(header) => header.1 = value,
+            None => self.headers.push((name, value)),
+        }
+    }
+
+    /// Return the value of header `name`.
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        let name = name.to_ascii_lowercase();
+        self.headers
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Return when the job was put, from the `enqueued-at` header.
+    pub fn enqueued_at(&self) -> Option<SystemTime> {
+        let millis = self.get_header(Envelope::ENQUEUED_AT)?.parse().ok()?;
+        UNIX_EPOCH.checked_add(Duration::from_millis(millis))
+    }
+
+    /// Return the headers in insertion order.
+    pub fn headers(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    /// Return the wrapped body.
+    pub fn body(&self) -> &[u8] {
+        &self.body[..]
+    }
+
+    /// Consume the envelope and return the wrapped body.
+    pub fn into_body(self) -> Vec<u8> {
+        self.body
+    }
+
+    /// Encode the envelope into a job body.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        for (name, value) in &self.headers {
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(b": ");
+            data.extend_from_slice(value.as_bytes());
+            data.extend_from_slice(CRLF);
+        }
+        data.extend_from_slice(CRLF);
+        data.extend_from_slice(&self.body);
+        data
+    }
+
+    /// Return whether `data` is an encoded envelope.
+    pub(crate) fn is_envelope(data: &[u8]) -> bool {
+        data.starts_with(MAGIC)
+    }
+
+    /// Decode a job body, which is returned as is when it is not an envelope.
+    pub fn decode(data: &[u8]) -> BeanstalkcResult<Envelope> {
+        let mut rest = match data.strip_prefix(MAGIC) {
+            Some(rest) => rest,
+            None => return Ok(Envelope::new(data.to_vec())),
+        };
+
+        let mut envelope = Envelope::default();
+        loop {
+            let end = rest
+                .windows(CRLF.len())
+                .position(|w| w == CRLF)
+                .ok_or_else(|| malformed("unterminated header"))?;
+            let line = std::str::from_utf8(&rest[..end])?;
+            rest = &rest[end + CRLF.len()..];
+            if line.is_empty() {
+                break;
+            }
+
+            let (name, value) = line.split_once(':').ok_or_else(|| malformed(line))?;
+            envelope.set_header(name, value.trim_start());
+        }
+        envelope.body = rest.to_vec();
+        Ok(envelope)
+    }
+}
+
+impl Beanstalkc {
+    /// Put a job into the current tube, wrapping the body into an [`Envelope`] with
+    /// `headers`. The `enqueued-at` header is set to the current time unless given. Return
+    /// the job id.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::{Beanstalkc, Envelope};
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let job_id = conn
+    ///     .put_with_headers(
+    ///         br#"{"to":"rust@example.com"}"#,
+    ///         &[(Envelope::CONTENT_TYPE, "application/json")],
+    ///         0,
+    ///         Duration::from_secs(0),
+    ///         Duration::from_secs(10),
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let job = conn.reserve().await.unwrap();
+    /// let envelope = job.envelope().unwrap();
+    /// assert_eq!(Some("application/json"), envelope.get_header(Envelope::CONTENT_TYPE));
+    /// }
+    /// ```
+    pub async fn put_with_headers(
+        &mut self,
+        body: &[u8],
+        headers: &[(&str, &str)],
+        priority: u32,
+        delay: Duration,
+        ttr: Duration,
+    ) -> BeanstalkcResult<u64> {
+        let mut envelope = Envelope::new(body.to_vec());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        envelope.set_header(Envelope::ENQUEUED_AT, &now.to_string());
+        for (name, value) in headers {
+            envelope.set_header(name, value);
+        }
+        self.put(&envelope.encode(), priority, delay, ttr).await
+    }
+}
+
+fn malformed(reason: &str) -> BeanstalkcError {
+    BeanstalkcError::UnexpectedResponse(format!("malformed envelope: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let envelope = Envelope::new(b"a\r\n\r\nb".to_vec())
+            .header("Content-Type", "text/plain")
+            .header("x:trace\r\n", "1\r\n2")
+            .header("content-type", "application/json");
+        assert_eq!(
+            &b"beanstalkc-envelope/1\r\ncontent-type: application/json\r\nx trace: 1  2\r\n\r\na\r\n\r\nb"[..],
+            &envelope.encode()[..]
+        );
+        assert_eq!(envelope, Envelope::decode(&envelope.encode()).unwrap());
+    }
+
+    #[test]
+    fn test_decode() {
+        let envelope = Envelope::decode(b"plain body").unwrap();
+        assert_eq!(0, envelope.headers().count());
+        assert_eq!(b"plain body", envelope.body());
+
+        let envelope = Envelope::decode(b"beanstalkc-envelope/1\r\nkey:value\r\n\r\n").unwrap();
+        assert_eq!(Some("value"), envelope.get_header("KEY"));
+        assert!(envelope.body().is_empty());
+        assert_eq!(None, envelope.enqueued_at());
+
+        let envelope = Envelope::new(vec![]).header(Envelope::ENQUEUED_AT, "1500");
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_millis(1500)),
+            envelope.enqueued_at()
+        );
+
+        assert!(Envelope::decode(b"beanstalkc-envelope/1\r\nkey\r\n\r\n").is_err());
+        assert!(Envelope::decode(b"beanstalkc-envelope/1\r\nkey: value").is_err());
+    }
+}
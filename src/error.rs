@@ -5,12 +5,101 @@ use std::net::AddrParseError;
 use std::num::ParseIntError;
 use std::str::Utf8Error;
 use std::string::FromUtf8Error;
+use std::sync::Arc;
+
+use crate::command::{CommandKind, Status};
 
 #[derive(Debug, Clone)]
 pub enum BeanstalkcError {
     ConnectionError(String),
     UnexpectedResponse(String),
     CommandFailed(String),
+    HandlerPanicked(String),
+    CircuitOpen(String),
+    Overloaded(String),
+    TubeNotFound(String),
+    InvalidSchedule(String),
+    /// An I/O error on the connection, displayed like `ConnectionError`.
+    Io(Arc<io::Error>),
+    /// An address which failed to parse, displayed like `ConnectionError`.
+    InvalidAddress(AddrParseError),
+    /// A response which failed to parse, displayed like `UnexpectedResponse`.
+    Parse(Arc<dyn Error + Send + Sync>),
+    /// A response whose status was not expected by the command, displayed like
+    /// `UnexpectedResponse`.
+    UnexpectedStatus {
+        command: CommandKind,
+        status: Status,
+        params: Vec<String>,
+    },
+    /// A malformed response, after which responses can no longer be matched to commands.
+    ProtocolDesync(String),
+    /// A job whose body of `size` bytes is larger than the client accepts, see
+    /// [`Beanstalkc::max_body_size`](crate::Beanstalkc::max_body_size).
+    BodyTooLarge {
+        id: u64,
+        size: u64,
+    },
+    /// A job whose body is not valid UTF-8, see
+    /// [`Beanstalkc::assume_utf8`](crate::Beanstalkc::assume_utf8).
+    NonUtf8Body {
+        id: u64,
+        error: Utf8Error,
+    },
+    /// A job returned without its body by
+    /// [`Beanstalkc::reserve_into`](crate::Beanstalkc::reserve_into) or
+    /// [`Beanstalkc::peek_into`](crate::Beanstalkc::peek_into), for an operation which
+    /// needs the body.
+    BodyNotLoaded(u64),
+    /// A put refused because tube `tube` holds `ready` ready jobs, over its `limit`, see
+    /// [`Beanstalkc::put_backlog_limit`](crate::Beanstalkc::put_backlog_limit).
+    BackpressureExceeded {
+        tube: String,
+        ready: u64,
+        limit: u64,
+    },
+}
+
+impl BeanstalkcError {
+    /// Return whether the error means the connection is unusable, in which case it is
+    /// closed and the command may be retried on a new connection.
+    pub fn is_connection_error(&self) -> bool {
+        matches!(
+            self,
+            BeanstalkcError::ConnectionError(_)
+                | BeanstalkcError::Io(_)
+                | BeanstalkcError::InvalidAddress(_)
+                | BeanstalkcError::ProtocolDesync(_)
+        )
+    }
+
+    /// Return whether the error concerns a single reserved job which the client finalized
+    /// instead of returning it, e.g. with a body over the maximum size. The connection stays
+    /// usable and the next job can be reserved.
+    pub fn is_job_error(&self) -> bool {
+        matches!(
+            self,
+            BeanstalkcError::BodyTooLarge { .. } | BeanstalkcError::NonUtf8Body { .. }
+        )
+    }
+
+    /// Return the kind of the underlying I/O error, if any.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io;
+    /// use beanstalkc::BeanstalkcError;
+    ///
+    /// let err = BeanstalkcError::from(io::Error::from(io::ErrorKind::BrokenPipe));
+    /// assert_eq!(Some(io::ErrorKind::BrokenPipe), err.io_kind());
+    /// ```
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            BeanstalkcError::Io(err) => Some(err.kind()),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for BeanstalkcError {
@@ -19,42 +108,143 @@ impl fmt::Display for BeanstalkcError {
             BeanstalkcError::ConnectionError(msg) => format!("Connection error: {}", msg),
             BeanstalkcError::UnexpectedResponse(msg) => format!("Unexpected response: {}", msg),
             BeanstalkcError::CommandFailed(msg) => format!("Command failed: {}", msg),
+            BeanstalkcError::HandlerPanicked(msg) => format!("Handler panicked: {}", msg),
+            BeanstalkcError::CircuitOpen(msg) => format!("Circuit open: {}", msg),
+            BeanstalkcError::Overloaded(msg) => format!("Overloaded: {}", msg),
+            BeanstalkcError::TubeNotFound(name) => format!("Tube not found: {}", name),
+            BeanstalkcError::InvalidSchedule(msg) => format!("Invalid schedule: {}", msg),
+            BeanstalkcError::Io(err) => format!("Connection error: {}", err),
+            BeanstalkcError::InvalidAddress(err) => format!("Connection error: {}", err),
+            BeanstalkcError::Parse(err) => format!("Unexpected response: {}", err),
+            BeanstalkcError::UnexpectedStatus {
+                command,
+                status,
+                params,
+            } => {
+                let mut msg = format!("Unexpected response: {:?}", status);
+                for param in params {
+                    msg.push(' ');
+                    msg.push_str(param);
+                }
+                format!("{} to {}", msg, command)
+            }
+            BeanstalkcError::ProtocolDesync(msg) => format!("Protocol desync: {}", msg),
+            BeanstalkcError::BodyTooLarge { id, size } => {
+                format!("Body too large: job {} has {} bytes", id, size)
+            }
+            BeanstalkcError::NonUtf8Body { id, error } => {
+                format!("Non UTF-8 body: job {}: {}", id, error)
+            }
+            BeanstalkcError::BodyNotLoaded(id) => {
+                format!("Body not loaded: job {} was streamed", id)
+            }
+            BeanstalkcError::BackpressureExceeded { tube, ready, limit } => format!(
+                "Backpressure exceeded: tube {} has {} ready jobs, limit is {}",
+                tube, ready, limit
+            ),
         };
 
         write!(formatter, "{}", description)
     }
 }
 
-impl Error for BeanstalkcError {}
+impl Error for BeanstalkcError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BeanstalkcError::Io(err) => Some(err.as_ref()),
+            BeanstalkcError::InvalidAddress(err) => Some(err),
+            BeanstalkcError::Parse(err) => Some(err.as_ref()),
+            BeanstalkcError::NonUtf8Body { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
 
 impl From<io::Error> for BeanstalkcError {
     fn from(err: io::Error) -> Self {
-        BeanstalkcError::ConnectionError(err.to_string())
+        BeanstalkcError::Io(Arc::new(err))
     }
 }
 
 impl From<AddrParseError> for BeanstalkcError {
     fn from(err: AddrParseError) -> Self {
-        BeanstalkcError::ConnectionError(err.to_string())
+        BeanstalkcError::InvalidAddress(err)
     }
 }
 
 impl From<ParseIntError> for BeanstalkcError {
     fn from(err: ParseIntError) -> Self {
-        BeanstalkcError::UnexpectedResponse(err.to_string())
+        BeanstalkcError::Parse(Arc::new(err))
     }
 }
 
 impl From<FromUtf8Error> for BeanstalkcError {
     fn from(err: FromUtf8Error) -> Self {
-        BeanstalkcError::UnexpectedResponse(err.to_string())
+        BeanstalkcError::Parse(Arc::new(err))
     }
 }
 
 impl From<Utf8Error> for BeanstalkcError {
     fn from(err: Utf8Error) -> Self {
-        BeanstalkcError::UnexpectedResponse(err.to_string())
+        BeanstalkcError::Parse(Arc::new(err))
     }
 }
 
 pub type BeanstalkcResult<T> = Result<T, BeanstalkcError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source() {
+        let err =
+            BeanstalkcError::from(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"));
+        assert_eq!("Connection error: refused", err.to_string());
+        assert!(err.is_connection_error());
+        let source = err.source().unwrap().downcast_ref::<io::Error>().unwrap();
+        assert_eq!(io::ErrorKind::ConnectionRefused, source.kind());
+
+        let err = BeanstalkcError::from("x".parse::<u64>().unwrap_err());
+        assert_eq!(
+            "Unexpected response: invalid digit found in string",
+            err.to_string()
+        );
+        assert!(!err.is_connection_error());
+        assert!(err.source().unwrap().is::<ParseIntError>());
+
+        assert!(BeanstalkcError::CommandFailed("".to_string())
+            .source()
+            .is_none());
+
+        let err = BeanstalkcError::BodyTooLarge { id: 1, size: 10 };
+        assert!(err.is_job_error());
+        assert!(!err.is_connection_error());
+    }
+
+    #[test]
+    fn test_unexpected_status() {
+        let err = BeanstalkcError::UnexpectedStatus {
+            command: CommandKind::PeekJob,
+            status: Status::Reserved,
+            params: vec!["1".to_string(), "4".to_string()],
+        };
+        assert_eq!("Unexpected response: Reserved 1 4 to peek", err.to_string());
+    }
+
+    #[test]
+    fn test_non_utf8_body() {
+        let body = vec![b'a', b'b', 0xff];
+        let err = BeanstalkcError::NonUtf8Body {
+            id: 3,
+            error: std::str::from_utf8(&body).unwrap_err(),
+        };
+        assert_eq!(
+            "Non UTF-8 body: job 3: invalid utf-8 sequence of 1 bytes from index 2",
+            err.to_string()
+        );
+        assert!(!err.is_connection_error());
+        assert!(err.is_job_error());
+        assert!(err.source().unwrap().is::<Utf8Error>());
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::Beanstalkc;
+
+/// `StatsExporter` publishes beanstalkd server and tube stats as metrics.
+///
+/// Every interval it runs `stats` and `stats-tube`, and records each numeric stat through
+/// the [`metrics`] facade, so any installed recorder such as a Prometheus exporter can
+/// serve them. Names are prefixed with `beanstalkd_` with dashes replaced by underscores,
+/// e.g. `beanstalkd_current_jobs_ready`, and tube stats carry a `tube` label. Cumulative
+/// stats (`cmd-*`, `total-*`, ...) are counters, the others gauges, including the
+/// `rusage-*` CPU times in fractional seconds which counters could not hold. Tubes which
+/// do not exist (anymore) are skipped. `beanstalkd_up` is 1 when the last poll succeeded
+/// and 0 otherwise.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use std::time::Duration;
+/// use beanstalkc::{Beanstalkc, StatsExporter};
+///
+/// let conn = Beanstalkc::new().connect().await.unwrap();
+/// StatsExporter::new(conn, Duration::from_secs(15))
+///     .tubes(&["emails", "reports"])
+///     .spawn();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct StatsExporter {
+    conn: Beanstalkc,
+    interval: Duration,
+    tubes: Option<Vec<String>>,
+}
+
+impl StatsExporter {
+    /// Create an exporter polling through `conn` every `interval`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn new(conn: Beanstalkc, interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "export interval must be positive");
+        StatsExporter {
+            conn,
+            interval,
+            tubes: None,
+        }
+    }
+
+    /// Only export the stats of `tubes`, instead of every existing tube.
+    pub fn tubes(mut self, tubes: &[&str]) -> Self {
+        self.tubes = Some(tubes.iter().map(|tube| tube.to_string()).collect());
+        self
+    }
+
+    /// Spawn a task exporting stats until it is aborted. Must be called from within a
+    /// tokio runtime.
+    pub fn spawn(mut self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(self.interval);
+            loop {
+                ticks.tick().await;
+                let up = self.export().await.is_ok();
+                metrics::gauge!("beanstalkd_up").set(if up { 1.0 } else { 0.0 });
+            }
+        })
+    }
+
+    /// Poll the stats once and record them.
+    pub async fn export(&mut self) -> BeanstalkcResult<()> {
+        record(&self.conn.stats().await?, None);
+
+        let tubes = match &self.tubes {
+            Some(tubes) => tubes.clone(),
+            None => self.conn.tubes().await?.into_vec(),
+        };
+        for tube in tubes {
+            match self.conn.stats_tube(&tube).await {
+                Ok(stats) => record(&stats, Some(&tube)),
+                Err(BeanstalkcError::TubeNotFound(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Record the numeric stats of `stats`, labeled with `tube` if any.
+fn record(stats: &HashMap<String, String>, tube: Option<&str>) {
+    let labels: Vec<(&'static str, String)> = tube
+        .map(|tube| vec![("tube", tube.to_string())])
+        .unwrap_or_default();
+    for (stat, value) in stats {
+        let value = match value.parse::<f64>() {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let name = metric_name(stat);
+        if is_cumulative(stat) {
+            metrics::counter!(name, &labels).absolute(value as u64);
+        } else {
+            metrics::gauge!(name, &labels).set(value);
+        }
+    }
+}
+
+fn metric_name(stat: &str) -> String {
+    format!("beanstalkd_{}", stat.replace('-', "_"))
+}
+
+/// Return whether `stat` only ever grows while the server runs.
+fn is_cumulative(stat: &str) -> bool {
+    stat.starts_with("cmd-")
+        || stat.starts_with("total-")
+        || stat == "job-timeouts"
+        || stat == "binlog-records-written"
+        || stat == "binlog-records-migrated"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+
+    #[test]
+    fn test_metric_name() {
+        assert_eq!(
+            "beanstalkd_current_jobs_ready",
+            metric_name("current-jobs-ready")
+        );
+        assert!(is_cumulative("cmd-put"));
+        assert!(is_cumulative("total-jobs"));
+        assert!(!is_cumulative("current-jobs-buried"));
+        assert!(!is_cumulative("rusage-utime"));
+    }
+
+    #[test]
+    #[should_panic(expected = "export interval must be positive")]
+    fn test_zero_interval() {
+        StatsExporter::new(Beanstalkc::new(), Duration::from_secs(0));
+    }
+
+    #[tokio::test]
+    async fn test_export_skips_missing_tubes() {
+        let server = FakeServer::start().await;
+        server.reply("stats", "OK 14\r\n---\nuptime: 1\n");
+        let conn = server.client().connect().await.unwrap();
+        let mut exporter =
+            StatsExporter::new(conn, Duration::from_secs(15)).tubes(&["missing", "default"]);
+        exporter.export().await.unwrap();
+        assert_eq!(
+            vec!["stats", "stats-tube missing", "stats-tube default"],
+            server.commands()
+        );
+    }
+}
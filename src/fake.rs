@@ -0,0 +1,463 @@
+//! A minimal in-process beanstalkd for the tests which need a server.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::Beanstalkc;
+
+#[derive(Debug, Clone)]
+struct FakeJob {
+    tube: String,
+    priority: u32,
+    ttr: u64,
+    body: Vec<u8>,
+    state: &'static str,
+    ready_at: Instant,
+    owner: Option<usize>,
+    reserves: u64,
+    releases: u64,
+    buries: u64,
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    next_id: u64,
+    next_conn: usize,
+    jobs: BTreeMap<u64, FakeJob>,
+    replies: HashMap<String, String>,
+    commands: Vec<String>,
+    connections: usize,
+}
+
+impl Store {
+    /// Return the next ready job of `tubes`, promoting delayed jobs which are due.
+    fn ready_in(&mut self, tubes: &[String]) -> Option<u64> {
+        let now = Instant::now();
+        for job in self.jobs.values_mut() {
+            if job.state == "delayed" && job.ready_at <= now {
+                job.state = "ready";
+            }
+        }
+        self.jobs
+            .iter()
+            .filter(|(_, job)| job.state == "ready" && tubes.contains(&job.tube))
+            .min_by_key(|(id, job)| (job.priority, **id))
+            .map(|(id, _)| *id)
+    }
+}
+
+/// A fake beanstalkd listening on a local port, answering the commands used by the tests.
+#[derive(Debug, Clone)]
+pub(crate) struct FakeServer {
+    port: u16,
+    store: Arc<Mutex<Store>>,
+    drops: Arc<watch::Sender<u64>>,
+}
+
+impl FakeServer {
+    pub(crate) async fn start() -> FakeServer {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let store = Arc::new(Mutex::new(Store {
+            next_id: 1,
+            ..Store::default()
+        }));
+        let (drops, _) = watch::channel(0);
+        let server = FakeServer {
+            port,
+            store,
+            drops: Arc::new(drops),
+        };
+        let accepting = server.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let conn = accepting.clone();
+                tokio::spawn(async move { conn.serve(stream).await });
+            }
+        });
+        server
+    }
+
+    /// Return a client of this server, not connected yet.
+    pub(crate) fn client(&self) -> Beanstalkc {
+        Beanstalkc::new().host("127.0.0.1").port(self.port)
+    }
+
+    /// Return the local port this server listens on.
+    pub(crate) fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Answer the command `line` with `response` instead of running it.
+    pub(crate) fn reply(&self, line: &str, response: &str) {
+        self.store
+            .lock()
+            .unwrap()
+            .replies
+            .insert(line.to_string(), format!("{}\r\n", response));
+    }
+
+    /// Put a job straight into `tube`. Return its id.
+    pub(crate) fn put(&self, tube: &str, body: &[u8]) -> u64 {
+        let mut store = self.store.lock().unwrap();
+        let id = store.next_id;
+        store.next_id += 1;
+        store
+            .jobs
+            .insert(id, new_job(tube, 0, 0, 60, body.to_vec()));
+        id
+    }
+
+    /// Return the state of job `id`, or `None` once deleted.
+    pub(crate) fn state(&self, id: u64) -> Option<&'static str> {
+        self.store
+            .lock()
+            .unwrap()
+            .jobs
+            .get(&id)
+            .map(|job| job.state)
+    }
+
+    /// Return the tube of job `id`, and its body.
+    pub(crate) fn job(&self, id: u64) -> Option<(String, Vec<u8>)> {
+        let store = self.store.lock().unwrap();
+        store
+            .jobs
+            .get(&id)
+            .map(|job| (job.tube.clone(), job.body.clone()))
+    }
+
+    /// Return the command lines received so far.
+    pub(crate) fn commands(&self) -> Vec<String> {
+        self.store.lock().unwrap().commands.clone()
+    }
+
+    /// Return the number of connections accepted so far.
+    pub(crate) fn connections(&self) -> usize {
+        self.store.lock().unwrap().connections
+    }
+
+    /// Close every open connection, as a restarting server would.
+    pub(crate) fn drop_connections(&self) {
+        self.drops.send_modify(|drops| *drops += 1);
+    }
+
+    /// Return the ids of the jobs in `state`.
+    pub(crate) fn jobs_in(&self, state: &str) -> Vec<u64> {
+        let store = self.store.lock().unwrap();
+        store
+            .jobs
+            .iter()
+            .filter(|(_, job)| job.state == state)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    async fn serve(self, stream: TcpStream) {
+        let conn = {
+            let mut store = self.store.lock().unwrap();
+            store.connections += 1;
+            store.next_conn += 1;
+            store.next_conn
+        };
+        let mut drops = self.drops.subscribe();
+        let mut stream = BufReader::new(stream);
+        let mut using = "default".to_string();
+        let mut watching = vec!["default".to_string()];
+        loop {
+            let mut line = String::new();
+            let read = tokio::select! {
+                read = stream.read_line(&mut line) => read,
+                _ = drops.changed() => break,
+            };
+            match read {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let line = line.trim_end().to_string();
+            let args: Vec<&str> = line.split_whitespace().collect();
+            let body = match args.as_slice() {
+                ["put", .., len] => {
+                    let mut body = vec![0; len.parse::<usize>().unwrap_or(0) + 2];
+                    if stream.read_exact(&mut body).await.is_err() {
+                        break;
+                    }
+                    body.truncate(body.len() - 2);
+                    body
+                }
+                _ => vec![],
+            };
+
+            let canned = {
+                let mut store = self.store.lock().unwrap();
+                store.commands.push(line.clone());
+                store.replies.get(&line).cloned()
+            };
+            let response = match canned {
+                Some(response) => response.into_bytes(),
+                None => match args.as_slice() {
+                    ["quit"] => break,
+                    ["reserve"] => self.reserve(conn, &watching, None).await,
+                    ["reserve-with-timeout", secs] => {
+                        let secs = secs.parse().unwrap_or(0);
+                        self.reserve(conn, &watching, Some(Duration::from_secs(secs)))
+                            .await
+                    }
+                    _ => self.run(conn, &args, body, &mut using, &mut watching),
+                },
+            };
+            if stream.get_mut().write_all(&response).await.is_err() {
+                break;
+            }
+        }
+
+        let mut store = self.store.lock().unwrap();
+        for job in store.jobs.values_mut() {
+            if job.owner == Some(conn) {
+                job.owner = None;
+                job.state = "ready";
+            }
+        }
+    }
+
+    async fn reserve(
+        &self,
+        conn: usize,
+        watching: &[String],
+        timeout: Option<Duration>,
+    ) -> Vec<u8> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            {
+                let mut store = self.store.lock().unwrap();
+                if let Some(id) = store.ready_in(watching) {
+                    let job = store.jobs.get_mut(&id).unwrap();
+                    job.state = "reserved";
+                    job.owner = Some(conn);
+                    job.reserves += 1;
+                    return framed(format!("RESERVED {} {}", id, job.body.len()), &job.body);
+                }
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return b"TIMED_OUT\r\n".to_vec();
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    fn run(
+        &self,
+        conn: usize,
+        args: &[&str],
+        body: Vec<u8>,
+        using: &mut String,
+        watching: &mut Vec<String>,
+    ) -> Vec<u8> {
+        let mut store = self.store.lock().unwrap();
+        let id = args.get(1).and_then(|id| id.parse::<u64>().ok());
+        let owned = |store: &Store, id: Option<u64>| {
+            id.filter(|id| {
+                store
+                    .jobs
+                    .get(id)
+                    .is_some_and(|job| job.state != "reserved" || job.owner == Some(conn))
+            })
+        };
+        let line = match args {
+            ["put", priority, delay, ttr, _] => {
+                let id = store.next_id;
+                store.next_id += 1;
+                let mut job = new_job(
+                    using,
+                    priority.parse().unwrap_or(0),
+                    delay.parse().unwrap_or(0),
+                    ttr.parse().unwrap_or(1),
+                    body,
+                );
+                if job.ready_at > Instant::now() {
+                    job.state = "delayed";
+                }
+                store.jobs.insert(id, job);
+                format!("INSERTED {}", id)
+            }
+            ["use", tube] => {
+                *using = tube.to_string();
+                format!("USING {}", tube)
+            }
+            ["watch", tube] => {
+                if !watching.iter().any(|t| t == tube) {
+                    watching.push(tube.to_string());
+                }
+                format!("WATCHING {}", watching.len())
+            }
+            ["ignore", tube] => {
+                if watching.len() == 1 && watching[0] == *tube {
+                    "NOT_IGNORED".to_string()
+                } else {
+                    watching.retain(|t| t != tube);
+                    format!("WATCHING {}", watching.len())
+                }
+            }
+            ["list-tube-used"] => format!("USING {}", using),
+            ["list-tubes-watched"] => return ok_yaml(watching.iter().map(|t| format!("- {}", t))),
+            ["list-tubes"] => {
+                let mut tubes: Vec<String> = store.jobs.values().map(|j| j.tube.clone()).collect();
+                tubes.push("default".to_string());
+                tubes.sort();
+                tubes.dedup();
+                return ok_yaml(tubes.iter().map(|t| format!("- {}", t)));
+            }
+            ["delete", _] => match owned(&store, id) {
+                Some(id) => {
+                    store.jobs.remove(&id);
+                    "DELETED".to_string()
+                }
+                None => "NOT_FOUND".to_string(),
+            },
+            ["release", _, priority, delay] => match owned(&store, id) {
+                Some(id) if store.jobs[&id].state == "reserved" => {
+                    let job = store.jobs.get_mut(&id).unwrap();
+                    let delay = delay.parse().unwrap_or(0);
+                    job.priority = priority.parse().unwrap_or(0);
+                    job.owner = None;
+                    job.releases += 1;
+                    job.ready_at = Instant::now() + Duration::from_secs(delay);
+                    job.state = if delay > 0 { "delayed" } else { "ready" };
+                    "RELEASED".to_string()
+                }
+                _ => "NOT_FOUND".to_string(),
+            },
+            ["bury", _, priority] => match owned(&store, id) {
+                Some(id) if store.jobs[&id].state == "reserved" => {
+                    let job = store.jobs.get_mut(&id).unwrap();
+                    job.priority = priority.parse().unwrap_or(0);
+                    job.owner = None;
+                    job.buries += 1;
+                    job.state = "buried";
+                    "BURIED".to_string()
+                }
+                _ => "NOT_FOUND".to_string(),
+            },
+            ["touch", _] => match owned(&store, id) {
+                Some(id) if store.jobs[&id].state == "reserved" => "TOUCHED".to_string(),
+                _ => "NOT_FOUND".to_string(),
+            },
+            ["peek", _] => match id.and_then(|id| store.jobs.get(&id)) {
+                Some(job) => {
+                    return framed(
+                        format!("FOUND {} {}", id.unwrap(), job.body.len()),
+                        &job.body,
+                    )
+                }
+                None => "NOT_FOUND".to_string(),
+            },
+            ["peek-ready"] | ["peek-delayed"] | ["peek-buried"] => {
+                let state = &args[0]["peek-".len()..];
+                let found = store
+                    .jobs
+                    .iter()
+                    .find(|(_, job)| job.state == state && job.tube == *using);
+                match found {
+                    Some((id, job)) => {
+                        return framed(format!("FOUND {} {}", id, job.body.len()), &job.body)
+                    }
+                    None => "NOT_FOUND".to_string(),
+                }
+            }
+            ["stats-job", _] => match id.and_then(|id| store.jobs.get(&id)) {
+                Some(job) => {
+                    return ok_yaml(
+                        vec![
+                            format!("id: {}", id.unwrap()),
+                            format!("tube: {}", job.tube),
+                            format!("state: {}", job.state),
+                            format!("pri: {}", job.priority),
+                            "age: 0".to_string(),
+                            "delay: 0".to_string(),
+                            format!("ttr: {}", job.ttr),
+                            format!("time-left: {}", time_left(job)),
+                            format!("reserves: {}", job.reserves),
+                            format!("releases: {}", job.releases),
+                            format!("buries: {}", job.buries),
+                        ]
+                        .into_iter(),
+                    )
+                }
+                None => "NOT_FOUND".to_string(),
+            },
+            ["stats-tube", tube] => {
+                let count = |state: &str| {
+                    store
+                        .jobs
+                        .values()
+                        .filter(|job| job.tube == *tube && job.state == state)
+                        .count()
+                };
+                if *tube != "default" && !store.jobs.values().any(|job| job.tube == *tube) {
+                    "NOT_FOUND".to_string()
+                } else {
+                    return ok_yaml(
+                        vec![
+                            format!("name: {}", tube),
+                            format!("current-jobs-ready: {}", count("ready")),
+                            format!("current-jobs-reserved: {}", count("reserved")),
+                            format!("current-jobs-delayed: {}", count("delayed")),
+                            format!("current-jobs-buried: {}", count("buried")),
+                        ]
+                        .into_iter(),
+                    );
+                }
+            }
+            _ => "UNKNOWN_COMMAND".to_string(),
+        };
+        format!("{}\r\n", line).into_bytes()
+    }
+}
+
+/// Return the delay left of a delayed job, or the TTR of the others.
+fn time_left(job: &FakeJob) -> u64 {
+    match job.state {
+        "delayed" => job
+            .ready_at
+            .saturating_duration_since(Instant::now())
+            .as_secs(),
+        _ => job.ttr,
+    }
+}
+
+fn new_job(tube: &str, priority: u32, delay: u64, ttr: u64, body: Vec<u8>) -> FakeJob {
+    FakeJob {
+        tube: tube.to_string(),
+        priority,
+        ttr,
+        body,
+        state: "ready",
+        ready_at: Instant::now() + Duration::from_secs(delay),
+        owner: None,
+        reserves: 0,
+        releases: 0,
+        buries: 0,
+    }
+}
+
+fn framed(line: String, body: &[u8]) -> Vec<u8> {
+    let mut response = format!("{}\r\n", line).into_bytes();
+    response.extend_from_slice(body);
+    response.extend_from_slice(b"\r\n");
+    response
+}
+
+fn ok_yaml(lines: impl Iterator<Item = String>) -> Vec<u8> {
+    let mut yaml = "---\n".to_string();
+    for line in lines {
+        yaml.push_str(&line);
+        yaml.push('\n');
+    }
+    framed(format!("OK {}", yaml.len()), yaml.as_bytes())
+}
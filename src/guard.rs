@@ -0,0 +1,86 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::job::Job;
+
+/// `JobGuard` releases a reserved job dropped before it was finalized, for instance because
+/// the task working on it returned early with an error or was cancelled.
+///
+/// The job is released with its priority before the next command sent through its
+/// connection, since it cannot be released while being dropped. Dereference the guard to
+/// work on the job.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use beanstalkc::Beanstalkc;
+///
+/// let mut conn = Beanstalkc::new().connect().await.unwrap();
+///
+/// let mut job = conn.reserve().await.unwrap().guard();
+/// if job.body().is_empty() {
+///     return; // Released before the next command.
+/// }
+/// job.delete().await.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct JobGuard<'a> {
+    job: Job<'a>,
+}
+
+impl<'a> JobGuard<'a> {
+    pub(crate) fn new(job: Job<'a>) -> Self {
+        JobGuard { job }
+    }
+}
+
+impl<'a> Deref for JobGuard<'a> {
+    type Target = Job<'a>;
+
+    fn deref(&self) -> &Job<'a> {
+        &self.job
+    }
+}
+
+impl<'a> DerefMut for JobGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Job<'a> {
+        &mut self.job
+    }
+}
+
+impl<'a> Drop for JobGuard<'a> {
+    fn drop(&mut self) {
+        if self.job.reserved() {
+            self.job.abandon();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fake::FakeServer;
+
+    #[tokio::test]
+    async fn test_dropped_guard_releases() {
+        let server = FakeServer::start().await;
+        let id = server.put("default", b"work");
+        let mut conn = server.client().connect().await.unwrap();
+
+        let job = conn.reserve().await.unwrap().guard();
+        assert_eq!(id, job.id());
+        drop(job);
+        assert_eq!(Some("reserved"), server.state(id));
+
+        conn.stats_tube("default").await.unwrap();
+        assert_eq!(Some("ready"), server.state(id));
+        let commands = server.commands();
+        let release = commands
+            .iter()
+            .position(|cmd| cmd.starts_with(&format!("release {} ", id)))
+            .unwrap();
+        let stats = commands.iter().position(|cmd| cmd == "stats-tube default");
+        assert!(Some(release) < stats, "{:?}", commands);
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tokio::net::TcpStream;
+
+type HandshakeFn = dyn Fn(TcpStream) -> BoxFuture<'static, io::Result<TcpStream>> + Send + Sync;
+
+/// Async callback run on every new stream before any command, set with
+/// [`Beanstalkc::on_connected`](crate::Beanstalkc::on_connected).
+#[derive(Clone)]
+pub(crate) struct Handshake(Arc<HandshakeFn>);
+
+impl Handshake {
+    pub(crate) fn new<F, Fut>(handshake: F) -> Self
+    where
+        F: Fn(TcpStream) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<TcpStream>> + Send + 'static,
+    {
+        Handshake(Arc::new(move |stream| handshake(stream).boxed()))
+    }
+
+    pub(crate) async fn run(&self, stream: TcpStream) -> io::Result<TcpStream> {
+        (self.0)(stream).await
+    }
+}
+
+impl fmt::Debug for Handshake {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handshake")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::fake::FakeServer;
+
+    #[tokio::test]
+    async fn test_handshake() {
+        let server = FakeServer::start().await;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let mut conn = server
+            .client()
+            .on_connected(move |mut stream| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    stream.write_all(b"use shaken\r\n").await?;
+                    let mut reply = [0; 14];
+                    stream.read_exact(&mut reply).await?;
+                    assert_eq!(b"USING shaken\r\n", &reply);
+                    Ok(stream)
+                }
+            })
+            .connect()
+            .await
+            .unwrap();
+
+        let id = conn.put_default(b"shaken").await.unwrap();
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+        assert_eq!("use shaken", server.commands()[0]);
+        assert_eq!(
+            Some(("shaken".to_string(), b"shaken".to_vec())),
+            server.job(id)
+        );
+
+        let failed = server
+            .client()
+            .on_connected(|_| async {
+                Err(io::Error::new(io::ErrorKind::PermissionDenied, "denied"))
+            })
+            .connect()
+            .await;
+        assert!(failed.is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+type BeatFn = dyn Fn() + Send + Sync;
+
+/// Callback invoked between the attempts of a blocking reserve, set with
+/// [`Beanstalkc::reserve_heartbeat`](crate::Beanstalkc::reserve_heartbeat).
+#[derive(Clone)]
+pub(crate) struct Heartbeat {
+    interval: Duration,
+    beat: Arc<BeatFn>,
+}
+
+impl Heartbeat {
+    pub(crate) fn new<F>(interval: Duration, beat: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        Heartbeat {
+            interval,
+            beat: Arc::new(beat),
+        }
+    }
+
+    /// Return the longest time a reserve attempt may block.
+    pub(crate) fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub(crate) fn beat(&self) {
+        (self.beat)()
+    }
+}
+
+impl fmt::Debug for Heartbeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Heartbeat({:?})", self.interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::fake::FakeServer;
+
+    #[tokio::test]
+    async fn test_reserve_heartbeat() {
+        let server = FakeServer::start().await;
+        let beats = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&beats);
+        let mut conn = server
+            .client()
+            .reserve_heartbeat(Duration::from_secs(1), move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .connect()
+            .await
+            .unwrap();
+
+        let fake = server.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(1500)).await;
+            fake.put("default", b"late");
+        });
+        let job = conn.reserve().await.unwrap();
+        assert_eq!(b"late", job.body());
+        assert_eq!(1, beats.load(Ordering::SeqCst));
+        assert_eq!(
+            vec!["reserve-with-timeout 1", "reserve-with-timeout 1"],
+            server.commands()
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use futures::FutureExt;
+
+use crate::command::{self, Command, Status};
+use crate::error::BeanstalkcResult;
+use crate::response::numeric;
+use crate::Beanstalkc;
+
+/// Head jobs of the ready, delayed and buried queues of a tube.
+type Heads = (
+    Option<JobSnapshot>,
+    Option<JobSnapshot>,
+    Option<JobSnapshot>,
+);
+
+/// `JobSnapshot` is a job at the head of one of the queues of a tube, with its stats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobSnapshot {
+    /// Job id.
+    pub id: u64,
+    /// Job body.
+    pub body: Vec<u8>,
+    /// Numeric stats of the job, e.g. `pri` or `age`.
+    pub stats: HashMap<String, u64>,
+}
+
+impl JobSnapshot {
+    /// Return the value of `stat`.
+    pub fn get(&self, stat: &str) -> Option<u64> {
+        self.stats.get(stat).copied()
+    }
+}
+
+/// `TubeSnapshot` describes the state of a tube at one point in time: its stats, and the
+/// job at the head of each of its ready, delayed and buried queues.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TubeSnapshot {
+    /// Tube name.
+    pub tube: String,
+    /// Numeric stats of the tube, e.g. `current-jobs-ready`.
+    pub stats: HashMap<String, u64>,
+    /// Next ready job, the one a reserve would return.
+    pub ready: Option<JobSnapshot>,
+    /// Delayed job with the shortest delay left.
+    pub delayed: Option<JobSnapshot>,
+    /// Next buried job, the one a kick would return to the ready queue.
+    pub buried: Option<JobSnapshot>,
+}
+
+impl TubeSnapshot {
+    /// Return the number of ready jobs.
+    pub fn ready_count(&self) -> u64 {
+        self.stat("current-jobs-ready")
+    }
+
+    /// Return the number of delayed jobs.
+    pub fn delayed_count(&self) -> u64 {
+        self.stat("current-jobs-delayed")
+    }
+
+    /// Return the number of buried jobs.
+    pub fn buried_count(&self) -> u64 {
+        self.stat("current-jobs-buried")
+    }
+
+    /// Return the number of reserved jobs.
+    pub fn reserved_count(&self) -> u64 {
+        self.stat("current-jobs-reserved")
+    }
+
+    fn stat(&self, stat: &str) -> u64 {
+        self.stats.get(stat).copied().unwrap_or(0)
+    }
+}
+
+/// `TubeInspector` takes snapshots of tubes for admin dashboards, combining `stats-tube`,
+/// `peek-ready`, `peek-delayed`, `peek-buried` and `stats-job` into one [`TubeSnapshot`].
+///
+/// Peeking requires using the tube: the tube used by the connection before the snapshot is
+/// used again afterwards. The commands are not atomic, so a head job may be missing from a
+/// snapshot if it was reserved or deleted meanwhile.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use beanstalkc::{Beanstalkc, TubeInspector};
+///
+/// let mut conn = Beanstalkc::new().connect().await.unwrap();
+///
+/// let snapshot = TubeInspector::new(&mut conn).snapshot("emails").await.unwrap();
+/// dbg!(snapshot.ready_count());
+/// if let Some(job) = &snapshot.buried {
+///     dbg!(job.id, job.get("age"));
+/// }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TubeInspector<'a> {
+    conn: &'a mut Beanstalkc,
+}
+
+impl<'a> TubeInspector<'a> {
+    /// Create an inspector running its commands through `conn`.
+    pub fn new(conn: &'a mut Beanstalkc) -> Self {
+        TubeInspector { conn }
+    }
+
+    /// Take a snapshot of tube `name`. Fail if the tube does not exist.
+    pub async fn snapshot(&mut self, name: &str) -> BeanstalkcResult<TubeSnapshot> {
+        let stats = numeric(self.conn.stats_tube(name).await?);
+        let (ready, delayed, buried) = self.conn.with_tube(name, |c| heads(c).boxed()).await?;
+
+        Ok(TubeSnapshot {
+            tube: name.to_string(),
+            stats,
+            ready,
+            delayed,
+            buried,
+        })
+    }
+
+    /// Take a snapshot of every existing tube.
+    pub async fn snapshot_all(&mut self) -> BeanstalkcResult<Vec<TubeSnapshot>> {
+        let mut snapshots = vec![];
+        for tube in self.conn.tubes().await? {
+            snapshots.push(self.snapshot(&tube).await?);
+        }
+        Ok(snapshots)
+    }
+}
+
+/// Return the head job of the ready, delayed and buried queues of the tube used by `conn`.
+async fn heads(conn: &mut Beanstalkc) -> BeanstalkcResult<Heads> {
+    let ready = head(conn, command::peek_ready()).await?;
+    let delayed = head(conn, command::peek_delayed()).await?;
+    let buried = head(conn, command::peek_buried()).await?;
+    Ok((ready, delayed, buried))
+}
+
+/// Peek a job with `cmd` and fetch its stats. Return `None` if there is no such job, or if
+/// it was deleted before its stats could be fetched.
+async fn head(conn: &mut Beanstalkc, cmd: Command<'_>) -> BeanstalkcResult<Option<JobSnapshot>> {
+    let resp = conn.send(cmd.expect(Status::NotFound)).await?;
+    if resp.status == Status::NotFound {
+        return Ok(None);
+    }
+    let id = resp.job_id()?;
+
+    let stats = conn
+        .send(command::stats_job(id).expect(Status::NotFound))
+        .await?;
+    if stats.status == Status::NotFound {
+        return Ok(None);
+    }
+    Ok(Some(JobSnapshot {
+        id,
+        body: resp.body.unwrap_or_default(),
+        stats: numeric(stats.body_as_map()?),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+
+    #[tokio::test]
+    async fn test_snapshot() {
+        let server = FakeServer::start().await;
+        let ready = server.put("emails", b"welcome");
+        server.put("emails", b"reminder");
+        let mut conn = server.client().connect().await.unwrap();
+        conn.use_tube("reports").await.unwrap();
+
+        let snapshot = TubeInspector::new(&mut conn)
+            .snapshot("emails")
+            .await
+            .unwrap();
+        assert_eq!("emails", snapshot.tube);
+        assert_eq!(2, snapshot.ready_count());
+        let head = snapshot.ready.unwrap();
+        assert_eq!(ready, head.id);
+        assert_eq!(b"welcome", head.body.as_slice());
+        assert_eq!(Some(0), head.get("pri"));
+        assert_eq!(None, snapshot.delayed);
+        assert_eq!(None, snapshot.buried);
+        assert_eq!("reports", conn.using().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restores_tube_on_error() {
+        let server = FakeServer::start().await;
+        server.put("emails", b"welcome");
+        server.reply("peek-delayed", "INTERNAL_ERROR");
+        let mut conn = server.client().connect().await.unwrap();
+
+        assert!(TubeInspector::new(&mut conn)
+            .snapshot("emails")
+            .await
+            .is_err());
+        assert_eq!("default", conn.using().await.unwrap());
+
+        assert!(matches!(
+            TubeInspector::new(&mut conn).snapshot("missing").await,
+            Err(crate::BeanstalkcError::TubeNotFound(_))
+        ));
+    }
+}
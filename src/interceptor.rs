@@ -0,0 +1,95 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::command::{Command, Status};
+use crate::error::BeanstalkcResult;
+
+/// `CommandInterceptor` observes, and may rewrite, every command sent by a client.
+///
+/// Interceptors are registered with [`Beanstalkc::interceptor`](crate::Beanstalkc::interceptor)
+/// and called in registration order. They enable audit logging, latency metrics or tube-name
+/// rewriting without patching the crate.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use beanstalkc::{BeanstalkcResult, Command, CommandInterceptor, CommandKind, Status};
+///
+/// #[derive(Debug)]
+/// struct Audit;
+///
+/// impl CommandInterceptor for Audit {
+///     fn before(&self, cmd: &mut Command<'_>) {
+///         if let CommandKind::Use | CommandKind::Watch = cmd.kind() {
+///             cmd.args_mut()[0].insert_str(0, "staging.");
+///         }
+///     }
+///
+///     fn after(&self, cmd: &Command<'_>, result: &BeanstalkcResult<Status>, latency: Duration) {
+///         println!("{} {:?} in {:?}", cmd.name(), result, latency);
+///     }
+/// }
+/// ```
+pub trait CommandInterceptor: Send + Sync {
+    /// Called before the command is written to the connection.
+    fn before(&self, _cmd: &mut Command<'_>) {}
+
+    /// Called once the response status has been read, or the command failed to be sent.
+    fn after(&self, _cmd: &Command<'_>, _result: &BeanstalkcResult<Status>, _latency: Duration) {}
+}
+
+impl fmt::Debug for dyn CommandInterceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CommandInterceptor")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::command::CommandKind;
+    use crate::fake::FakeServer;
+
+    #[derive(Debug)]
+    struct Staging(Arc<Mutex<Vec<String>>>);
+
+    impl CommandInterceptor for Staging {
+        fn before(&self, cmd: &mut Command<'_>) {
+            if let CommandKind::Use = cmd.kind() {
+                cmd.args_mut()[0].insert_str(0, "staging.");
+            }
+        }
+
+        fn after(&self, cmd: &Command<'_>, result: &BeanstalkcResult<Status>, _: Duration) {
+            let entry = format!("{} {:?}", cmd.name(), result.as_ref().ok());
+            self.0.lock().unwrap().push(entry);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptor() {
+        let server = FakeServer::start().await;
+        let calls = Arc::new(Mutex::new(vec![]));
+        let mut conn = server
+            .client()
+            .interceptor(Staging(Arc::clone(&calls)))
+            .connect()
+            .await
+            .unwrap();
+
+        conn.use_tube("jobs").await.unwrap();
+        let id = conn
+            .put(b"job", 0, Duration::from_secs(0), Duration::from_secs(10))
+            .await
+            .unwrap();
+
+        assert_eq!("staging.jobs", server.job(id).unwrap().0);
+        assert_eq!(
+            vec!["use Some(Using)", "put Some(Inserted)"],
+            *calls.lock().unwrap()
+        );
+    }
+}
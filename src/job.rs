@@ -1,39 +1,95 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt;
-use std::time::Duration;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 
-use crate::config::DEFAULT_JOB_DELAY;
-use crate::config::DEFAULT_JOB_PRIORITY;
-use crate::error::BeanstalkcResult;
+use futures::FutureExt;
+
+use crate::chain;
+use crate::dead_letter::DeadLetterAction;
+use crate::envelope::Envelope;
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::guard::JobGuard;
+use crate::process::{panic_message, Outcome, OversizeAction, PanicAction, ReservedJob};
+use crate::wire;
 use crate::Beanstalkc;
 
 /// `Job` is a simple abstraction about beanstalkd job.
-#[derive(Debug)]
+///
+/// `Display` and `Debug` summarize the job with the length of its body and its first
+/// bytes only, so that jobs with large bodies can be logged: see [`Job::dump_body`] for the
+/// whole body.
 pub struct Job<'a> {
     conn: &'a mut Beanstalkc,
     id: u64,
-    body: Vec<u8>,
+    body: Cow<'a, [u8]>,
+    streamed: bool,
     reserved: bool,
+    created_at: SystemTime,
 }
 
 impl<'a> fmt::Display for Job<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         write!(
             f,
-            "Job(id: {}, reserved: {}, body: \"{:?}\")",
-            self.id, self.reserved, self.body
+            "Job(id: {}, reserved: {}, body: {} bytes {})",
+            self.id,
+            self.reserved,
+            self.body.len(),
+            wire::preview(&self.body)
         )
     }
 }
 
+impl<'a> fmt::Debug for Job<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("Job")
+            .field("id", &self.id)
+            .field("reserved", &self.reserved)
+            .field("body_len", &self.body.len())
+            .field("body", &format_args!("{}", wire::preview(&self.body)))
+            .finish_non_exhaustive()
+    }
+}
+
 impl<'a> Job<'a> {
     /// Initialize and return the `Job` object.
-    pub fn new(conn: &'a mut Beanstalkc, job_id: u64, body: Vec<u8>, reserved: bool) -> Job {
+    pub fn new(conn: &'a mut Beanstalkc, job_id: u64, body: Vec<u8>, reserved: bool) -> Job<'a> {
+        Job {
+            conn,
+            id: job_id,
+            body: Cow::Owned(body),
+            streamed: false,
+            reserved,
+            created_at: SystemTime::now(),
+        }
+    }
+
+    /// Return a `Job` whose body was streamed to a writer of the caller, and is not held.
+    pub(crate) fn streamed(conn: &'a mut Beanstalkc, job_id: u64, reserved: bool) -> Job<'a> {
+        Job {
+            streamed: true,
+            ..Job::new(conn, job_id, vec![], reserved)
+        }
+    }
+
+    /// Return a `Job` borrowing its body from a buffer of the caller.
+    pub(crate) fn borrowed(
+        conn: &'a mut Beanstalkc,
+        job_id: u64,
+        body: &'a [u8],
+        reserved: bool,
+    ) -> Job<'a> {
         Job {
             conn,
             id: job_id,
-            body,
+            body: Cow::Borrowed(body),
+            streamed: false,
             reserved,
+            created_at: SystemTime::now(),
         }
     }
 
@@ -42,16 +98,94 @@ impl<'a> Job<'a> {
         self.id
     }
 
-    /// Return job body.
+    /// Return job body, empty for a job whose body was streamed by
+    /// [`Beanstalkc::reserve_into`] or [`Beanstalkc::peek_into`].
     pub fn body(&self) -> &[u8] {
         &self.body[..]
     }
 
+    /// Return the whole job body with non-printable bytes escaped, for the logs which need
+    /// the full payload rather than the summary of `Display`.
+    pub fn dump_body(&self) -> String {
+        self.body.escape_ascii().to_string()
+    }
+
+    /// Return job body as text, replacing invalid UTF-8 sequences with `U+FFFD`.
+    pub fn body_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    /// Decode the [`Envelope`] wrapping the job body, e.g. put with
+    /// [`Beanstalkc::put_with_headers`]. A body without an envelope is returned as the body
+    /// of an envelope without headers.
+    pub fn envelope(&self) -> BeanstalkcResult<Envelope> {
+        Envelope::decode(&self.body)
+    }
+
+    /// Return the headers of the [`Envelope`] wrapping the job body, in insertion order.
+    pub fn headers(&self) -> BeanstalkcResult<Vec<(String, String)>> {
+        let envelope = self.envelope()?;
+        Ok(envelope
+            .headers()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect())
+    }
+
+    /// Return when the job was put, from the `enqueued-at` header of its [`Envelope`], or
+    /// else from its `age` stat, with a precision of one second. An age the system clock
+    /// cannot go back by fails with `BeanstalkcError::UnexpectedResponse`.
+    pub async fn enqueued_at(&mut self) -> BeanstalkcResult<SystemTime> {
+        if let Some(enqueued_at) = self.envelope()?.enqueued_at() {
+            return Ok(enqueued_at);
+        }
+        let age = stat(&self.stats().await?, "age").unwrap_or(0);
+        SystemTime::now()
+            .checked_sub(Duration::from_secs(age))
+            .ok_or_else(|| {
+                BeanstalkcError::UnexpectedResponse(format!("job age out of range: {}", age))
+            })
+    }
+
+    /// Return how long the job waited between being put and being reserved or peeked by
+    /// this client, see [`Job::enqueued_at`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut job = conn.reserve().await.unwrap();
+    /// dbg!(job.queue_latency().await.unwrap());
+    /// }
+    /// ```
+    pub async fn queue_latency(&mut self) -> BeanstalkcResult<Duration> {
+        let enqueued_at = self.enqueued_at().await?;
+        Ok(self
+            .created_at
+            .duration_since(enqueued_at)
+            .unwrap_or_default())
+    }
+
     /// Return job reserving status.
     pub fn reserved(&self) -> bool {
         self.reserved
     }
 
+    /// Wrap this job in a [`JobGuard`], releasing it if it is dropped while still reserved.
+    pub fn guard(self) -> JobGuard<'a> {
+        JobGuard::new(self)
+    }
+
+    /// Give up this reserved job, which the connection releases before its next command.
+    pub(crate) fn abandon(&mut self) {
+        self.conn.abandon_job(self.id);
+        self.reserved = false;
+    }
+
     /// Delete this job.
     ///
     /// # Example
@@ -73,7 +207,8 @@ impl<'a> Job<'a> {
         Ok(())
     }
 
-    /// Release this job back to the ready queue with default priority and delay.
+    /// Release this job back to the ready queue with its current priority, and the default
+    /// delay configured on the client.
     ///
     /// # Example
     ///
@@ -90,7 +225,8 @@ impl<'a> Job<'a> {
     /// ```
     pub async fn release_default(&mut self) -> BeanstalkcResult<()> {
         let priority = self.priority().await;
-        self.release(priority, DEFAULT_JOB_DELAY).await
+        let (_, delay) = self.conn.release_defaults();
+        self.release(priority, delay).await
     }
 
     /// Release this job back to the ready queue with custom priority and delay.
@@ -119,7 +255,7 @@ impl<'a> Job<'a> {
         Ok(())
     }
 
-    /// Bury this job with default priority.
+    /// Bury this job with its current priority.
     ///
     /// # Example
     ///
@@ -166,6 +302,211 @@ impl<'a> Job<'a> {
         Ok(())
     }
 
+    /// Mark this reserved job as failed, applying the configured retry topology or
+    /// dead-letter policy.
+    ///
+    /// A job reserved from a tube of the [`RetryTopology`](crate::RetryTopology) is moved
+    /// into its next retry tube with the delay of that tube, or once its retries are
+    /// exhausted, buried or moved as configured by the topology.
+    ///
+    /// Otherwise, without a policy the job is released with its current priority and
+    /// default delay. With a policy the job is released after the policy retry delay, unless
+    /// its `releases` counter has reached the threshold, in which case it is buried or moved
+    /// into the dead-letter tube.
+    ///
+    /// Moving a job whose body was streamed by [`Beanstalkc::reserve_into`] fails with
+    /// `BeanstalkcError::BodyNotLoaded`, and the job stays reserved.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, DeadLetterPolicy};
+    ///
+    /// let mut conn = Beanstalkc::new()
+    ///        .dead_letter_policy(DeadLetterPolicy::bury_after(5))
+    ///        .connect()
+    ///        .await
+    ///        .unwrap();
+    ///
+    /// let mut job = conn.reserve().await.unwrap();
+    /// job.fail().await.unwrap();
+    /// }
+    /// ```
+    pub async fn fail(&mut self) -> BeanstalkcResult<()> {
+        if !self.reserved {
+            return Ok(());
+        }
+
+        if let Some(topology) = self.conn.get_retry_topology().cloned() {
+            let stats = self.stats().await?;
+            let tube = stats
+                .get("tube")
+                .map_or("", |tube| self.conn.strip_namespace(tube));
+            if topology.contains(tube) {
+                let priority = stat(&stats, "pri").unwrap_or(self.conn.release_defaults().0);
+                return match topology.next_retry(tube) {
+                    Some((retry, delay)) => self.move_to(retry, priority, delay, &stats).await,
+                    None => {
+                        self.dead_letter(topology.exhausted_action(), priority, &stats)
+                            .await
+                    }
+                };
+            }
+        }
+
+        let policy = match self.conn.get_dead_letter_policy() {
+            Some(policy) => policy.clone(),
+            None => return self.release_default().await,
+        };
+
+        let stats = self.stats().await?;
+        let priority = stat(&stats, "pri").unwrap_or(self.conn.release_defaults().0);
+        let releases = stat(&stats, "releases").unwrap_or(0);
+        if !policy.should_dead_letter(releases) {
+            return self.release(priority, policy.retry_delay).await;
+        }
+        self.dead_letter(&policy.action, priority, &stats).await
+    }
+
+    /// Bury this job or move it into a dead-letter tube according to `action`.
+    async fn dead_letter(
+        &mut self,
+        action: &DeadLetterAction,
+        priority: u32,
+        stats: &HashMap<String, String>,
+    ) -> BeanstalkcResult<()> {
+        match action {
+            DeadLetterAction::Bury => self.bury(priority).await,
+            DeadLetterAction::Move(tube) => {
+                let (_, delay) = self.conn.release_defaults();
+                self.move_to(tube, priority, delay, stats).await
+            }
+        }
+    }
+
+    /// Re-put the body of this job into `tube` with `delay`, keeping its priority and TTR,
+    /// and delete this job. Fail with `BeanstalkcError::BodyNotLoaded` if the body was
+    /// streamed, leaving the job reserved.
+    async fn move_to(
+        &mut self,
+        tube: &str,
+        priority: u32,
+        delay: Duration,
+        stats: &HashMap<String, String>,
+    ) -> BeanstalkcResult<()> {
+        if self.streamed {
+            return Err(BeanstalkcError::BodyNotLoaded(self.id));
+        }
+        let ttr = stat(stats, "ttr")
+            .map(Duration::from_secs)
+            .unwrap_or(self.conn.put_defaults().2);
+        let body = self.body.to_vec();
+        self.conn
+            .with_tube(tube, move |c| {
+                async move { c.put(&body, priority, delay, ttr).await }.boxed()
+            })
+            .await?;
+        self.delete().await
+    }
+
+    /// Run `handler` against this job and finalize the job from the handler result.
+    ///
+    /// `Ok(Outcome::Delete)`, `Ok(Outcome::Release { .. })` and `Ok(Outcome::Bury)` map to
+    /// the corresponding commands, while `Err` is handled by [`Job::fail`]. The handler
+    /// result is returned once the job has been finalized.
+    ///
+    /// If the handler panics, the panic is caught, the job is finalized according to the
+    /// configured [`PanicAction`] and `BeanstalkcError::HandlerPanicked` is returned with
+    /// the panic message.
+    ///
+    /// A job whose body was streamed by [`Beanstalkc::reserve_into`] is refused with
+    /// `BeanstalkcError::BodyNotLoaded`, before the handler runs.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, Outcome};
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut job = conn.reserve().await.unwrap();
+    /// let result = job
+    ///     .run(|job| async move {
+    ///         std::str::from_utf8(job.body())?;
+    ///         Ok::<_, std::str::Utf8Error>(Outcome::Delete)
+    ///     })
+    ///     .await
+    ///     .unwrap();
+    /// }
+    /// ```
+    pub async fn run<F, Fut, E>(&mut self, handler: F) -> BeanstalkcResult<Result<Outcome, E>>
+    where
+        F: FnOnce(ReservedJob) -> Fut,
+        Fut: Future<Output = Result<Outcome, E>>,
+    {
+        if self.streamed {
+            return Err(BeanstalkcError::BodyNotLoaded(self.id));
+        }
+        let job = ReservedJob::new(self.id, self.body.to_vec());
+        let result = AssertUnwindSafe(async move { handler(job).await })
+            .catch_unwind()
+            .await;
+
+        match result {
+            Ok(result) => {
+                self.finalize(&result).await?;
+                Ok(result)
+            }
+            Err(payload) => {
+                self.finalize_panicked().await?;
+                Err(BeanstalkcError::HandlerPanicked(panic_message(
+                    payload.as_ref(),
+                )))
+            }
+        }
+    }
+
+    /// Finalize this job after its handler panicked, according to the panic action.
+    pub(crate) async fn finalize_panicked(&mut self) -> BeanstalkcResult<()> {
+        match self.conn.get_panic_action() {
+            PanicAction::Fail => self.fail().await,
+            PanicAction::Release => self.release_default().await,
+            PanicAction::Bury => self.bury_default().await,
+        }
+    }
+
+    /// Finalize this job after its body was skipped for being too large, according to the
+    /// oversize action.
+    pub(crate) async fn finalize_oversized(&mut self) -> BeanstalkcResult<()> {
+        match self.conn.get_oversize_action() {
+            OversizeAction::Release => self.release_default().await,
+            OversizeAction::Bury => self.bury_default().await,
+        }
+    }
+
+    /// Finalize this job from a handler result.
+    pub(crate) async fn finalize<E>(
+        &mut self,
+        result: &Result<Outcome, E>,
+    ) -> BeanstalkcResult<()> {
+        match result {
+            Ok(Outcome::Delete) => {
+                chain::advance(self.conn, &self.body).await?;
+                self.delete().await
+            }
+            Ok(Outcome::Release { delay }) => {
+                let priority = self.priority().await;
+                self.release(priority, *delay).await
+            }
+            Ok(Outcome::Bury) => self.bury_default().await,
+            Err(_) => self.fail().await,
+        }
+    }
+
     /// Kick this job to ready queue.
     ///
     /// # Example
@@ -231,12 +572,115 @@ impl<'a> Job<'a> {
         self.conn.stats_job(self.id).await
     }
 
-    /// Return the job priority from this job stats. If not found, return the `DEFAULT_JOB_PRIORITY`.
-    async fn priority(&mut self) -> u32 {
+    /// Return the job priority from this job stats. If not found, return the default
+    /// priority configured on the client.
+    pub(crate) async fn priority(&mut self) -> u32 {
         let stats = self.stats().await.unwrap_or_default();
-        stats
-            .get("pri")
-            .map(|x| x.parse().unwrap_or(DEFAULT_JOB_PRIORITY))
-            .unwrap_or(DEFAULT_JOB_PRIORITY)
+        stat(&stats, "pri").unwrap_or(self.conn.release_defaults().0)
+    }
+}
+
+/// Parse a single field from job stats.
+fn stat<T: FromStr>(stats: &HashMap<String, String>, name: &str) -> Option<T> {
+    stats.get(name).and_then(|x| x.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+    use crate::topology::RetryTopology;
+
+    #[tokio::test]
+    async fn test_streamed_body_not_loaded() {
+        let server = FakeServer::start().await;
+        let id = server.put("jobs", b"payload");
+        let mut conn = server
+            .client()
+            .retry_topology(RetryTopology::new("jobs", &[Duration::from_secs(60)]))
+            .connect()
+            .await
+            .unwrap();
+
+        let mut body = vec![];
+        let mut job = conn.reserve_into(&mut body).await.unwrap();
+        assert_eq!(b"payload", &body[..]);
+        assert!(matches!(
+            job.fail().await,
+            Err(BeanstalkcError::BodyNotLoaded(job_id)) if job_id == id
+        ));
+        assert!(matches!(
+            job.run(|_| async { Ok::<_, ()>(Outcome::Delete) }).await,
+            Err(BeanstalkcError::BodyNotLoaded(_))
+        ));
+        assert!(job.reserved());
+        assert_eq!(Some("reserved"), server.state(id));
+    }
+
+    #[tokio::test]
+    async fn test_fail_moves_to_retry_tube() {
+        let server = FakeServer::start().await;
+        let id = server.put("jobs", b"payload");
+        let mut conn = server
+            .client()
+            .retry_topology(RetryTopology::new("jobs", &[Duration::from_secs(60)]))
+            .watch_tubes(["other"])
+            .connect()
+            .await
+            .unwrap();
+        conn.use_tube("results").await.unwrap();
+
+        let mut job = conn.reserve().await.unwrap();
+        job.fail().await.unwrap();
+        assert_eq!(None, server.state(id));
+        let retried = server.jobs_in("delayed");
+        assert_eq!(1, retried.len());
+        assert_eq!(
+            "jobs.retry.1m",
+            conn.stats_job(retried[0]).await.unwrap()["tube"]
+        );
+        assert_eq!("results", conn.using().await.unwrap());
+
+        let mut watching = conn.watching().await.unwrap().into_vec();
+        watching.sort();
+        assert_eq!(vec!["jobs", "jobs.retry.1m", "other"], watching);
+    }
+
+    #[tokio::test]
+    async fn test_enqueued_at_huge_age() {
+        let server = FakeServer::start().await;
+        let id = server.put("default", b"job");
+        let age = format!("age: {}\n", u64::MAX);
+        server.reply(
+            &format!("stats-job {}", id),
+            &format!("OK {}\r\n{}", age.len(), age),
+        );
+        let mut conn = server.client().connect().await.unwrap();
+
+        let mut job = conn.reserve().await.unwrap();
+        assert!(matches!(
+            job.enqueued_at().await,
+            Err(BeanstalkcError::UnexpectedResponse(msg)) if msg.starts_with("job age")
+        ));
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut conn = Beanstalkc::new();
+        let job = Job::new(&mut conn, 7, b"a\x00\n".to_vec(), true);
+        assert_eq!(
+            "Job(id: 7, reserved: true, body: 3 bytes \"a\\x00\\n\")",
+            job.to_string()
+        );
+        assert_eq!("a\\x00\\n", job.dump_body());
+
+        let job = Job::new(&mut conn, 8, vec![b'x'; 1 << 20], false);
+        assert_eq!(
+            format!(
+                "Job {{ id: 8, reserved: false, body_len: 1048576, body: \"{}\"..., .. }}",
+                "x".repeat(32)
+            ),
+            format!("{:?}", job)
+        );
     }
 }
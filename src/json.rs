@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Number, Value};
+
+use crate::error::BeanstalkcResult;
+use crate::Beanstalkc;
+
+impl Beanstalkc {
+    /// Return the statistical information about the beanstalkd server as a JSON object,
+    /// see [`stats_to_json`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let stats = conn.stats_json().await.unwrap();
+    /// println!("{}", stats);
+    /// }
+    /// ```
+    pub async fn stats_json(&mut self) -> BeanstalkcResult<Value> {
+        Ok(stats_to_json(&self.stats().await?))
+    }
+
+    /// Return the statistical information about the specified tube as a JSON object.
+    pub async fn stats_tube_json(&mut self, name: &str) -> BeanstalkcResult<Value> {
+        Ok(stats_to_json(&self.stats_tube(name).await?))
+    }
+
+    /// Return the statistical information about a job as a JSON object.
+    pub async fn stats_job_json(&mut self, job_id: u64) -> BeanstalkcResult<Value> {
+        Ok(stats_to_json(&self.stats_job(job_id).await?))
+    }
+}
+
+/// Convert stats returned by beanstalkd into a JSON object with sorted keys. Integer stats
+/// become JSON numbers, as well as the `rusage-*` durations in seconds; other stats, such
+/// as `version` or `hostname`, stay strings.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use beanstalkc::stats_to_json;
+///
+/// let mut stats = HashMap::new();
+/// stats.insert("current-jobs-ready".to_string(), "3".to_string());
+/// stats.insert("version".to_string(), "1.12".to_string());
+/// assert_eq!(
+///     r#"{"current-jobs-ready":3,"version":"1.12"}"#,
+///     stats_to_json(&stats).to_string()
+/// );
+/// ```
+pub fn stats_to_json(stats: &HashMap<String, String>) -> Value {
+    let object: Map<String, Value> = stats
+        .iter()
+        .map(|(name, value)| (name.clone(), stat_to_json(name, value)))
+        .collect();
+    Value::Object(object)
+}
+
+fn stat_to_json(name: &str, value: &str) -> Value {
+    if let Ok(n) = value.parse::<u64>() {
+        return Value::Number(n.into());
+    }
+    if name.starts_with("rusage-") {
+        if let Some(n) = value.parse().ok().and_then(Number::from_f64) {
+            return Value::Number(n);
+        }
+    }
+    Value::String(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stat_to_json() {
+        assert_eq!(Value::from(42), stat_to_json("total-jobs", "42"));
+        assert_eq!(Value::from(0.25), stat_to_json("rusage-utime", "0.250000"));
+        assert_eq!(Value::from("1.12"), stat_to_json("version", "1.12"));
+        assert_eq!(Value::from("false"), stat_to_json("draining", "false"));
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::command::{Command, CommandKind, Status};
+use crate::error::BeanstalkcResult;
+use crate::interceptor::CommandInterceptor;
+
+/// Number of buckets per power of two of microseconds, as a power of two. Quantiles are
+/// reported within 1/8 of the actual latency.
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+
+/// `LatencyHistogram` counts command latencies in log-linear buckets: each power of two of
+/// microseconds is split into 8 buckets, so quantiles are within 12.5% of the actual latency
+/// with a fixed amount of memory.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyHistogram {
+    buckets: Vec<u64>,
+    count: u64,
+    sum: Duration,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram.
+    pub fn new() -> Self {
+        LatencyHistogram::default()
+    }
+
+    /// Count a latency.
+    pub fn record(&mut self, latency: Duration) {
+        let index = bucket_index(latency.as_micros() as u64);
+        if self.buckets.len() <= index {
+            self.buckets.resize(index + 1, 0);
+        }
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.sum += latency;
+        self.max = self.max.max(latency);
+    }
+
+    /// Return the number of latencies counted.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Return the mean latency, or zero if none was counted.
+    pub fn mean(&self) -> Duration {
+        match self.count {
+            0 => Duration::from_secs(0),
+            count => Duration::from_nanos((self.sum.as_nanos() / u128::from(count)) as u64),
+        }
+    }
+
+    /// Return the highest latency counted.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Return the latency below which a fraction `q` of the latencies fall, e.g. `0.99` for
+    /// the 99th percentile, or zero if none was counted.
+    pub fn quantile(&self, q: f64) -> Duration {
+        let rank = (q.clamp(0.0, 1.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_micros(bucket_upper_bound(index)).min(self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Return the median latency.
+    pub fn p50(&self) -> Duration {
+        self.quantile(0.5)
+    }
+
+    /// Return the 99th percentile latency.
+    pub fn p99(&self) -> Duration {
+        self.quantile(0.99)
+    }
+}
+
+/// Return the bucket counting a latency of `micros` microseconds.
+fn bucket_index(micros: u64) -> usize {
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let shift = 63 - micros.leading_zeros() - SUB_BUCKET_BITS;
+    (u64::from(shift) * SUB_BUCKETS + (micros >> shift)) as usize
+}
+
+/// Return the lowest latency in microseconds above the bucket `index`.
+fn bucket_upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index + 1;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let top = index % SUB_BUCKETS + SUB_BUCKETS;
+    (top + 1).saturating_mul(1 << shift)
+}
+
+/// `LatencyReport` holds the latency histogram of each command kind sent by a client, see
+/// [`Beanstalkc::record_latencies`](crate::Beanstalkc::record_latencies).
+///
+/// Latencies run from writing the command until its response status is read, so blocking
+/// commands such as `reserve` include the time spent waiting for a job.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyReport {
+    commands: HashMap<CommandKind, LatencyHistogram>,
+}
+
+impl LatencyReport {
+    /// Return the histogram of commands of `kind`, if any was sent.
+    pub fn get(&self, kind: CommandKind) -> Option<&LatencyHistogram> {
+        self.commands.get(&kind)
+    }
+
+    /// Return the histogram of each command kind sent.
+    pub fn iter(&self) -> impl Iterator<Item = (CommandKind, &LatencyHistogram)> {
+        self.commands
+            .iter()
+            .map(|(kind, histogram)| (*kind, histogram))
+    }
+}
+
+/// Records the latency of every command answered by the server.
+#[derive(Debug, Default)]
+pub(crate) struct LatencyRecorder {
+    report: Mutex<LatencyReport>,
+}
+
+impl LatencyRecorder {
+    /// Return a copy of the histograms recorded so far.
+    pub(crate) fn report(&self) -> LatencyReport {
+        self.report.lock().unwrap().clone()
+    }
+}
+
+impl CommandInterceptor for LatencyRecorder {
+    fn after(&self, cmd: &Command<'_>, result: &BeanstalkcResult<Status>, latency: Duration) {
+        if result.is_ok() {
+            self.report
+                .lock()
+                .unwrap()
+                .commands
+                .entry(cmd.kind())
+                .or_default()
+                .record(latency);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets() {
+        for micros in (0..100_000).chain(vec![u64::MAX / 4, u64::MAX / 2]) {
+            let index = bucket_index(micros);
+            assert!(micros < bucket_upper_bound(index), "{}", micros);
+            assert!(
+                index == 0 || micros >= bucket_upper_bound(index - 1),
+                "{}",
+                micros
+            );
+        }
+    }
+
+    #[test]
+    fn test_quantile() {
+        let mut histogram = LatencyHistogram::new();
+        assert_eq!(Duration::from_secs(0), histogram.p99());
+
+        for millis in 1..=100 {
+            histogram.record(Duration::from_millis(millis));
+        }
+        assert_eq!(100, histogram.count());
+        assert_eq!(Duration::from_micros(50_500), histogram.mean());
+        assert_eq!(Duration::from_millis(100), histogram.max());
+
+        let p50 = histogram.p50().as_secs_f64();
+        assert!((0.050..=0.050 * 1.125).contains(&p50), "{}", p50);
+        let p99 = histogram.p99().as_secs_f64();
+        assert!((0.099..=0.100).contains(&p99), "{}", p99);
+    }
+}
@@ -48,14 +48,101 @@
 //! job.delete().await.unwrap();
 //! }
 //! ```
+pub use crate::audit::AuditedJob;
+pub use crate::backlog::{BacklogAction, BacklogLimit};
+pub use crate::batch::JobBatch;
 pub use crate::beanstalkc::Beanstalkc;
+pub use crate::chain::{chain, Chain};
+pub use crate::circuit::CircuitBreaker;
+pub use crate::cluster::{
+    aggregate_stats, ClusterStats, HostStats, ServerStats, StatsReport, TubeStats,
+};
+pub use crate::command::{Command, CommandKind, Status};
+pub use crate::dead_letter::{DeadLetterAction, DeadLetterPolicy};
+pub use crate::dedup::{DedupStore, MemoryDedupStore};
+pub use crate::envelope::Envelope;
 pub use crate::error::{BeanstalkcError, BeanstalkcResult};
+#[cfg(feature = "metrics")]
+pub use crate::exporter::StatsExporter;
+pub use crate::guard::JobGuard;
+pub use crate::inspector::{JobSnapshot, TubeInspector, TubeSnapshot};
+pub use crate::interceptor::CommandInterceptor;
 pub use crate::job::Job;
+#[cfg(feature = "json")]
+pub use crate::json::stats_to_json;
+pub use crate::latency::{LatencyHistogram, LatencyReport};
+pub use crate::listener::ConnectionListener;
+pub use crate::long_delay::LongDelay;
+pub use crate::monitor::{TubeStatsDelta, TubeStatsWatcher};
+pub use crate::pool::PooledJob;
+pub use crate::process::{Outcome, OversizeAction, PanicAction, ReservedJob, TimeoutAction};
+pub use crate::producer::{BatchedProducer, PendingPut};
+pub use crate::pump::{Completion, PumpOptions};
+pub use crate::put::PutRequest;
+pub use crate::rate_limit::RateLimit;
+pub use crate::response::{Response, ResponseParser};
+pub use crate::retry::RetryPolicy;
+pub use crate::scheduler::{MissedRuns, Schedule, Scheduler};
+pub use crate::shared::{Overflow, SharedClient};
+pub use crate::sink::PutSink;
+pub use crate::supervisor::ConnectionState;
+pub use crate::topology::RetryTopology;
+#[cfg(feature = "opentelemetry")]
+pub use crate::trace::TracedJob;
+pub use crate::tube::Tube;
+pub use crate::tube_set::TubeSet;
+pub use crate::worker::{DrainHandle, Worker};
 
+mod audit;
+mod backfill;
+mod backlog;
+mod batch;
 mod beanstalkc;
-mod command;
+mod chain;
+mod circuit;
+mod cluster;
+pub mod command;
 mod config;
+mod cron;
+mod dead_letter;
+mod dedup;
+mod envelope;
 mod error;
+#[cfg(feature = "metrics")]
+mod exporter;
+#[cfg(test)]
+mod fake;
+mod guard;
+mod handshake;
+mod heartbeat;
+mod inspector;
+mod interceptor;
 mod job;
+#[cfg(feature = "json")]
+mod json;
+mod latency;
+mod listener;
+mod logging;
+mod long_delay;
+mod monitor;
+mod pool;
+mod process;
+mod producer;
+mod pump;
+mod put;
+mod rate_limit;
 mod request;
+mod resolver;
 mod response;
+mod retry;
+mod scheduler;
+mod shared;
+mod sink;
+mod supervisor;
+mod topology;
+#[cfg(feature = "opentelemetry")]
+mod trace;
+mod tube;
+mod tube_set;
+mod wire;
+mod worker;
@@ -0,0 +1,111 @@
+use std::fmt;
+
+use crate::error::BeanstalkcError;
+
+/// `ConnectionListener` is notified of the lifecycle of the connection of a client.
+///
+/// Listeners are registered with
+/// [`Beanstalkc::connection_listener`](crate::Beanstalkc::connection_listener) and called in
+/// registration order. They enable logging or metrics around connection drops and
+/// reconnects. Tubes used and watched before a reconnect are selected again automatically
+/// before `on_connect` is called.
+///
+/// # Example
+///
+/// ```no_run
+/// use beanstalkc::{BeanstalkcError, ConnectionListener};
+///
+/// #[derive(Debug)]
+/// struct Log;
+///
+/// impl ConnectionListener for Log {
+///     fn on_connect(&self, addr: &str) {
+///         println!("connected to {}", addr);
+///     }
+///
+///     fn on_disconnect(&self, reason: Option<&BeanstalkcError>) {
+///         println!("disconnected: {:?}", reason);
+///     }
+///
+///     fn on_reconnect_attempt(&self, attempt: u32) {
+///         println!("reconnecting, attempt {}", attempt);
+///     }
+/// }
+/// ```
+pub trait ConnectionListener: Send + Sync {
+    /// Called once a connection to the server at `addr` has been established.
+    fn on_connect(&self, _addr: &str) {}
+
+    /// Called once the connection has been lost with the error which revealed it, or has
+    /// been closed by the client with `None`.
+    fn on_disconnect(&self, _reason: Option<&BeanstalkcError>) {}
+
+    /// Called before each attempt to replace a lost connection, starting at 1.
+    fn on_reconnect_attempt(&self, _attempt: u32) {}
+}
+
+impl fmt::Debug for dyn ConnectionListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ConnectionListener")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::fake::FakeServer;
+
+    #[derive(Debug)]
+    struct Events(Arc<Mutex<Vec<String>>>);
+
+    impl ConnectionListener for Events {
+        fn on_connect(&self, addr: &str) {
+            self.0.lock().unwrap().push(format!("connect {}", addr));
+        }
+
+        fn on_disconnect(&self, reason: Option<&BeanstalkcError>) {
+            let event = format!("disconnect {}", reason.is_some());
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn on_reconnect_attempt(&self, attempt: u32) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("reconnect {}", attempt));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_listener() {
+        let server = FakeServer::start().await;
+        let events = Arc::new(Mutex::new(vec![]));
+        let conn = server
+            .client()
+            .connection_listener(Events(Arc::clone(&events)))
+            .connect()
+            .await
+            .unwrap();
+        let addr = format!("127.0.0.1:{}", server.port());
+
+        let mut conn = conn.reconnect().await.unwrap();
+        conn.stats_tube("default").await.unwrap();
+        server.drop_connections();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(conn.stats_tube("default").await.is_err());
+
+        assert_eq!(
+            vec![
+                format!("connect {}", addr),
+                "disconnect false".to_string(),
+                "reconnect 1".to_string(),
+                format!("connect {}", addr),
+                "disconnect true".to_string(),
+            ],
+            *events.lock().unwrap()
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Connection and command events logged through the `log` crate, with the `log` feature.
+#![cfg_attr(not(feature = "log"), allow(unused_variables))]
+
+use crate::command::Command;
+use crate::error::BeanstalkcError;
+
+/// Target of the logs emitted by the client.
+#[cfg_attr(not(feature = "log"), allow(dead_code))]
+const TARGET: &str = "beanstalkc";
+
+/// Log a new connection to `addr`.
+pub(crate) fn connected(addr: &str) {
+    #[cfg(feature = "log")]
+    log::debug!(target: TARGET, "connected to {}", addr);
+}
+
+/// Log a connection dropped because of `err`.
+pub(crate) fn disconnected(err: &BeanstalkcError) {
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "connection lost: {}", err);
+}
+
+/// Log a reconnect attempt.
+pub(crate) fn reconnecting(attempt: u32) {
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "reconnecting, attempt {}", attempt);
+}
+
+/// Log the last reconnect attempt failing with `err`.
+pub(crate) fn reconnect_failed(err: &BeanstalkcError) {
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "giving up reconnecting: {}", err);
+}
+
+/// Log `cmd` failing with `err`.
+pub(crate) fn command_failed(cmd: &Command<'_>, err: &BeanstalkcError) {
+    #[cfg(feature = "log")]
+    log::debug!(target: TARGET, "{} failed: {}", cmd.kind(), err);
+}
+
+/// Log a reserved job skipped because of `err`.
+pub(crate) fn job_skipped(err: &BeanstalkcError) {
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "job skipped: {}", err);
+}
+
+/// Log job `id` failing to be finalized with `err`.
+pub(crate) fn job_failed(id: u64, err: &BeanstalkcError) {
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "finalizing job {} failed: {}", id, err);
+}
+
+/// Log the handler of job `id` panicking with `message`.
+pub(crate) fn handler_panicked(id: u64, message: &str) {
+    #[cfg(feature = "log")]
+    log::error!(target: TARGET, "handler of job {} panicked: {}", id, message);
+}
+
+/// Log dedup key `key` failing to be removed after its put failed, with `err`.
+pub(crate) fn dedup_remove_failed(key: &str, err: &BeanstalkcError) {
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "removing dedup key {} failed: {}", key, err);
+}
+
+/// Log a scheduled put into `tube` failing with `err`.
+pub(crate) fn scheduled_put_failed(tube: &str, err: &BeanstalkcError) {
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "scheduled put into {} failed: {}", tube, err);
+}
+
+/// Log a wire-level protocol `line`, when `tracing` is not enabled to log it.
+#[cfg_attr(feature = "tracing", allow(dead_code))]
+pub(crate) fn wire(line: &str) {
+    #[cfg(feature = "log")]
+    log::debug!(target: crate::wire::TARGET, "{}", line);
+}
+
+/// Log a response which could not be parsed.
+pub(crate) fn parse_error(err: &BeanstalkcError) {
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "{}", err);
+}
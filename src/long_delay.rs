@@ -0,0 +1,211 @@
+use std::future::{self, Future};
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::{DEFAULT_SCHEDULED_TUBE, DEFAULT_TUBE, LONG_DELAY_HOP};
+use crate::envelope::Envelope;
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::logging;
+use crate::Beanstalkc;
+
+const TUBE_HEADER: &str = "x-deliver-tube";
+const AT_HEADER: &str = "x-deliver-at";
+const PRIORITY_HEADER: &str = "x-deliver-priority";
+const TTR_HEADER: &str = "x-deliver-ttr";
+
+/// `LongDelay` puts jobs due at any wall-clock time, however far in the future.
+///
+/// Jobs due within an hour are put straight into their tube with the matching delay. The
+/// others are parked in a scheduled tube, wrapped into an [`Envelope`] recording their
+/// tube, due time, priority and TTR, and delayed by at most an hour at a time: a pump
+/// reserves them from the scheduled tube as they surface and puts them again, until they
+/// are due within the hour and go to their tube. Priorities are only applied then, so
+/// parked jobs never compete with ready ones.
+///
+/// Producers and the pump need their own `LongDelay`, each on its own connection. A job
+/// may be delivered twice if the pump stops between putting it and deleting the parked
+/// copy.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use std::time::{Duration, SystemTime};
+/// use beanstalkc::{Beanstalkc, LongDelay};
+///
+/// let conn = Beanstalkc::new().connect().await.unwrap();
+/// let mut producer = LongDelay::new(conn);
+/// let in_a_year = SystemTime::now() + Duration::from_secs(365 * 86400);
+/// producer
+///     .put_at("renewals", b"account-42", 0, in_a_year, Duration::from_secs(60))
+///     .await
+///     .unwrap();
+///
+/// let conn = Beanstalkc::new().connect().await.unwrap();
+/// let mut pump = LongDelay::new(conn);
+/// pump.run_pump_until(tokio::signal::ctrl_c()).await.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LongDelay {
+    conn: Beanstalkc,
+    scheduled_tube: String,
+}
+
+impl LongDelay {
+    /// Create a new `LongDelay` on a connected client, parking jobs in the `scheduled`
+    /// tube.
+    pub fn new(conn: Beanstalkc) -> Self {
+        LongDelay {
+            conn,
+            scheduled_tube: DEFAULT_SCHEDULED_TUBE.to_string(),
+        }
+    }
+
+    /// Park jobs in tube `name` instead.
+    pub fn scheduled_tube(mut self, name: &str) -> Self {
+        self.scheduled_tube = name.to_string();
+        self
+    }
+
+    /// Put a job into `tube`, to become ready at `at`. Return the id of the job put, which
+    /// is the id of the parked job if it is not due within an hour.
+    pub async fn put_at(
+        &mut self,
+        tube: &str,
+        body: &[u8],
+        priority: u32,
+        at: SystemTime,
+        ttr: Duration,
+    ) -> BeanstalkcResult<u64> {
+        let at = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let envelope = Envelope::new(body.to_vec())
+            .header(TUBE_HEADER, tube)
+            .header(AT_HEADER, &at.to_string())
+            .header(PRIORITY_HEADER, &priority.to_string())
+            .header(TTR_HEADER, &ttr.as_secs().to_string());
+        self.forward(&envelope).await
+    }
+
+    /// Move parked jobs as they surface until the connection fails. Parked jobs which
+    /// cannot be moved, e.g. with a malformed header or refused by the server, are buried
+    /// in the scheduled tube.
+    pub async fn run_pump(&mut self) -> BeanstalkcResult<()> {
+        self.run_pump_until(future::pending::<()>()).await
+    }
+
+    /// Move parked jobs like [`LongDelay::run_pump`] until `shutdown` completes.
+    pub async fn run_pump_until<S: Future>(&mut self, shutdown: S) -> BeanstalkcResult<()> {
+        tokio::pin!(shutdown);
+        self.conn.watch(&self.scheduled_tube).await?;
+        if self.scheduled_tube != DEFAULT_TUBE {
+            self.conn.ignore(DEFAULT_TUBE).await?;
+        }
+
+        loop {
+            let (id, body) = match self.conn.reserve_until(&mut shutdown).await? {
+                Some(job) => (job.id(), job.body().to_vec()),
+                None => return Ok(()),
+            };
+            let moved = match Envelope::decode(&body) {
+                Ok(envelope) if envelope.get_header(TUBE_HEADER).is_some() => {
+                    match self.forward(&envelope).await {
+                        Ok(_) => self.conn.delete(id).await,
+                        Err(err) if !err.is_connection_error() => {
+                            logging::job_failed(id, &err);
+                            self.conn.bury_default(id).await
+                        }
+                        Err(err) => Err(err),
+                    }
+                }
+                _ => self.conn.bury_default(id).await,
+            };
+            match moved {
+                Err(err) if err.is_connection_error() => return Err(err),
+                // The parked job may have expired and been handed to another pump.
+                Err(err) => logging::job_failed(id, &err),
+                Ok(()) => {}
+            }
+        }
+    }
+
+    /// Put the job described by `envelope` into its tube if it is due within an hour, or
+    /// park it again otherwise.
+    async fn forward(&mut self, envelope: &Envelope) -> BeanstalkcResult<u64> {
+        let tube = envelope.get_header(TUBE_HEADER).unwrap_or_default();
+        let at = UNIX_EPOCH + Duration::from_secs(parse_header(envelope, AT_HEADER)?);
+        let priority = parse_header(envelope, PRIORITY_HEADER)?;
+        let ttr = Duration::from_secs(parse_header(envelope, TTR_HEADER)?);
+
+        let remaining = at.duration_since(SystemTime::now()).unwrap_or_default();
+        if remaining <= LONG_DELAY_HOP {
+            self.conn
+                .tube(tube)
+                .put(envelope.body(), priority, round_up(remaining), ttr)
+                .await
+        } else {
+            self.conn
+                .tube(&self.scheduled_tube)
+                .put(&envelope.encode(), 0, LONG_DELAY_HOP, ttr)
+                .await
+        }
+    }
+}
+
+fn parse_header<T>(envelope: &Envelope, name: &str) -> BeanstalkcResult<T>
+where
+    T: FromStr<Err = ParseIntError>,
+{
+    let value = envelope.get_header(name).ok_or_else(|| {
+        BeanstalkcError::UnexpectedResponse(format!("scheduled job without {}", name))
+    })?;
+    Ok(value.parse()?)
+}
+
+/// Round `delay` up to whole seconds, so jobs never become ready early.
+pub(crate) fn round_up(delay: Duration) -> Duration {
+    Duration::from_secs(delay.as_secs() + u64::from(delay.subsec_nanos() > 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+
+    #[tokio::test]
+    async fn test_pump_buries_malformed() {
+        let server = FakeServer::start().await;
+        let parked = |at: &str| {
+            Envelope::new(b"renew".to_vec())
+                .header(TUBE_HEADER, "renewals")
+                .header(AT_HEADER, at)
+                .header(PRIORITY_HEADER, "0")
+                .header(TTR_HEADER, "60")
+                .encode()
+        };
+        let malformed = server.put(DEFAULT_SCHEDULED_TUBE, &parked("tomorrow"));
+        let due = server.put(DEFAULT_SCHEDULED_TUBE, &parked("0"));
+
+        let conn = server.client().connect().await.unwrap();
+        let mut pump = LongDelay::new(conn);
+        pump.run_pump_until(tokio::time::sleep(Duration::from_millis(500)))
+            .await
+            .unwrap();
+
+        assert_eq!(vec![malformed], server.jobs_in("buried"));
+        assert_eq!(None, server.state(due));
+        assert_eq!(1, server.jobs_in("ready").len());
+    }
+
+    #[test]
+    fn test_round_up() {
+        assert_eq!(Duration::from_secs(0), round_up(Duration::from_secs(0)));
+        assert_eq!(
+            Duration::from_secs(2),
+            round_up(Duration::from_millis(1001))
+        );
+        assert_eq!(Duration::from_secs(3), round_up(Duration::from_secs(3)));
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::stream::{self, BoxStream};
+use futures::{Stream, StreamExt};
+use tokio::time::{interval, Interval};
+
+use crate::error::BeanstalkcResult;
+use crate::response::numeric;
+use crate::Beanstalkc;
+
+/// `TubeStatsDelta` is one poll of the stats of a tube, along with how they changed since
+/// the previous poll.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct TubeStatsDelta {
+    /// Tube name.
+    pub tube: String,
+    /// Numeric stats of the tube, e.g. `current-jobs-ready`.
+    pub stats: HashMap<String, u64>,
+    /// Stats which changed since the previous poll, with the difference. Empty on the
+    /// first poll.
+    pub changes: HashMap<String, i64>,
+}
+
+impl TubeStatsDelta {
+    fn new(tube: &str, stats: HashMap<String, u64>, previous: Option<&TubeStatsDelta>) -> Self {
+        let changes = match previous {
+            Some(previous) => stats
+                .iter()
+                .filter_map(|(stat, value)| {
+                    let before = previous.get(stat).unwrap_or(0);
+                    let change = *value as i64 - before as i64;
+                    if change == 0 {
+                        None
+                    } else {
+                        Some((stat.clone(), change))
+                    }
+                })
+                .collect(),
+            None => HashMap::new(),
+        };
+        TubeStatsDelta {
+            tube: tube.to_string(),
+            stats,
+            changes,
+        }
+    }
+
+    /// Return the value of `stat`.
+    pub fn get(&self, stat: &str) -> Option<u64> {
+        self.stats.get(stat).copied()
+    }
+
+    /// Return how much `stat` changed since the previous poll.
+    pub fn change(&self, stat: &str) -> i64 {
+        self.changes.get(stat).copied().unwrap_or(0)
+    }
+}
+
+type AlertCallback = Arc<dyn Fn(&TubeStatsDelta) + Send + Sync>;
+
+/// Callback fired when a stat goes above a limit.
+struct Alert {
+    stat: String,
+    limit: u64,
+    callback: AlertCallback,
+    firing: bool,
+}
+
+impl Alert {
+    /// Fire the callback if the stat just went above the limit.
+    fn check(&mut self, delta: &TubeStatsDelta) {
+        let above = delta
+            .get(&self.stat)
+            .is_some_and(|value| value > self.limit);
+        if above && !self.firing {
+            (self.callback)(delta);
+        }
+        self.firing = above;
+    }
+}
+
+impl fmt::Debug for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Alert")
+            .field("stat", &self.stat)
+            .field("limit", &self.limit)
+            .field("firing", &self.firing)
+            .finish()
+    }
+}
+
+/// Polling state, moved into the stream on the first poll.
+#[derive(Debug)]
+struct Watch {
+    conn: Beanstalkc,
+    tube: String,
+    ticks: Interval,
+    previous: Option<TubeStatsDelta>,
+    alerts: Vec<Alert>,
+}
+
+impl Watch {
+    async fn poll(&mut self) -> BeanstalkcResult<TubeStatsDelta> {
+        self.ticks.tick().await;
+        let stats = numeric(self.conn.stats_tube(&self.tube).await?);
+        let delta = TubeStatsDelta::new(&self.tube, stats, self.previous.as_ref());
+        for alert in self.alerts.iter_mut() {
+            alert.check(&delta);
+        }
+        self.previous = Some(delta.clone());
+        Ok(delta)
+    }
+}
+
+/// `TubeStatsWatcher` is a stream polling the stats of a tube, created by
+/// [`Beanstalkc::watch_tube_stats`].
+///
+/// Each poll yields a [`TubeStatsDelta`], or the error of the poll, after which polling
+/// goes on. Alerts registered before the stream is first polled fire whenever a stat goes
+/// above its limit, and again only once it went back below.
+pub struct TubeStatsWatcher {
+    watch: Option<Watch>,
+    stream: Option<BoxStream<'static, BeanstalkcResult<TubeStatsDelta>>>,
+}
+
+impl TubeStatsWatcher {
+    /// Call `callback` when `stat` goes above `limit`, e.g. `current-jobs-buried` above 0.
+    pub fn alert_above<F>(mut self, stat: &str, limit: u64, callback: F) -> Self
+    where
+        F: Fn(&TubeStatsDelta) + Send + Sync + 'static,
+    {
+        if let Some(watch) = self.watch.as_mut() {
+            watch.alerts.push(Alert {
+                stat: stat.to_string(),
+                limit,
+                callback: Arc::new(callback),
+                firing: false,
+            });
+        }
+        self
+    }
+}
+
+impl Stream for TubeStatsWatcher {
+    type Item = BeanstalkcResult<TubeStatsDelta>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(watch) = self.watch.take() {
+            let polls = stream::unfold(watch, |mut watch| async move {
+                let delta = watch.poll().await;
+                Some((delta, watch))
+            });
+            self.stream = Some(polls.boxed());
+        }
+        match self.stream.as_mut() {
+            Some(stream) => stream.poll_next_unpin(cx),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+impl fmt::Debug for TubeStatsWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TubeStatsWatcher")
+            .field("watch", &self.watch)
+            .finish()
+    }
+}
+
+impl Beanstalkc {
+    /// Turn the connection into a stream of the stats of `tube`, polled every `every`
+    /// starting immediately.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::Beanstalkc;
+    /// use futures::StreamExt;
+    ///
+    /// let conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut stats = conn
+    ///     .watch_tube_stats("jobs", Duration::from_secs(5))
+    ///     .alert_above("current-jobs-buried", 0, |delta| eprintln!("{} has buried jobs", delta.tube))
+    ///     .alert_above("current-jobs-ready", 10_000, |_| eprintln!("jobs backlog"));
+    /// while let Some(delta) = stats.next().await {
+    ///     dbg!(delta.unwrap().changes);
+    /// }
+    /// }
+    /// ```
+    pub fn watch_tube_stats(self, tube: &str, every: Duration) -> TubeStatsWatcher {
+        assert!(!every.is_zero(), "stats interval must be positive");
+        TubeStatsWatcher {
+            watch: Some(Watch {
+                conn: self,
+                tube: tube.to_string(),
+                ticks: interval(every),
+                previous: None,
+                alerts: vec![],
+            }),
+            stream: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn delta(ready: u64, previous: Option<&TubeStatsDelta>) -> TubeStatsDelta {
+        let stats = vec![
+            ("current-jobs-ready".to_string(), ready),
+            ("current-jobs-buried".to_string(), 0),
+        ];
+        TubeStatsDelta::new("jobs", stats.into_iter().collect(), previous)
+    }
+
+    #[test]
+    fn test_delta() {
+        let first = delta(5, None);
+        assert!(first.changes.is_empty());
+
+        let second = delta(2, Some(&first));
+        assert_eq!(-3, second.change("current-jobs-ready"));
+        assert_eq!(0, second.change("current-jobs-buried"));
+        assert_eq!(1, second.changes.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "stats interval must be positive")]
+    fn test_zero_interval() {
+        Beanstalkc::new().watch_tube_stats("jobs", Duration::from_secs(0));
+    }
+
+    #[test]
+    fn test_alert() {
+        let fired = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&fired);
+        let mut alert = Alert {
+            stat: "current-jobs-ready".to_string(),
+            limit: 10,
+            callback: Arc::new(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }),
+            firing: false,
+        };
+        for ready in [5, 11, 20, 3, 12] {
+            alert.check(&delta(ready, None));
+        }
+        assert_eq!(2, fired.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::runtime::Handle;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::error::BeanstalkcResult;
+use crate::Beanstalkc;
+
+/// Dedicated connections reserving jobs for a [`SharedClient`](crate::SharedClient).
+#[derive(Debug)]
+pub(crate) struct ReservePool {
+    idle: Mutex<Vec<Beanstalkc>>,
+    permits: Arc<Semaphore>,
+}
+
+impl ReservePool {
+    pub(crate) fn new(conns: Vec<Beanstalkc>) -> Arc<Self> {
+        Arc::new(ReservePool {
+            permits: Arc::new(Semaphore::new(conns.len())),
+            idle: Mutex::new(conns),
+        })
+    }
+
+    /// Wait for an idle connection, connecting it first if needed.
+    pub(crate) async fn checkout(self: &Arc<Self>) -> BeanstalkcResult<Lease> {
+        let permit = Arc::clone(&self.permits)
+            .acquire_owned()
+            .await
+            .expect("reserve pool semaphore is never closed");
+        let conn = self.idle.lock().unwrap().pop();
+        let mut lease = Lease {
+            pool: Arc::clone(self),
+            conn,
+            _permit: permit,
+        };
+        if !lease.conn().is_connected() {
+            lease.conn().open().await?;
+        }
+        Ok(lease)
+    }
+}
+
+/// A connection checked out of a [`ReservePool`], returned to it on drop.
+#[derive(Debug)]
+pub(crate) struct Lease {
+    pool: Arc<ReservePool>,
+    conn: Option<Beanstalkc>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Lease {
+    pub(crate) fn conn(&mut self) -> &mut Beanstalkc {
+        self.conn
+            .as_mut()
+            .expect("a permit always comes with a connection")
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.idle.lock().unwrap().push(conn);
+        }
+    }
+}
+
+/// `PooledJob` is a job reserved by [`SharedClient::reserve`](crate::SharedClient::reserve).
+///
+/// The job keeps the reserve connection which reserved it until it is dropped, since only
+/// that connection can finalize it. A job dropped before being finalized is released in
+/// the background, or before the next reserve on its connection outside of a runtime.
+#[derive(Debug)]
+pub struct PooledJob {
+    lease: Option<Lease>,
+    id: u64,
+    body: Vec<u8>,
+    finalized: bool,
+}
+
+impl PooledJob {
+    pub(crate) fn new(lease: Lease, id: u64, body: Vec<u8>) -> Self {
+        PooledJob {
+            lease: Some(lease),
+            id,
+            body,
+            finalized: false,
+        }
+    }
+
+    fn conn(&mut self) -> &mut Beanstalkc {
+        self.lease
+            .as_mut()
+            .expect("the lease is only taken on drop")
+            .conn()
+    }
+
+    /// Return job id.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Return job body.
+    pub fn body(&self) -> &[u8] {
+        &self.body[..]
+    }
+
+    /// Delete this job.
+    pub async fn delete(mut self) -> BeanstalkcResult<()> {
+        let id = self.id;
+        self.conn().delete(id).await?;
+        self.finalized = true;
+        Ok(())
+    }
+
+    /// Release this job back to the ready queue with custom priority and delay.
+    pub async fn release(mut self, priority: u32, delay: Duration) -> BeanstalkcResult<()> {
+        let id = self.id;
+        self.conn().release(id, priority, delay).await?;
+        self.finalized = true;
+        Ok(())
+    }
+
+    /// Bury this job with custom priority.
+    pub async fn bury(mut self, priority: u32) -> BeanstalkcResult<()> {
+        let id = self.id;
+        self.conn().bury(id, priority).await?;
+        self.finalized = true;
+        Ok(())
+    }
+
+    /// Touch this job, requesting more time to work on it.
+    pub async fn touch(&mut self) -> BeanstalkcResult<()> {
+        let id = self.id;
+        self.conn().touch(id).await
+    }
+}
+
+impl Drop for PooledJob {
+    fn drop(&mut self) {
+        let mut lease = match self.lease.take() {
+            Some(lease) if !self.finalized => lease,
+            _ => return,
+        };
+        lease.conn().abandon_job(self.id);
+        if let Ok(runtime) = Handle::try_current() {
+            runtime.spawn(async move { lease.conn().release_abandoned().await });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fake::FakeServer;
+    use crate::SharedClient;
+
+    #[tokio::test]
+    async fn test_dropped_job_releases() {
+        let server = FakeServer::start().await;
+        let id = server.put("default", b"work");
+        let conn = server
+            .client()
+            .reserve_connections(1)
+            .connect()
+            .await
+            .unwrap();
+        let client = SharedClient::new(conn);
+
+        let job = client.reserve().await.unwrap();
+        assert_eq!(id, job.id());
+        drop(job);
+
+        let job = client.reserve().await.unwrap();
+        assert_eq!(id, job.id());
+        let commands = server.commands();
+        let release = commands
+            .iter()
+            .position(|cmd| cmd.starts_with(&format!("release {} ", id)))
+            .unwrap();
+        let reserve = commands.iter().rposition(|cmd| cmd == "reserve");
+        assert!(Some(release) < reserve, "{:?}", commands);
+    }
+}
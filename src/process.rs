@@ -0,0 +1,153 @@
+use std::any::Any;
+use std::time::{Duration, SystemTime};
+
+use crate::envelope::Envelope;
+use crate::error::BeanstalkcResult;
+
+/// `Outcome` tells [`Job::run`](crate::Job::run) how to finalize a job once its handler
+/// returns successfully. A handler error is treated like [`Job::fail`](crate::Job::fail).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Delete the job.
+    Delete,
+    /// Release the job back into the ready queue with its current priority.
+    Release { delay: Duration },
+    /// Bury the job with its current priority.
+    Bury,
+}
+
+/// `PanicAction` decides how a job is finalized when its handler panics.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PanicAction {
+    /// Treat the panic like a handler error, see [`Job::fail`](crate::Job::fail).
+    #[default]
+    Fail,
+    /// Release the job with its current priority and default delay.
+    Release,
+    /// Bury the job with its current priority.
+    Bury,
+}
+
+/// `TimeoutAction` decides how a job is finalized when its handler runs out of time, see
+/// [`Worker::ttr_timeout`](crate::Worker::ttr_timeout).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TimeoutAction {
+    /// Release the job with its current priority and default delay.
+    #[default]
+    Release,
+    /// Bury the job with its current priority.
+    Bury,
+}
+
+/// `OversizeAction` decides how a reserved job is finalized when its body is larger than
+/// the client accepts, see [`Beanstalkc::max_body_size`](crate::Beanstalkc::max_body_size).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OversizeAction {
+    /// Release the job with its current priority and default delay, for a worker accepting
+    /// larger bodies to take it.
+    Release,
+    /// Bury the job with its current priority.
+    #[default]
+    Bury,
+}
+
+impl TimeoutAction {
+    /// Return the outcome finalizing a job timed out with this action, releasing it with
+    /// the `delay` configured on the client.
+    pub(crate) fn outcome(self, delay: Duration) -> Outcome {
+        match self {
+            TimeoutAction::Release => Outcome::Release { delay },
+            TimeoutAction::Bury => Outcome::Bury,
+        }
+    }
+}
+
+/// Render a panic payload as a message.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// `ReservedJob` is an owned snapshot of a reserved job handed to job handlers.
+#[derive(Debug, Clone)]
+pub struct ReservedJob {
+    id: u64,
+    body: Vec<u8>,
+    created_at: SystemTime,
+}
+
+impl ReservedJob {
+    pub(crate) fn new(id: u64, body: Vec<u8>) -> Self {
+        ReservedJob {
+            id,
+            body,
+            created_at: SystemTime::now(),
+        }
+    }
+
+    /// Return job id.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Return job body.
+    pub fn body(&self) -> &[u8] {
+        &self.body[..]
+    }
+
+    /// Decode the [`Envelope`] wrapping the job body, see [`Job::envelope`](crate::Job::envelope).
+    pub fn envelope(&self) -> BeanstalkcResult<Envelope> {
+        Envelope::decode(&self.body)
+    }
+
+    /// Return the headers of the [`Envelope`] wrapping the job body, in insertion order.
+    pub fn headers(&self) -> BeanstalkcResult<Vec<(String, String)>> {
+        let envelope = self.envelope()?;
+        Ok(envelope
+            .headers()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect())
+    }
+
+    /// Return when the job was put, from the `enqueued-at` header of its [`Envelope`].
+    pub fn enqueued_at(&self) -> Option<SystemTime> {
+        if !Envelope::is_envelope(&self.body) {
+            return None;
+        }
+        self.envelope().ok()?.enqueued_at()
+    }
+
+    /// Return how long the job waited between being put and being reserved, when it was put
+    /// with an `enqueued-at` header.
+    pub fn queue_latency(&self) -> Option<Duration> {
+        self.created_at.duration_since(self.enqueued_at()?).ok()
+    }
+
+    /// Consume the snapshot and return the job body.
+    pub fn into_body(self) -> Vec<u8> {
+        self.body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic;
+
+    #[test]
+    fn test_panic_message() {
+        let payload = panic::catch_unwind(|| panic!("boom")).unwrap_err();
+        assert_eq!("boom", panic_message(payload.as_ref()));
+
+        let payload = panic::catch_unwind(|| panic!("job {}", 1)).unwrap_err();
+        assert_eq!("job 1", panic_message(payload.as_ref()));
+
+        let payload = panic::catch_unwind(|| panic::panic_any(1)).unwrap_err();
+        assert_eq!("Box<dyn Any>", panic_message(payload.as_ref()));
+    }
+}
@@ -0,0 +1,208 @@
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::ready;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
+
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::put::PutRequest;
+use crate::Beanstalkc;
+
+enum Message {
+    Put(PutRequest, oneshot::Sender<BeanstalkcResult<u64>>),
+    Flush(oneshot::Sender<()>),
+}
+
+/// `BatchedProducer` buffers puts and sends them as pipelined batches.
+///
+/// A batch is written at once, then the ids of its jobs are read back, once `max_batch`
+/// requests are buffered or `flush_interval` has elapsed since the first of them was
+/// buffered. This turns many small round trips into a few writes. Each put returns a
+/// [`PendingPut`] resolving to the id of its job, or to its own error, once its batch has
+/// been sent: a failed put does not affect the other puts, nor the following batches.
+///
+/// Puts go through a background task owning a dedicated connection.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use std::time::Duration;
+/// use beanstalkc::{BatchedProducer, Beanstalkc, PutRequest};
+///
+/// let mut conn = Beanstalkc::new().connect().await.unwrap();
+/// conn.use_tube("jobs").await.unwrap();
+///
+/// let producer = BatchedProducer::new(conn, 100, Duration::from_millis(50));
+/// let mut pending = vec![];
+/// for i in 0..10_000 {
+///     pending.push(producer.put(PutRequest::new(format!("{}", i).into_bytes())).await.unwrap());
+/// }
+/// producer.close().await.unwrap();
+/// for put in pending {
+///     if let Err(err) = put.await {
+///         eprintln!("put failed: {}", err);
+///     }
+/// }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BatchedProducer {
+    sender: mpsc::Sender<Message>,
+    task: JoinHandle<()>,
+}
+
+/// `PendingPut` resolves to the id of a job put by a [`BatchedProducer`] once its batch has
+/// been sent. It can be dropped if the id is not needed.
+#[derive(Debug)]
+pub struct PendingPut {
+    receiver: oneshot::Receiver<BeanstalkcResult<u64>>,
+}
+
+impl Future for PendingPut {
+    type Output = BeanstalkcResult<u64>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let put = ready!(Pin::new(&mut self.receiver).poll(cx));
+        Poll::Ready(put.unwrap_or_else(|_| Err(closed())))
+    }
+}
+
+impl BatchedProducer {
+    /// Spawn the task putting batches through `conn`, into the tube it uses. Must be
+    /// called from within a tokio runtime.
+    pub fn new(conn: Beanstalkc, max_batch: usize, flush_interval: Duration) -> Self {
+        let max_batch = max_batch.max(1);
+        let (sender, receiver) = mpsc::channel(max_batch);
+        let task = tokio::spawn(run(conn, receiver, max_batch, flush_interval));
+        BatchedProducer { sender, task }
+    }
+
+    /// Buffer a put, waiting while the buffer of the background task is full. Return the
+    /// pending result of the put.
+    pub async fn put(&self, request: PutRequest) -> BeanstalkcResult<PendingPut> {
+        let (sender, receiver) = oneshot::channel();
+        self.sender
+            .send(Message::Put(request, sender))
+            .await
+            .map_err(|_| closed())?;
+        Ok(PendingPut { receiver })
+    }
+
+    /// Put every buffered request now.
+    pub async fn flush(&self) -> BeanstalkcResult<()> {
+        let (done, flushed) = oneshot::channel();
+        self.sender
+            .send(Message::Flush(done))
+            .await
+            .map_err(|_| closed())?;
+        flushed.await.map_err(|_| closed())
+    }
+
+    /// Put every buffered request, then close the connection.
+    pub async fn close(self) -> BeanstalkcResult<()> {
+        drop(self.sender);
+        self.task
+            .await
+            .map_err(|err| BeanstalkcError::ConnectionError(err.to_string()))
+    }
+}
+
+fn closed() -> BeanstalkcError {
+    BeanstalkcError::ConnectionError("batched producer is closed".to_string())
+}
+
+/// Collect requests into batches and put them until the producer is dropped.
+async fn run(
+    mut conn: Beanstalkc,
+    mut receiver: mpsc::Receiver<Message>,
+    max_batch: usize,
+    flush_interval: Duration,
+) {
+    let mut batch = Vec::with_capacity(max_batch);
+    let mut deadline = None;
+    loop {
+        let interval = time::sleep_until(deadline.unwrap_or_else(Instant::now));
+        let done = tokio::select! {
+            message = receiver.recv() => match message {
+                Some(Message::Put(request, pending)) => {
+                    if batch.is_empty() {
+                        deadline = Some(Instant::now() + flush_interval);
+                    }
+                    batch.push((request, pending));
+                    if batch.len() < max_batch {
+                        continue;
+                    }
+                    None
+                }
+                Some(Message::Flush(done)) => Some(done),
+                None => break,
+            },
+            _ = interval, if deadline.is_some() => None,
+        };
+
+        deadline = None;
+        put_batch(&mut conn, mem::take(&mut batch)).await;
+        if let Some(done) = done {
+            let _ = done.send(());
+        }
+    }
+
+    put_batch(&mut conn, batch).await;
+    conn.close().await;
+}
+
+/// Put a batch, sending the result of each put to its pending put. When the whole batch
+/// fails, e.g. on a connection error, every put gets that error.
+async fn put_batch(
+    conn: &mut Beanstalkc,
+    batch: Vec<(PutRequest, oneshot::Sender<BeanstalkcResult<u64>>)>,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let (requests, pending): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+    match conn.put_pipelined(&requests).await {
+        Ok(ids) => {
+            for (pending, id) in pending.into_iter().zip(ids) {
+                let _ = pending.send(id);
+            }
+        }
+        Err(err) => {
+            for pending in pending {
+                let _ = pending.send(Err(err.clone()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+
+    #[tokio::test]
+    async fn test_put_results() {
+        let server = FakeServer::start().await;
+        server.reply("put 2147483648 0 120 2", "JOB_TOO_BIG");
+        let conn = server.client().connect().await.unwrap();
+        let producer = BatchedProducer::new(conn, 10, Duration::from_secs(60));
+
+        let first = producer.put(PutRequest::new(b"a".to_vec())).await.unwrap();
+        let failed = producer.put(PutRequest::new(b"bb".to_vec())).await.unwrap();
+        producer.flush().await.unwrap();
+        let last = producer.put(PutRequest::new(b"c".to_vec())).await.unwrap();
+        producer.close().await.unwrap();
+
+        assert!(first.await.is_ok());
+        assert!(failed.await.is_err());
+        assert!(last.await.is_ok());
+        assert_eq!(2, server.jobs_in("ready").len());
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::command::Status;
+use crate::config::{DEFAULT_WORKER_DRAIN_TIMEOUT, DEFAULT_WORKER_POLL_INTERVAL};
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::job::Job;
+use crate::logging;
+use crate::process::{Outcome, ReservedJob};
+use crate::Beanstalkc;
+
+/// `Completion` reports how a job forwarded by [`Beanstalkc::pump_into`] was handled, for
+/// the pump to finalize it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    id: u64,
+    outcome: Option<Outcome>,
+}
+
+impl Completion {
+    /// Finalize job `id` with `outcome`, like a successful handler of
+    /// [`Job::run`](crate::Job::run).
+    pub fn done(id: u64, outcome: Outcome) -> Self {
+        Completion {
+            id,
+            outcome: Some(outcome),
+        }
+    }
+
+    /// Mark job `id` as failed, see [`Job::fail`](crate::Job::fail).
+    pub fn failed(id: u64) -> Self {
+        Completion { id, outcome: None }
+    }
+}
+
+/// `PumpOptions` configures [`Beanstalkc::pump_into`].
+#[derive(Debug)]
+pub struct PumpOptions {
+    completions: mpsc::Receiver<Completion>,
+    poll_interval: Duration,
+    drain_timeout: Duration,
+}
+
+impl PumpOptions {
+    /// Finalize the jobs forwarded by the pump as their [`Completion`] is received from
+    /// `completions`.
+    pub fn new(completions: mpsc::Receiver<Completion>) -> Self {
+        PumpOptions {
+            completions,
+            poll_interval: DEFAULT_WORKER_POLL_INTERVAL,
+            drain_timeout: DEFAULT_WORKER_DRAIN_TIMEOUT,
+        }
+    }
+
+    /// Change how long a reserve waits for a job, during which completions are not
+    /// applied, and how long the pump waits for completions before reserving again once a
+    /// job in flight is about to reach its TTR. Default poll interval is `1s`.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Change how long the pump waits for the completion of the jobs in flight once the
+    /// job channel is closed, before releasing them. Default drain timeout is `30s`.
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+}
+
+impl Beanstalkc {
+    /// Reserve jobs from the watched tubes and forward them into `sender`, for pipelines
+    /// built on channels. Reserves pause while the channel is full, so a bounded channel
+    /// caps the number of jobs waiting to be processed. The pipeline sends a
+    /// [`Completion`] for each job on the completion channel of `opts` to have it deleted,
+    /// released, buried or failed.
+    ///
+    /// The pump runs until the receiver of `sender` is dropped. It then keeps applying
+    /// completions until every job in flight is finalized, the completion channel is
+    /// closed, or the drain timeout elapses, releases the jobs left and returns. Jobs left
+    /// without completion while the pump runs stay reserved until their TTR expires.
+    ///
+    /// A job which cannot be finalized from its completion is logged and skipped without
+    /// stopping the pump. When the pump fails, e.g. on a connection error, the jobs in
+    /// flight are released before the error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, Completion, Outcome, PumpOptions, ReservedJob};
+    /// use tokio::sync::mpsc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let (jobs, mut received) = mpsc::channel::<ReservedJob>(16);
+    /// let (done, completions) = mpsc::channel(16);
+    /// tokio::spawn(async move {
+    ///     while let Some(job) = received.recv().await {
+    ///         // Process the job...
+    ///         done.send(Completion::done(job.id(), Outcome::Delete)).await.unwrap();
+    ///     }
+    /// });
+    ///
+    /// conn.pump_into(jobs, PumpOptions::new(completions)).await.unwrap();
+    /// }
+    /// ```
+    pub async fn pump_into(
+        &mut self,
+        sender: mpsc::Sender<ReservedJob>,
+        mut opts: PumpOptions,
+    ) -> BeanstalkcResult<()> {
+        let mut in_flight = HashMap::new();
+        let pumped = self.pump(sender, &mut opts, &mut in_flight).await;
+        let released = self.release_pumped(in_flight).await;
+        pumped.and(released)
+    }
+
+    /// Run the pump of [`Beanstalkc::pump_into`], leaving the jobs not finalized in
+    /// `in_flight` for the caller to release, whichever way the pump ends.
+    async fn pump(
+        &mut self,
+        sender: mpsc::Sender<ReservedJob>,
+        opts: &mut PumpOptions,
+        in_flight: &mut HashMap<u64, Vec<u8>>,
+    ) -> BeanstalkcResult<()> {
+        let mut completing = true;
+
+        loop {
+            while let Ok(completion) = opts.completions.try_recv() {
+                self.complete_pumped(in_flight, completion).await?;
+            }
+
+            let permit = tokio::select! {
+                permit = sender.reserve() => match permit {
+                    Ok(permit) => permit,
+                    Err(_) => break,
+                },
+                completion = opts.completions.recv(), if completing => {
+                    match completion {
+                        Some(completion) => self.complete_pumped(in_flight, completion).await?,
+                        None => completing = false,
+                    }
+                    continue;
+                }
+            };
+
+            match self.reserve_status(Some(opts.poll_interval)).await {
+                Ok(Ok((id, body))) => {
+                    in_flight.insert(id, body.clone());
+                    permit.send(ReservedJob::new(id, body));
+                }
+                Ok(Err(Status::DeadlineSoon)) if completing => {
+                    // Give the pipeline a chance to complete jobs before reserving again.
+                    let wait = tokio::time::timeout(opts.poll_interval, opts.completions.recv());
+                    match wait.await {
+                        Ok(Some(completion)) => self.complete_pumped(in_flight, completion).await?,
+                        Ok(None) => completing = false,
+                        Err(_) => {}
+                    }
+                }
+                Ok(Err(Status::DeadlineSoon)) => tokio::time::sleep(opts.poll_interval).await,
+                Ok(Err(_)) => {}
+                Err(err) if err.is_job_error() => logging::job_skipped(&err),
+                Err(err) => return Err(err),
+            }
+        }
+
+        let deadline = Instant::now() + opts.drain_timeout;
+        while completing && !in_flight.is_empty() {
+            match tokio::time::timeout_at(deadline, opts.completions.recv()).await {
+                Ok(Some(completion)) => self.complete_pumped(in_flight, completion).await?,
+                _ => completing = false,
+            }
+        }
+        Ok(())
+    }
+
+    /// Release the jobs forwarded by [`Beanstalkc::pump_into`] and left without completion.
+    /// Once the connection is lost, the server releases them itself.
+    async fn release_pumped(&mut self, in_flight: HashMap<u64, Vec<u8>>) -> BeanstalkcResult<()> {
+        if !self.is_connected() {
+            return Ok(());
+        }
+        for (id, body) in in_flight {
+            match Job::new(self, id, body, true).release_default().await {
+                Ok(()) | Err(BeanstalkcError::CommandFailed(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Finalize a job forwarded by [`Beanstalkc::pump_into`] from its completion.
+    async fn complete_pumped(
+        &mut self,
+        in_flight: &mut HashMap<u64, Vec<u8>>,
+        completion: Completion,
+    ) -> BeanstalkcResult<()> {
+        let body = match in_flight.remove(&completion.id) {
+            Some(body) => body,
+            None => return Ok(()),
+        };
+
+        let mut job = Job::new(self, completion.id, body, true);
+        let finalized = match completion.outcome {
+            Some(outcome) => job.finalize(&Ok::<_, ()>(outcome)).await,
+            None => job.fail().await,
+        };
+        match finalized {
+            // The job may have expired and been handed to another worker meanwhile.
+            Err(BeanstalkcError::CommandFailed(_)) => Ok(()),
+            Err(err) if !err.is_connection_error() => {
+                logging::job_failed(completion.id, &err);
+                Ok(())
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+
+    #[tokio::test]
+    async fn test_pump_into() {
+        let server = FakeServer::start().await;
+        let first = server.put("default", b"first");
+        let second = server.put("default", b"second");
+        let mut conn = server.client().connect().await.unwrap();
+
+        let (jobs, mut received) = mpsc::channel::<ReservedJob>(1);
+        let (done, completions) = mpsc::channel(1);
+        tokio::spawn(async move {
+            let job = received.recv().await.unwrap();
+            done.send(Completion::done(job.id(), Outcome::Delete))
+                .await
+                .unwrap();
+            received.recv().await.unwrap();
+        });
+
+        conn.pump_into(jobs, PumpOptions::new(completions))
+            .await
+            .unwrap();
+        assert_eq!(None, server.state(first));
+        assert_eq!(Some("ready"), server.state(second));
+    }
+
+    #[tokio::test]
+    async fn test_pump_waits_on_deadline_soon() {
+        let server = FakeServer::start().await;
+        server.reply("reserve-with-timeout 1", "DEADLINE_SOON");
+        let mut conn = server.client().connect().await.unwrap();
+
+        let (jobs, received) = mpsc::channel::<ReservedJob>(1);
+        let (_done, completions) = mpsc::channel(1);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            drop(received);
+        });
+
+        conn.pump_into(jobs, PumpOptions::new(completions))
+            .await
+            .unwrap();
+        let reserves = server
+            .commands()
+            .iter()
+            .filter(|line| line.starts_with("reserve"))
+            .count();
+        assert_eq!(1, reserves);
+    }
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+/// `PutRequest` describes a job to put, for producers which put jobs on behalf of the
+/// caller such as [`PutSink`](crate::PutSink).
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use beanstalkc::PutRequest;
+///
+/// let request = PutRequest::new(b"Rust".to_vec())
+///     .priority(0)
+///     .delay(Duration::from_secs(10));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PutRequest {
+    pub(crate) body: Vec<u8>,
+    pub(crate) priority: Option<u32>,
+    pub(crate) delay: Option<Duration>,
+    pub(crate) ttr: Option<Duration>,
+}
+
+impl PutRequest {
+    /// Create a request putting `body` with the default priority, delay and TTR configured
+    /// on the client putting it, see [`Beanstalkc::default_priority`](crate::Beanstalkc::default_priority).
+    pub fn new(body: Vec<u8>) -> Self {
+        PutRequest {
+            body,
+            priority: None,
+            delay: None,
+            ttr: None,
+        }
+    }
+
+    /// Change job priority.
+    pub fn priority(mut self, priority: u32) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Change job delay.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Change job TTR.
+    pub fn ttr(mut self, ttr: Duration) -> Self {
+        self.ttr = Some(ttr);
+        self
+    }
+
+    /// Return job body.
+    pub fn body(&self) -> &[u8] {
+        &self.body[..]
+    }
+}
+
+impl From<Vec<u8>> for PutRequest {
+    fn from(body: Vec<u8>) -> Self {
+        PutRequest::new(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_request() {
+        let request = PutRequest::from(b"Rust".to_vec());
+        assert_eq!(b"Rust", request.body());
+        assert_eq!(None, request.priority);
+        assert_eq!(None, request.ttr);
+
+        let request = request.priority(1).ttr(Duration::from_secs(5));
+        assert_eq!(Some(1), request.priority);
+        assert_eq!(Some(Duration::from_secs(5)), request.ttr);
+        assert_eq!(None, request.delay);
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::config::MAX_RATE_LIMIT_WAIT;
+
+/// `RateLimit` is a token bucket limiting how fast jobs are put into a tube.
+///
+/// Up to `burst` jobs can be put at once, then puts are spaced to `per_second` on
+/// average. The limit is enforced by the client before writing, by waiting.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use beanstalkc::{Beanstalkc, RateLimit};
+///
+/// let mut conn = Beanstalkc::new()
+///        .put_rate_limit(RateLimit::new(500.0, 50))
+///        .tube_put_rate_limit("backfill", RateLimit::new(20.0, 1))
+///        .connect()
+///        .await
+///        .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    per_second: f64,
+    burst: u32,
+}
+
+impl RateLimit {
+    /// Allow `per_second` puts per second on average, and up to `burst` puts at once.
+    /// A put never waits more than a day for its token, however low the rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `per_second` is not a positive finite number.
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        assert!(
+            per_second.is_finite() && per_second > 0.0,
+            "rate limit must be positive, got {}",
+            per_second
+        );
+        RateLimit {
+            per_second,
+            burst: burst.max(1),
+        }
+    }
+}
+
+/// Tracks the tokens available to a single tube.
+#[derive(Debug)]
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        TokenBucket {
+            limit,
+            tokens: f64::from(limit.burst),
+            updated: now,
+        }
+    }
+
+    /// Take a token and return how long to wait until it is actually available.
+    fn acquire(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * self.limit.per_second).min(f64::from(self.limit.burst));
+        self.updated = now;
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::try_from_secs_f64(-self.tokens / self.limit.per_second)
+                .unwrap_or(MAX_RATE_LIMIT_WAIT)
+                .min(MAX_RATE_LIMIT_WAIT)
+        }
+    }
+}
+
+/// Rate limits applied to puts, per tube.
+#[derive(Debug, Default)]
+pub(crate) struct PutLimiter {
+    default: Option<RateLimit>,
+    tubes: HashMap<String, RateLimit>,
+    buckets: HashMap<String, TokenBucket>,
+}
+
+impl PutLimiter {
+    /// Limit every tube without a limit of its own.
+    pub(crate) fn set_default(&mut self, limit: RateLimit) {
+        self.default = Some(limit);
+        self.buckets.clear();
+    }
+
+    /// Limit puts into `tube`.
+    pub(crate) fn set_tube(&mut self, tube: &str, limit: RateLimit) {
+        self.tubes.insert(tube.to_string(), limit);
+        self.buckets.remove(tube);
+    }
+
+    /// Take `count` tokens for `tube` and return how long to wait before putting.
+    pub(crate) fn acquire(&mut self, tube: &str, count: usize) -> Duration {
+        let limit = match self.tubes.get(tube).or(self.default.as_ref()) {
+            Some(limit) => *limit,
+            None => return Duration::from_secs(0),
+        };
+        let now = Instant::now();
+        let bucket = self
+            .buckets
+            .entry(tube.to_string())
+            .or_insert_with(|| TokenBucket::new(limit, now));
+        (0..count)
+            .map(|_| bucket.acquire(now))
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(RateLimit::new(10.0, 2), now);
+        assert_eq!(Duration::from_secs(0), bucket.acquire(now));
+        assert_eq!(Duration::from_secs(0), bucket.acquire(now));
+        assert_eq!(Duration::from_millis(100), bucket.acquire(now));
+        assert_eq!(Duration::from_millis(200), bucket.acquire(now));
+
+        let later = now + Duration::from_secs(10);
+        assert_eq!(Duration::from_secs(0), bucket.acquire(later));
+    }
+
+    #[test]
+    fn test_token_bucket_tiny_rate() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(RateLimit::new(f64::MIN_POSITIVE, 1), now);
+        assert_eq!(Duration::from_secs(0), bucket.acquire(now));
+        assert_eq!(MAX_RATE_LIMIT_WAIT, bucket.acquire(now));
+    }
+
+    #[test]
+    #[should_panic(expected = "rate limit must be positive")]
+    fn test_rate_limit_nan() {
+        RateLimit::new(f64::NAN, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "rate limit must be positive")]
+    fn test_rate_limit_zero() {
+        RateLimit::new(0.0, 1);
+    }
+
+    #[test]
+    fn test_put_limiter() {
+        let mut limiter = PutLimiter::default();
+        assert_eq!(Duration::from_secs(0), limiter.acquire("default", 100));
+
+        limiter.set_tube("slow", RateLimit::new(1.0, 1));
+        assert_eq!(Duration::from_secs(0), limiter.acquire("default", 100));
+        assert!(limiter.acquire("slow", 3) > Duration::from_millis(1900));
+
+        limiter.set_default(RateLimit::new(100.0, 10));
+        assert_eq!(Duration::from_secs(0), limiter.acquire("default", 10));
+        assert!(limiter.acquire("default", 1) > Duration::from_secs(0));
+    }
+}
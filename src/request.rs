@@ -1,57 +1,270 @@
-use std::str::FromStr;
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{
+    self, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
 use tokio::net::TcpStream;
 
-use crate::command::Status;
+use crate::config::{DESYNC_DRAIN_TIMEOUT, MAX_RESPONSE_LINE};
 use crate::error::{BeanstalkcError, BeanstalkcResult};
-use crate::response::Response;
+use crate::response::{desync, parse_line, Response, ResponseParser};
+use crate::wire;
+
+/// Progress of the messages exchanged on a connection, to detect exchanges cut short when
+/// the future driving a request is dropped.
+#[derive(Debug, Default)]
+pub(crate) struct Exchange {
+    /// Responses owed by the server and not read yet.
+    pending: usize,
+    /// Whether a message was partially written or a response partially read.
+    torn: bool,
+}
+
+impl Exchange {
+    pub(crate) fn pending(&self) -> usize {
+        self.pending
+    }
+
+    pub(crate) fn is_torn(&self) -> bool {
+        self.torn
+    }
+}
 
 #[derive(Debug)]
 pub struct Request<'b> {
     stream: &'b mut BufReader<TcpStream>,
+    exchange: &'b mut Exchange,
+    debug: bool,
+    max_body: Option<usize>,
 }
 
 impl<'b> Request<'b> {
-    pub fn new(stream: &'b mut BufReader<TcpStream>) -> Self {
-        Request { stream }
+    pub fn new(stream: &'b mut BufReader<TcpStream>, exchange: &'b mut Exchange) -> Self {
+        Request {
+            stream,
+            exchange,
+            debug: false,
+            max_body: None,
+        }
+    }
+
+    /// Log the raw protocol lines exchanged by this request.
+    pub fn debug(mut self, enabled: bool) -> Self {
+        self.debug = enabled;
+        self
+    }
+
+    /// Skip job bodies larger than `max` bytes, failing with
+    /// `BeanstalkcError::BodyTooLarge` once they have been read, by [`Request::read`] as
+    /// well as [`Request::read_into`].
+    pub fn max_body(mut self, max: Option<usize>) -> Self {
+        self.max_body = max;
+        self
     }
 
     pub async fn send(&mut self, message: &[u8]) -> BeanstalkcResult<Response> {
-        let _ = self.stream.write(message).await?;
+        self.write(&[message]).await?;
+        self.read().await
+    }
+
+    /// Write several messages at once, without waiting for their responses.
+    pub async fn write(&mut self, messages: &[&[u8]]) -> BeanstalkcResult<()> {
+        self.check_sequence()?;
+        if self.debug {
+            for message in messages {
+                wire::outbound(message);
+            }
+        }
+        self.exchange.torn = true;
+        self.stream.write_all(&messages.concat()).await?;
         self.stream.flush().await?;
+        self.exchange.torn = false;
+        self.exchange.pending += messages.len();
+        Ok(())
+    }
 
-        let mut line = String::new();
-        self.stream.read_line(&mut line).await?;
+    /// Write `head`, then a body of `len` bytes copied from `reader` in chunks and the
+    /// trailing line break.
+    pub async fn write_from<R>(
+        &mut self,
+        head: &[u8],
+        reader: &mut R,
+        len: u64,
+    ) -> BeanstalkcResult<()>
+    where
+        R: AsyncRead + Unpin + ?Sized,
+    {
+        self.check_sequence()?;
+        if self.debug {
+            wire::outbound(head);
+        }
+        self.exchange.torn = true;
+        self.stream.write_all(head).await?;
+        let copied = io::copy(&mut reader.take(len), &mut *self.stream).await?;
+        if copied < len {
+            return Err(BeanstalkcError::ConnectionError(format!(
+                "body reader ended after {} of {} bytes",
+                copied, len
+            )));
+        }
+        self.stream.write_all(b"\r\n").await?;
+        self.stream.flush().await?;
+        self.exchange.torn = false;
+        self.exchange.pending += 1;
+        Ok(())
+    }
+
+    /// Read the response like [`Request::read`], but copy its body into `writer` in chunks
+    /// instead of buffering it.
+    pub async fn read_into<W>(&mut self, writer: &mut W) -> BeanstalkcResult<Response>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let (line, response, body_byte_count) = self.read_head().await?;
+        let body_byte_count = match body_byte_count {
+            Some(count) => count,
+            None => return Ok(response),
+        };
+
+        if self
+            .max_body
+            .is_some_and(|max| body_byte_count > max as u64)
+        {
+            let body = &mut (&mut *self.stream).take(body_byte_count);
+            let skipped = io::copy_buf(body, &mut io::sink()).await?;
+            if skipped < body_byte_count {
+                return Err(BeanstalkcError::ConnectionError(
+                    "connection closed by server".to_string(),
+                ));
+            }
+            self.read_line_break().await?;
+            self.answered();
+            if self.debug {
+                wire::inbound(&line, None);
+            }
+            return Err(BeanstalkcError::BodyTooLarge {
+                id: response.job_id()?,
+                size: body_byte_count,
+            });
+        }
 
-        if line.trim().is_empty() {
-            return Err(BeanstalkcError::UnexpectedResponse(
-                "empty response".to_string(),
+        let copied = io::copy_buf(&mut (&mut *self.stream).take(body_byte_count), writer).await?;
+        if copied < body_byte_count {
+            return Err(BeanstalkcError::ConnectionError(
+                "connection closed by server".to_string(),
             ));
         }
+        self.read_line_break().await?;
+        self.answered();
+        writer.flush().await?;
+        if self.debug {
+            wire::inbound(&line, None);
+        }
+        Ok(response)
+    }
 
-        let line_parts: Vec<_> = line.split_whitespace().collect();
+    /// Read the response to the oldest message written and not answered yet.
+    pub async fn read(&mut self) -> BeanstalkcResult<Response> {
+        let mut parser = ResponseParser::new().max_body(self.max_body);
+        loop {
+            let received = self.stream.fill_buf().await?;
+            if received.is_empty() {
+                return Err(BeanstalkcError::ConnectionError(
+                    "connection closed by server".to_string(),
+                ));
+            }
+            let (used, response) = parser.feed(received)?;
+            self.stream.consume(used);
+            self.exchange.torn = true;
+            if let Some(response) = response {
+                self.answered();
+                if self.debug {
+                    wire::inbound(parser.line(), response.body.as_deref());
+                }
+                if parser.skipped() {
+                    return Err(BeanstalkcError::BodyTooLarge {
+                        id: response.job_id()?,
+                        size: response.int_param(1)?,
+                    });
+                }
+                return Ok(response);
+            }
+        }
+    }
 
-        let mut response = Response {
-            status: Status::from_str(line_parts.first().unwrap_or(&""))?,
-            params: line_parts[1..].iter().map(|&x| x.to_string()).collect(),
-            ..Default::default()
-        };
+    /// Read a response line, no longer than [`ResponseParser`] accepts. Return it along with
+    /// the response, and the size of the body following it, if any.
+    async fn read_head(&mut self) -> BeanstalkcResult<(String, Response, Option<u64>)> {
+        let mut line = String::new();
+        self.exchange.torn = true;
+        let limit = MAX_RESPONSE_LINE as u64 + 1;
+        if (&mut *self.stream).take(limit).read_line(&mut line).await? == 0 {
+            return Err(BeanstalkcError::ConnectionError(
+                "connection closed by server".to_string(),
+            ));
+        }
+        if !line.ends_with('\n') && line.len() > MAX_RESPONSE_LINE {
+            return Err(desync("response line too long"));
+        }
 
-        let body_byte_count = match response.status {
-            Status::Ok => response.get_int_param(0)?,
-            Status::Reserved => response.get_int_param(1)?,
-            Status::Found => response.get_int_param(1)?,
-            _ => {
-                return Ok(response);
+        let (response, body_byte_count) = parse_line(line.as_bytes())?;
+        if body_byte_count.is_none() {
+            self.answered();
+            if self.debug {
+                wire::inbound(&line, None);
             }
-        } as usize;
+        }
+        Ok((line, response, body_byte_count.map(|count| count as u64)))
+    }
 
-        let mut tmp: Vec<u8> = vec![0; body_byte_count + 2]; // +2 trailing line break
-        let body = &mut tmp[..];
-        self.stream.read_exact(body).await?;
-        tmp.truncate(body_byte_count);
-        response.body = Some(tmp);
+    /// Fail if responses to earlier messages are still owed or partially read, since the
+    /// next response read would then be matched to the wrong command.
+    fn check_sequence(&self) -> BeanstalkcResult<()> {
+        if self.exchange.torn {
+            return Err(desync("command issued while a response is partially read"));
+        }
+        if self.exchange.pending > 0 {
+            return Err(desync(&format!(
+                "command issued while {} responses are owed",
+                self.exchange.pending
+            )));
+        }
+        Ok(())
+    }
 
-        Ok(response)
+    /// Record that the oldest response owed has been read completely.
+    fn answered(&mut self) {
+        self.exchange.torn = false;
+        self.exchange.pending = self.exchange.pending.saturating_sub(1);
+    }
+
+    /// Read the line break following a body streamed by [`Request::read_into`].
+    async fn read_line_break(&mut self) -> BeanstalkcResult<()> {
+        let mut line_break = [0; 2];
+        self.stream.read_exact(&mut line_break).await?;
+        if &line_break != b"\r\n" {
+            return Err(desync("body not followed by a line break"));
+        }
+        Ok(())
+    }
+
+    /// Discard the bytes received after a malformed response, until the server has been
+    /// quiet for a moment. Return whether the discarded bytes, if any, end with a line
+    /// break, in which case the next response should start at the next byte.
+    pub async fn drain(&mut self) -> bool {
+        let mut at_line_break = true;
+        loop {
+            let received = tokio::time::timeout(DESYNC_DRAIN_TIMEOUT, self.stream.fill_buf()).await;
+            let buf = match received {
+                Ok(Ok(buf)) if !buf.is_empty() => buf,
+                Ok(_) => return false,
+                Err(_) if at_line_break => {
+                    *self.exchange = Exchange::default();
+                    return true;
+                }
+                Err(_) => return false,
+            };
+            let len = buf.len();
+            at_line_break = buf.ends_with(b"\r\n");
+            self.stream.consume(len);
+        }
     }
 }
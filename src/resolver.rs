@@ -0,0 +1,74 @@
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+type ResolveFn = dyn Fn() -> BoxFuture<'static, io::Result<Vec<SocketAddr>>> + Send + Sync;
+
+/// Async callback returning the addresses to connect to, set with
+/// [`Beanstalkc::resolver`](crate::Beanstalkc::resolver).
+#[derive(Clone)]
+pub(crate) struct Resolver(Arc<ResolveFn>);
+
+impl Resolver {
+    pub(crate) fn new<F, Fut>(resolve: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<Vec<SocketAddr>>> + Send + 'static,
+    {
+        Resolver(Arc::new(move || resolve().boxed()))
+    }
+
+    pub(crate) async fn resolve(&self) -> io::Result<Vec<SocketAddr>> {
+        (self.0)().await
+    }
+}
+
+impl fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Resolver")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::fake::FakeServer;
+
+    #[tokio::test]
+    async fn test_resolver() {
+        let server = FakeServer::start().await;
+        let port = server.port();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let mut conn = server
+            .client()
+            .host("nowhere.invalid")
+            .resolver(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async move { Ok(vec![SocketAddr::from(([127, 0, 0, 1], port))]) }
+            })
+            .connect()
+            .await
+            .unwrap();
+        conn.stats_tube("default").await.unwrap();
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+
+        let conn = conn.reconnect().await.unwrap();
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+        drop(conn);
+
+        let failed = server
+            .client()
+            .resolver(|| async { Err(io::Error::new(io::ErrorKind::NotFound, "no endpoints")) })
+            .connect()
+            .await;
+        assert!(failed.is_err());
+    }
+}
@@ -1,7 +1,13 @@
 use crate::command::Status;
+use crate::config::{MAX_JOB_BODY, MAX_RESPONSE_LINE, MAX_YAML_BODY};
 use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::logging;
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
+use std::str::FromStr;
 
+/// `Response` is a beanstalkd response: its status, the parameters following it on the
+/// response line and the body, if any.
 #[derive(Debug)]
 pub struct Response {
     pub status: Status,
@@ -15,7 +21,11 @@ impl Response {
     }
 
     pub fn get_int_param(&self, index: usize) -> BeanstalkcResult<u64> {
-        let value: u64 = self.get_param(index)?.parse()?;
+        let value = self.get_param(index)?.parse().map_err(|err| {
+            let err = BeanstalkcError::from(err);
+            logging::parse_error(&err);
+            err
+        })?;
         Ok(value)
     }
 
@@ -50,6 +60,242 @@ impl Response {
         };
         Ok(res)
     }
+
+    /// Return the body, e.g. the job of a `RESERVED` response or the YAML of an `OK` one.
+    pub fn body_bytes(&self) -> Option<&[u8]> {
+        self.body.as_deref()
+    }
+
+    /// Deserialize the YAML body of an `OK` response, e.g. to a struct picking a few stats.
+    /// Fail if there is no body or if it does not match `T`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beanstalkc::{Response, Status};
+    ///
+    /// let resp = Response {
+    ///     status: Status::Ok,
+    ///     params: vec!["18".to_string()],
+    ///     body: Some(b"---\n- default\n- emails\n".to_vec()),
+    /// };
+    /// let tubes: Vec<String> = resp.body_yaml().unwrap();
+    /// assert_eq!(vec!["default", "emails"], tubes);
+    /// assert_eq!(18, resp.int_param(0).unwrap());
+    /// assert!(resp.int_param(1).is_err());
+    /// ```
+    pub fn body_yaml<T: DeserializeOwned>(&self) -> BeanstalkcResult<T> {
+        let body = self.body_bytes().ok_or_else(|| self.malformed("no body"))?;
+        serde_yaml::from_slice(body)
+            .map_err(|err| self.malformed(&format!("invalid YAML body: {}", err)))
+    }
+
+    /// Return the parameters following the status on the response line.
+    pub fn params_iter(&self) -> impl Iterator<Item = &str> {
+        self.params.iter().map(String::as_str)
+    }
+
+    /// Return parameter `index` as an integer, e.g. the job id of an `INSERTED` response.
+    /// Fail with an error naming the response and the parameter if it is missing or not an
+    /// integer.
+    pub fn int_param(&self, index: usize) -> BeanstalkcResult<u64> {
+        let param = self
+            .params
+            .get(index)
+            .ok_or_else(|| self.malformed(&format!("no param {}", index)))?;
+        param
+            .parse()
+            .map_err(|_| self.malformed(&format!("param {} is not an integer: {:?}", index, param)))
+    }
+
+    fn malformed(&self, reason: &str) -> BeanstalkcError {
+        let err =
+            BeanstalkcError::UnexpectedResponse(format!("{:?} response: {}", self.status, reason));
+        logging::parse_error(&err);
+        err
+    }
+}
+
+/// Parse a response line ending with a line break. Return the response along with the
+/// size of the body following it, if any.
+pub(crate) fn parse_line(line: &[u8]) -> BeanstalkcResult<(Response, Option<usize>)> {
+    let line = std::str::from_utf8(line).map_err(|_| desync("response line not UTF-8"))?;
+    if !line.ends_with("\r\n") {
+        return Err(desync("response line not terminated"));
+    }
+    let mut parts = line.split_whitespace();
+    let status = parts.next().ok_or_else(|| desync("empty response"))?;
+    let response = Response {
+        status: Status::from_str(status)
+            .map_err(|_| desync(&format!("unknown status {:?}", status)))?,
+        params: parts.map(|x| x.to_string()).collect(),
+        body: None,
+    };
+
+    let size_index = match response.status {
+        Status::Ok => 0,
+        Status::Reserved | Status::Found => 1,
+        _ => return Ok((response, None)),
+    };
+    let size = response
+        .params
+        .get(size_index)
+        .and_then(|size| size.parse().ok())
+        .ok_or_else(|| desync(&format!("invalid body size in {:?}", line.trim_end())))?;
+    Ok((response, Some(size)))
+}
+
+/// Keep the stats with an integer value.
+pub(crate) fn numeric(stats: HashMap<String, String>) -> HashMap<String, u64> {
+    stats
+        .into_iter()
+        .filter_map(|(stat, value)| value.parse().ok().map(|value| (stat, value)))
+        .collect()
+}
+
+/// Return the error for a malformed response.
+pub(crate) fn desync(reason: &str) -> BeanstalkcError {
+    let err = BeanstalkcError::ProtocolDesync(reason.to_string());
+    logging::parse_error(&err);
+    err
+}
+
+/// `ResponseParser` parses responses from bytes received in chunks of any size,
+/// independently of the connection.
+///
+/// # Example
+///
+/// ```
+/// use beanstalkc::{ResponseParser, Status};
+///
+/// let mut parser = ResponseParser::new();
+/// assert!(parser.push_bytes(b"RESERVED 7 4\r\nRu").unwrap().is_none());
+///
+/// let response = parser.push_bytes(b"st\r\nDELETED\r\n").unwrap().unwrap();
+/// assert_eq!(Status::Reserved, response.status);
+/// assert_eq!(Some(&b"Rust"[..]), response.body.as_deref());
+///
+/// let response = parser.push_bytes(b"").unwrap().unwrap();
+/// assert_eq!(Status::Deleted, response.status);
+/// ```
+#[derive(Debug, Default)]
+pub struct ResponseParser {
+    pending: Vec<u8>,
+    frame: Vec<u8>,
+    line: String,
+    head: Option<(Response, usize)>,
+    received: usize,
+    max_body: Option<usize>,
+    skipping: bool,
+}
+
+impl ResponseParser {
+    /// Create a parser expecting the start of a response.
+    pub fn new() -> Self {
+        ResponseParser::default()
+    }
+
+    /// Skip job bodies larger than `max` bytes instead of buffering them: the response is
+    /// then returned without its body, see [`ResponseParser::skipped`].
+    pub(crate) fn max_body(mut self, max: Option<usize>) -> Self {
+        self.max_body = max;
+        self
+    }
+
+    /// Return whether the body of the last response was skipped for being too large.
+    pub(crate) fn skipped(&self) -> bool {
+        self.skipping
+    }
+
+    /// Add received bytes and return the next complete response, if any. Bytes following
+    /// it are kept for the next calls: push an empty slice to get the responses already
+    /// received.
+    ///
+    /// Malformed responses fail with `BeanstalkcError::ProtocolDesync`, after which the
+    /// parser should be dropped. So do responses declaring a body larger than beanstalkd
+    /// could send, before any of it is buffered: 1GiB for jobs and 16MiB for YAML bodies.
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> BeanstalkcResult<Option<Response>> {
+        let mut pending = std::mem::take(&mut self.pending);
+        pending.extend_from_slice(bytes);
+        let (used, response) = self.feed(&pending)?;
+        pending.drain(..used);
+        self.pending = pending;
+        Ok(response)
+    }
+
+    /// Parse bytes up to the end of the current response at most. Return how many bytes
+    /// were used, and the response if it is complete.
+    pub(crate) fn feed(&mut self, bytes: &[u8]) -> BeanstalkcResult<(usize, Option<Response>)> {
+        let mut used = 0;
+        if self.head.is_none() {
+            let end = match bytes.iter().position(|&b| b == b'\n') {
+                Some(i) => i + 1,
+                None => bytes.len(),
+            };
+            self.frame.extend_from_slice(&bytes[..end]);
+            used = end;
+            if !self.frame.ends_with(b"\n") {
+                if self.frame.len() > MAX_RESPONSE_LINE {
+                    return Err(desync("response line too long"));
+                }
+                return Ok((used, None));
+            }
+
+            let (response, size) = parse_line(&self.frame)?;
+            self.line = String::from_utf8_lossy(&self.frame).into_owned();
+            self.frame.clear();
+            self.skipping = false;
+            match size {
+                Some(size) => {
+                    let job = matches!(response.status, Status::Reserved | Status::Found);
+                    let limit = if job { MAX_JOB_BODY } else { MAX_YAML_BODY };
+                    if size > limit {
+                        return Err(desync(&format!(
+                            "body of {} bytes exceeds the limit of {} bytes",
+                            size, limit
+                        )));
+                    }
+                    self.skipping = job && self.max_body.is_some_and(|max| size > max);
+                    self.received = 0;
+                    self.head = Some((response, size));
+                }
+                None => return Ok((used, Some(response))),
+            }
+        }
+
+        let size = self.head.as_ref().map_or(0, |(_, size)| *size);
+        let missing = size.saturating_add(2) - self.received;
+        let take = missing.min(bytes.len() - used);
+        let chunk = &bytes[used..used + take];
+        if self.skipping {
+            // Keep only the line break following the body.
+            let body_left = size.saturating_sub(self.received).min(take);
+            self.frame.extend_from_slice(&chunk[body_left..]);
+        } else {
+            self.frame.extend_from_slice(chunk);
+        }
+        self.received += take;
+        used += take;
+        if take < missing {
+            return Ok((used, None));
+        }
+
+        if !self.frame.ends_with(b"\r\n") {
+            return Err(desync("body not followed by a line break"));
+        }
+        let mut body = std::mem::take(&mut self.frame);
+        let (mut response, _) = self.head.take().expect("a body follows a response line");
+        if !self.skipping {
+            body.truncate(size);
+            response.body = Some(body);
+        }
+        Ok((used, Some(response)))
+    }
+
+    /// Return the last response line parsed.
+    pub(crate) fn line(&self) -> &str {
+        &self.line
+    }
 }
 
 impl Default for Response {
@@ -91,6 +337,23 @@ mod tests {
         assert_eq!(5, r.unwrap());
     }
 
+    #[test]
+    fn test_int_param() {
+        let resp = Response {
+            status: Status::Reserved,
+            params: vec!["100".to_string(), "x".to_string()],
+            body: None,
+        };
+        assert_eq!(100, resp.int_param(0).unwrap());
+        assert_eq!(vec!["100", "x"], resp.params_iter().collect::<Vec<_>>());
+        assert_eq!(
+            "Unexpected response: Reserved response: param 1 is not an integer: \"x\"",
+            resp.int_param(1).unwrap_err().to_string()
+        );
+        assert!(resp.int_param(2).is_err());
+        assert!(resp.body_yaml::<Vec<String>>().is_err());
+    }
+
     #[test]
     fn test_get_body_as_vec() {
         let resp = Response {
@@ -103,6 +366,96 @@ mod tests {
         assert_eq!(vec!["default".to_string(), "jobs".to_string()], tubes);
     }
 
+    #[test]
+    fn test_parse_line() {
+        let (resp, size) = parse_line(b"RESERVED 1 5\r\n").unwrap();
+        assert_eq!(Status::Reserved, resp.status);
+        assert_eq!(Some(5), size);
+
+        let (resp, size) = parse_line(b"INSERTED 3\r\n").unwrap();
+        assert_eq!(vec!["3".to_string()], resp.params);
+        assert_eq!(None, size);
+
+        assert!(parse_line(b"\r\n").is_err());
+        assert!(parse_line(b"OK\r\n").is_err());
+        assert!(parse_line(b"FOUND 1 -2\r\n").is_err());
+        assert!(parse_line(b"NOPE\r\n").is_err());
+        assert!(parse_line(b"DELETED\n").is_err());
+    }
+
+    #[test]
+    fn test_parser_chunks() {
+        let data = b"OK 8\r\n- a\r\n- b\r\nINSERTED 9\r\nFOUND 2 0\r\n\r\n";
+        for chunk_size in 1..data.len() {
+            let mut parser = ResponseParser::new();
+            let mut responses = vec![];
+            for chunk in data.chunks(chunk_size) {
+                let mut next = parser.push_bytes(chunk).unwrap();
+                while let Some(resp) = next {
+                    responses.push(resp);
+                    next = parser.push_bytes(b"").unwrap();
+                }
+            }
+            let statuses: Vec<_> = responses.iter().map(|r| r.status).collect();
+            assert_eq!(vec![Status::Ok, Status::Inserted, Status::Found], statuses);
+            assert_eq!(Some(&b"- a\r\n- b"[..]), responses[0].body.as_deref());
+            assert_eq!(Some(&b""[..]), responses[2].body.as_deref());
+        }
+    }
+
+    #[test]
+    fn test_parser_skip() {
+        let data = b"RESERVED 1 10\r\n0123456789\r\nFOUND 2 3\r\nabc\r\n";
+        for chunk_size in 1..data.len() {
+            let mut parser = ResponseParser::new().max_body(Some(5));
+            let mut responses = vec![];
+            for chunk in data.chunks(chunk_size) {
+                let mut next = parser.push_bytes(chunk).unwrap();
+                while let Some(resp) = next {
+                    responses.push((resp, parser.skipped()));
+                    next = parser.push_bytes(b"").unwrap();
+                }
+            }
+            assert_eq!(2, responses.len());
+            assert!(responses[0].1);
+            assert_eq!(None, responses[0].0.body);
+            assert!(!responses[1].1);
+            assert_eq!(Some(&b"abc"[..]), responses[1].0.body.as_deref());
+        }
+    }
+
+    #[test]
+    fn test_parser_malformed() {
+        let mut parser = ResponseParser::new();
+        assert!(parser.push_bytes(b"RESERVED 1 2\r\nabcd").is_err());
+
+        let mut parser = ResponseParser::new();
+        assert!(parser.push_bytes(&[b'x'; 2000]).is_err());
+
+        let mut parser = ResponseParser::new();
+        let huge = format!("OK {}\r\n", usize::MAX);
+        assert!(parser.push_bytes(huge.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_parser_body_limit() {
+        let mut parser = ResponseParser::new();
+        let oversized = format!("OK {}\r\n", MAX_YAML_BODY + 1);
+        assert!(matches!(
+            parser.push_bytes(oversized.as_bytes()),
+            Err(BeanstalkcError::ProtocolDesync(_))
+        ));
+        assert!(parser.frame.is_empty() && parser.head.is_none());
+
+        let mut parser = ResponseParser::new().max_body(Some(5));
+        let oversized = format!("RESERVED 1 {}\r\n", MAX_JOB_BODY + 1);
+        assert!(parser.push_bytes(oversized.as_bytes()).is_err());
+
+        let mut parser = ResponseParser::new();
+        let allowed = format!("RESERVED 1 {}\r\n", MAX_YAML_BODY + 1);
+        assert!(parser.push_bytes(allowed.as_bytes()).unwrap().is_none());
+    }
+
     #[test]
     fn test_get_body_as_map() {
         let resp = Response {
@@ -0,0 +1,198 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::command::CommandKind;
+use crate::config::{DEFAULT_RETRY_INITIAL_BACKOFF, DEFAULT_RETRY_MAX_BACKOFF};
+use crate::error::BeanstalkcError;
+
+type Classifier = Arc<dyn Fn(&BeanstalkcError) -> bool + Send + Sync>;
+
+/// `RetryPolicy` retries commands which failed because of a transient I/O error.
+///
+/// Before each retry the client waits for an exponential backoff with jitter, then
+/// reconnects to the server and restores the used and watched tubes. Only idempotent
+/// commands are retried: `stats*`, `peek*`, `list-*`, `use`, `watch` and `ignore`. Puts are
+/// retried as well when enabled with [`RetryPolicy::retry_puts`].
+///
+/// Reconnecting releases every job reserved by the broken connection.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use std::time::Duration;
+/// use beanstalkc::{Beanstalkc, RetryPolicy};
+///
+/// let mut conn = Beanstalkc::new()
+///        .retry_policy(
+///            RetryPolicy::new(5)
+///                .backoff(Duration::from_millis(50), Duration::from_secs(2))
+///                .retry_puts(true),
+///        )
+///        .connect()
+///        .await
+///        .unwrap();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    pub(crate) max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    retry_puts: bool,
+    classifier: Classifier,
+}
+
+impl RetryPolicy {
+    /// Try each command at most `max_attempts` times, including the first attempt.
+    /// Connection errors are retried by default.
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: DEFAULT_RETRY_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_RETRY_MAX_BACKOFF,
+            jitter: true,
+            retry_puts: false,
+            classifier: Arc::new(BeanstalkcError::is_connection_error),
+        }
+    }
+
+    /// Wait `initial` before the first retry, doubling the wait for each following retry
+    /// up to `max`.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Randomize each wait between half and all of the backoff. Enabled by default.
+    pub fn jitter(mut self, enabled: bool) -> Self {
+        self.jitter = enabled;
+        self
+    }
+
+    /// Also reconnect and retry puts. A put whose response was lost may have been
+    /// inserted already, so enabling this can duplicate jobs.
+    pub fn retry_puts(mut self, enabled: bool) -> Self {
+        self.retry_puts = enabled;
+        self
+    }
+
+    /// Decide which errors are transient.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io::ErrorKind;
+    /// use beanstalkc::RetryPolicy;
+    ///
+    /// let policy = RetryPolicy::new(3).retry_if(|err| err.io_kind() == Some(ErrorKind::BrokenPipe));
+    /// ```
+    pub fn retry_if<F>(mut self, classifier: F) -> Self
+    where
+        F: Fn(&BeanstalkcError) -> bool + Send + Sync + 'static,
+    {
+        self.classifier = Arc::new(classifier);
+        self
+    }
+
+    /// Return whether commands of this kind may be retried.
+    pub(crate) fn applies_to(&self, kind: CommandKind) -> bool {
+        match kind {
+            CommandKind::PeekJob
+            | CommandKind::PeekReady
+            | CommandKind::PeekDelayed
+            | CommandKind::PeekBuried
+            | CommandKind::Stats
+            | CommandKind::JobStats
+            | CommandKind::StatsTube
+            | CommandKind::Use
+            | CommandKind::Watch
+            | CommandKind::Ignore
+            | CommandKind::ListTubes
+            | CommandKind::ListTubeUsed
+            | CommandKind::ListTubesWatched => true,
+            CommandKind::Put => self.retry_puts,
+            _ => false,
+        }
+    }
+
+    /// Return whether `err` is transient.
+    pub(crate) fn is_retryable(&self, err: &BeanstalkcError) -> bool {
+        (self.classifier)(err)
+    }
+
+    /// Return how long to wait before the retry following the given failed attempt.
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let backoff = self
+            .initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |d| d.min(self.max_backoff));
+        if !self.jitter {
+            return backoff;
+        }
+
+        let random = RandomState::new().build_hasher().finish();
+        backoff / 2 + backoff.mul_f64((random as f64 / u64::MAX as f64) / 2.0)
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("jitter", &self.jitter)
+            .field("retry_puts", &self.retry_puts)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::new(10)
+            .backoff(Duration::from_millis(100), Duration::from_secs(1))
+            .jitter(false);
+        assert_eq!(Duration::from_millis(100), policy.delay(1));
+        assert_eq!(Duration::from_millis(400), policy.delay(3));
+        assert_eq!(Duration::from_secs(1), policy.delay(5));
+        assert_eq!(Duration::from_secs(1), policy.delay(64));
+
+        let policy = policy.jitter(true);
+        for attempt in 1..5 {
+            let delay = policy.delay(attempt);
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn test_applies_to() {
+        let policy = RetryPolicy::new(3);
+        assert!(policy.applies_to(CommandKind::StatsTube));
+        assert!(policy.applies_to(CommandKind::Use));
+        assert!(!policy.applies_to(CommandKind::Reserve));
+        assert!(!policy.applies_to(CommandKind::Delete));
+        assert!(!policy.applies_to(CommandKind::Put));
+        assert!(policy.retry_puts(true).applies_to(CommandKind::Put));
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        let policy = RetryPolicy::new(3);
+        assert!(policy.is_retryable(&BeanstalkcError::ConnectionError("".to_string())));
+        assert!(!policy.is_retryable(&BeanstalkcError::CommandFailed("".to_string())));
+    }
+}
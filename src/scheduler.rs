@@ -0,0 +1,256 @@
+use std::future::{self, Future};
+use std::time::{Duration, SystemTime};
+
+use crate::config::SCHEDULER_MAX_SLEEP;
+use crate::cron::Cron;
+use crate::error::BeanstalkcResult;
+use crate::logging;
+use crate::put::PutRequest;
+use crate::Beanstalkc;
+
+/// When a [`Scheduler`] puts recurring jobs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule(Kind);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Every(Duration),
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// Put a job every `interval`, the first one `interval` after the scheduler starts.
+    pub fn every(interval: Duration) -> Self {
+        Schedule(Kind::Every(interval.max(Duration::from_millis(1))))
+    }
+
+    /// Put a job at the times matching a five field cron expression
+    /// (`minute hour day-of-month month day-of-week`) in UTC, e.g. `*/5 * * * *`.
+    ///
+    /// Fields are `*`, numbers, ranges `a-b`, steps `/n` and comma separated lists of
+    /// those, and `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` are accepted.
+    /// Fail with `BeanstalkcError::InvalidSchedule` on any other expression.
+    pub fn cron(expr: &str) -> BeanstalkcResult<Self> {
+        Cron::parse(expr).map(|cron| Schedule(Kind::Cron(cron)))
+    }
+
+    /// Return the first time the schedule fires after `time`.
+    fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        match &self.0 {
+            Kind::Every(interval) => Some(time + *interval),
+            Kind::Cron(cron) => cron.next_after(time),
+        }
+    }
+
+    /// Return the first time the schedule fires after `now`, skipping the runs missed
+    /// since the run due at `due`. Intervals stay in step with `due` rather than `now`.
+    fn next_skipping(&self, due: SystemTime, now: SystemTime) -> Option<SystemTime> {
+        match &self.0 {
+            Kind::Every(interval) => {
+                let late = now.duration_since(due).unwrap_or_default();
+                let runs = late.as_nanos() / interval.as_nanos() + 1;
+                Some(due + Duration::from_nanos((interval.as_nanos() * runs) as u64))
+            }
+            Kind::Cron(cron) => cron.next_after(now),
+        }
+    }
+}
+
+/// What a [`Scheduler`] does with the runs it missed, e.g. while it was not running or
+/// failed to put.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MissedRuns {
+    /// Put a single job for all the missed runs.
+    #[default]
+    Skip,
+    /// Put a job for every missed run.
+    CatchUp,
+}
+
+#[derive(Debug)]
+struct Entry {
+    tube: String,
+    schedule: Schedule,
+    request: PutRequest,
+    due: Option<SystemTime>,
+}
+
+/// `Scheduler` puts recurring jobs into tubes, on fixed intervals or cron schedules.
+///
+/// A run is only considered done once its job has been put: when a put fails, the error is
+/// logged and the put is tried again a second later, while the other schedules keep
+/// running. Configure a [`RetryPolicy`](crate::RetryPolicy) with `retry_puts`, or a
+/// supervisor, on the connection to ride out disconnects.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use std::time::Duration;
+/// use beanstalkc::{Beanstalkc, MissedRuns, PutRequest, Schedule, Scheduler};
+///
+/// let conn = Beanstalkc::new().connect().await.unwrap();
+///
+/// let mut scheduler = Scheduler::new(conn)
+///     .missed_runs(MissedRuns::CatchUp)
+///     .schedule(
+///         "reports",
+///         Schedule::cron("0 6 * * 1-5").unwrap(),
+///         PutRequest::new(b"daily-report".to_vec()),
+///     )
+///     .schedule(
+///         "health",
+///         Schedule::every(Duration::from_secs(30)),
+///         PutRequest::new(b"ping".to_vec()).ttr(Duration::from_secs(5)),
+///     );
+///
+/// scheduler.run_until(tokio::signal::ctrl_c()).await.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Scheduler {
+    conn: Beanstalkc,
+    entries: Vec<Entry>,
+    missed_runs: MissedRuns,
+}
+
+impl Scheduler {
+    /// Create a new `Scheduler` putting jobs through a connected client.
+    pub fn new(conn: Beanstalkc) -> Self {
+        Scheduler {
+            conn,
+            entries: vec![],
+            missed_runs: MissedRuns::default(),
+        }
+    }
+
+    /// Put `request` into `tube` on `schedule`.
+    pub fn schedule(mut self, tube: &str, schedule: Schedule, request: PutRequest) -> Self {
+        self.entries.push(Entry {
+            tube: tube.to_string(),
+            schedule,
+            request,
+            due: None,
+        });
+        self
+    }
+
+    /// Set what to do with missed runs. Default is `MissedRuns::Skip`.
+    pub fn missed_runs(mut self, policy: MissedRuns) -> Self {
+        self.missed_runs = policy;
+        self
+    }
+
+    /// Put the scheduled jobs until no schedule fires anymore.
+    pub async fn run(&mut self) -> BeanstalkcResult<()> {
+        self.run_until(future::pending::<()>()).await
+    }
+
+    /// Put the scheduled jobs like [`Scheduler::run`] until `shutdown` completes. A put in
+    /// progress is completed before returning.
+    pub async fn run_until<S: Future>(&mut self, shutdown: S) -> BeanstalkcResult<()> {
+        tokio::pin!(shutdown);
+        let start = SystemTime::now();
+        for entry in self.entries.iter_mut().filter(|e| e.due.is_none()) {
+            entry.due = entry.schedule.next_after(start);
+        }
+
+        loop {
+            let now = SystemTime::now();
+            let mut failed = false;
+            for entry in self.entries.iter_mut() {
+                while let Some(due) = entry.due.filter(|due| *due <= now) {
+                    let request = &entry.request;
+                    let (priority, delay, ttr) = self.conn.put_options(request);
+                    let put = self
+                        .conn
+                        .tube(&entry.tube)
+                        .put(&request.body, priority, delay, ttr)
+                        .await;
+                    if let Err(err) = put {
+                        logging::scheduled_put_failed(&entry.tube, &err);
+                        failed = true;
+                        break;
+                    }
+                    entry.due = match self.missed_runs {
+                        MissedRuns::Skip => entry.schedule.next_skipping(due, now),
+                        MissedRuns::CatchUp => entry.schedule.next_after(due),
+                    };
+                }
+            }
+
+            let next = match self.entries.iter().filter_map(|e| e.due).min() {
+                Some(next) => next,
+                None => return Ok(()),
+            };
+            let wait = if failed {
+                SCHEDULER_MAX_SLEEP
+            } else {
+                next.duration_since(SystemTime::now())
+                    .unwrap_or_default()
+                    .min(SCHEDULER_MAX_SLEEP)
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = &mut shutdown => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+
+    #[tokio::test]
+    async fn test_run_retries_failed_put() {
+        let server = FakeServer::start().await;
+        server.reply("put 2147483648 0 120 4", "DRAINING");
+        let conn = server.client().connect().await.unwrap();
+
+        let mut scheduler = Scheduler::new(conn).schedule(
+            "health",
+            Schedule::every(Duration::from_millis(100)),
+            PutRequest::new(b"ping".to_vec()),
+        );
+        scheduler
+            .run_until(tokio::time::sleep(Duration::from_millis(1500)))
+            .await
+            .unwrap();
+
+        let puts = server
+            .commands()
+            .iter()
+            .filter(|cmd| cmd.starts_with("put"))
+            .count();
+        assert_eq!(2, puts);
+    }
+
+    #[test]
+    fn test_schedule() {
+        let start = SystemTime::now();
+        let every = Schedule::every(Duration::from_secs(30));
+        assert_eq!(
+            Some(start + Duration::from_secs(30)),
+            every.next_after(start)
+        );
+        assert_eq!(
+            Schedule::every(Duration::from_millis(1)),
+            Schedule::every(Duration::from_secs(0))
+        );
+        assert!(Schedule::cron("@hourly").unwrap().next_after(start) > Some(start));
+
+        let now = start + Duration::from_secs(95);
+        assert_eq!(
+            Some(start + Duration::from_secs(120)),
+            every.next_skipping(start, now)
+        );
+        assert_eq!(
+            Some(start + Duration::from_secs(30)),
+            every.next_skipping(start, start)
+        );
+        assert!(Schedule::cron("every minute").is_err());
+    }
+}
@@ -0,0 +1,445 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+#[cfg(feature = "tower")]
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tokio::sync::{mpsc, oneshot, watch, OwnedSemaphorePermit, Semaphore};
+
+use crate::beanstalkc::command_failed;
+#[cfg(feature = "tower")]
+use crate::command::Command;
+use crate::config::SUPERVISOR_PING_INTERVAL;
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::pool::{PooledJob, ReservePool};
+use crate::put::PutRequest;
+#[cfg(feature = "tower")]
+use crate::response::Response;
+use crate::supervisor::ConnectionState;
+use crate::Beanstalkc;
+
+type Call = Box<dyn for<'a> FnOnce(&'a mut Beanstalkc) -> BoxFuture<'a, ()> + Send>;
+
+/// What a [`SharedClient`] does with a command once its in-flight limit is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Overflow {
+    /// Wait until another command completes.
+    #[default]
+    Wait,
+    /// Fail immediately with `BeanstalkcError::Overloaded`.
+    FailFast,
+}
+
+/// `SharedClient` is a cloneable handle sharing one connection between tasks.
+///
+/// Commands from every clone are queued to a background task owning the connection, which
+/// runs them one at a time, in order. Jobs are reserved through dedicated connections
+/// configured with [`Beanstalkc::reserve_connections`], so a blocking reserve never delays
+/// the other commands. A reserved [`PooledJob`] is touched and finalized on the connection
+/// which reserved it, so a burst of queued puts cannot delay a touch past the TTR of its
+/// job.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use beanstalkc::{Beanstalkc, Overflow, PutRequest, SharedClient};
+///
+/// let conn = Beanstalkc::new().connect().await.unwrap();
+/// let client = SharedClient::new(conn).max_in_flight(1000, Overflow::FailFast);
+///
+/// let producer = client.clone();
+/// tokio::spawn(async move {
+///     producer.put(PutRequest::new(b"Rust".to_vec())).await.unwrap();
+/// });
+/// dbg!(client.stats().await.unwrap());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedClient {
+    sender: mpsc::UnboundedSender<Call>,
+    state: watch::Receiver<ConnectionState>,
+    in_flight: Option<InFlight>,
+    reserve_pool: Option<Arc<ReservePool>>,
+}
+
+/// Limits the commands queued or running at once.
+#[derive(Debug, Clone)]
+struct InFlight {
+    permits: Arc<Semaphore>,
+    max: usize,
+    overflow: Overflow,
+}
+
+impl SharedClient {
+    /// Spawn the task owning `conn`. Must be called from within a tokio runtime.
+    pub fn new(mut conn: Beanstalkc) -> Self {
+        let reserve_pool = match conn.get_reserve_connections() {
+            0 => None,
+            count => Some(ReservePool::new(
+                (0..count).map(|_| conn.duplicate()).collect(),
+            )),
+        };
+        let state = conn.watch_state();
+        let supervised = conn.is_supervised();
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Call>();
+        tokio::spawn(async move {
+            loop {
+                let call = tokio::select! {
+                    call = receiver.recv() => match call {
+                        Some(call) => call,
+                        None => break,
+                    },
+                    _ = tokio::time::sleep(SUPERVISOR_PING_INTERVAL), if supervised => {
+                        conn.monitor(SUPERVISOR_PING_INTERVAL).await;
+                        continue;
+                    }
+                };
+                call(&mut conn).await;
+                if !conn.is_connected() {
+                    let _ = conn.supervise().await;
+                }
+            }
+            conn.close().await;
+        });
+
+        SharedClient {
+            sender,
+            state,
+            in_flight: None,
+            reserve_pool,
+        }
+    }
+
+    /// Limit the number of commands queued or running at once to `max`, across all clones
+    /// made afterwards, so a stalled server cannot make the queue grow without bound.
+    pub fn max_in_flight(mut self, max: usize, overflow: Overflow) -> Self {
+        let max = max.max(1);
+        self.in_flight = Some(InFlight {
+            permits: Arc::new(Semaphore::new(max)),
+            max,
+            overflow,
+        });
+        self
+    }
+
+    /// Return a channel receiving the state of the shared connection whenever it changes,
+    /// see [`Beanstalkc::watch_state`].
+    pub fn watch_state(&self) -> watch::Receiver<ConnectionState> {
+        self.state.clone()
+    }
+
+    /// Put a job into the current tube and return the job id. The current tube is shared by
+    /// every clone, see [`SharedClient::use_tube`].
+    pub async fn put(&self, request: PutRequest) -> BeanstalkcResult<u64> {
+        self.call(move |conn| async move { conn.put_request(&request).await }.boxed())
+            .await
+    }
+
+    /// Put a job into tube `name` and return the job id, whatever the current tube. Unlike
+    /// [`SharedClient::use_tube`] followed by [`SharedClient::put`], no command of another
+    /// clone can run in between, and the current tube is restored afterwards.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, PutRequest, SharedClient};
+    ///
+    /// let client = SharedClient::new(Beanstalkc::new().connect().await.unwrap());
+    ///
+    /// let emails = client.clone();
+    /// tokio::spawn(async move {
+    ///     emails.put_in("emails", PutRequest::new(b"welcome".to_vec())).await.unwrap();
+    /// });
+    /// client.put_in("reports", PutRequest::new(b"daily".to_vec())).await.unwrap();
+    /// }
+    /// ```
+    pub async fn put_in(&self, name: &str, request: PutRequest) -> BeanstalkcResult<u64> {
+        let name = name.to_string();
+        self.call(move |conn| {
+            async move {
+                conn.with_tube(&name, move |conn| {
+                    async move { conn.put_request(&request).await }.boxed()
+                })
+                .await
+            }
+            .boxed()
+        })
+        .await
+    }
+
+    /// Reserve a job on an idle reserve connection, waiting for one if they are all busy.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, PutRequest, SharedClient};
+    ///
+    /// let conn = Beanstalkc::new().reserve_connections(2).connect().await.unwrap();
+    /// let client = SharedClient::new(conn);
+    ///
+    /// let job = client.reserve().await.unwrap();
+    /// client.put(PutRequest::new(job.body().to_vec())).await.unwrap();
+    /// job.delete().await.unwrap();
+    /// }
+    /// ```
+    pub async fn reserve(&self) -> BeanstalkcResult<PooledJob> {
+        self.reserve_pooled(None).await
+    }
+
+    /// Reserve a job on an idle reserve connection, waiting at most `timeout` for a job.
+    pub async fn reserve_with_timeout(&self, timeout: Duration) -> BeanstalkcResult<PooledJob> {
+        self.reserve_pooled(Some(timeout)).await
+    }
+
+    async fn reserve_pooled(&self, timeout: Option<Duration>) -> BeanstalkcResult<PooledJob> {
+        let pool = self.reserve_pool.as_ref().ok_or_else(|| {
+            BeanstalkcError::ConnectionError("no reserve connection configured".to_string())
+        })?;
+        let mut lease = pool.checkout().await?;
+        let (id, body) = lease
+            .conn()
+            .reserve_status(timeout)
+            .await?
+            .map_err(command_failed)?;
+        Ok(PooledJob::new(lease, id, body))
+    }
+
+    /// Use a given tube for the following puts of every clone. The current tube belongs to
+    /// the shared connection: when clones put into different tubes, a clone may switch it
+    /// between the `use_tube` and the `put` of another, so use [`SharedClient::put_in`]
+    /// instead.
+    pub async fn use_tube(&self, name: &str) -> BeanstalkcResult<String> {
+        let name = name.to_string();
+        self.call(move |conn| async move { conn.use_tube(&name).await }.boxed())
+            .await
+    }
+
+    /// Delete a job which is not reserved by job id. Reserved jobs are deleted through their
+    /// [`PooledJob`], since only the connection which reserved them can.
+    pub async fn delete(&self, job_id: u64) -> BeanstalkcResult<()> {
+        self.call(move |conn| async move { conn.delete(job_id).await }.boxed())
+            .await
+    }
+
+    /// Kick a specific job to ready queue.
+    pub async fn kick_job(&self, job_id: u64) -> BeanstalkcResult<()> {
+        self.call(move |conn| async move { conn.kick_job(job_id).await }.boxed())
+            .await
+    }
+
+    /// Return a dict of statistical information about the beanstalkd server.
+    pub async fn stats(&self) -> BeanstalkcResult<HashMap<String, String>> {
+        self.call(move |conn| async move { conn.stats().await }.boxed())
+            .await
+    }
+
+    /// Return a dict of statistical information about the specified tube.
+    pub async fn stats_tube(&self, name: &str) -> BeanstalkcResult<HashMap<String, String>> {
+        let name = name.to_string();
+        self.call(move |conn| async move { conn.stats_tube(&name).await }.boxed())
+            .await
+    }
+
+    /// Return a dict of statistical information about a job.
+    pub async fn stats_job(&self, job_id: u64) -> BeanstalkcResult<HashMap<String, String>> {
+        self.call(move |conn| async move { conn.stats_job(job_id).await }.boxed())
+            .await
+    }
+
+    /// Queue `f` to run against the connection and wait for its result.
+    async fn call<T, F>(&self, f: F) -> BeanstalkcResult<T>
+    where
+        T: Send + 'static,
+        F: for<'a> FnOnce(&'a mut Beanstalkc) -> BoxFuture<'a, BeanstalkcResult<T>>
+            + Send
+            + 'static,
+    {
+        let permit = self.acquire().await?;
+        let (reply, result) = oneshot::channel();
+        let call: Call = Box::new(move |conn| {
+            async move {
+                let _ = reply.send(f(conn).await);
+                drop(permit);
+            }
+            .boxed()
+        });
+        self.sender.send(call).map_err(|_| closed())?;
+        result.await.map_err(|_| closed())?
+    }
+
+    /// Take an in-flight slot, if limited. The slot is released once the command has run,
+    /// even if the caller stopped waiting for it.
+    async fn acquire(&self) -> BeanstalkcResult<Option<OwnedSemaphorePermit>> {
+        let limit = match &self.in_flight {
+            Some(limit) => limit,
+            None => return Ok(None),
+        };
+        let permits = Arc::clone(&limit.permits);
+        let permit = match limit.overflow {
+            Overflow::Wait => permits.acquire_owned().await.ok(),
+            Overflow::FailFast => permits.try_acquire_owned().ok(),
+        };
+        permit.map(Some).ok_or_else(|| {
+            BeanstalkcError::Overloaded(format!("{} commands already in flight", limit.max))
+        })
+    }
+}
+
+/// Send raw commands, checking their response status like the typed methods do, so that
+/// tower middleware such as timeouts or load shedding can wrap the client. The client is
+/// always ready: combine it with `max_in_flight` or a concurrency limit layer to bound
+/// the queued commands.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use beanstalkc::{command, Beanstalkc, SharedClient};
+/// use tower_service::Service;
+///
+/// let conn = Beanstalkc::new().connect().await.unwrap();
+/// let mut client = SharedClient::new(conn);
+///
+/// let resp = client.call(command::stats_tube("default")).await.unwrap();
+/// dbg!(resp.body_as_map().unwrap());
+/// }
+/// ```
+#[cfg(feature = "tower")]
+impl<'a> tower_service::Service<Command<'a>> for SharedClient {
+    type Response = Response;
+    type Error = BeanstalkcError;
+    type Future = BoxFuture<'static, BeanstalkcResult<Response>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<BeanstalkcResult<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, cmd: Command<'a>) -> Self::Future {
+        let client = self.clone();
+        let cmd = cmd.into_owned();
+        async move {
+            client
+                .call(move |conn| async move { conn.send(cmd).await }.boxed())
+                .await
+        }
+        .boxed()
+    }
+}
+
+fn closed() -> BeanstalkcError {
+    BeanstalkcError::ConnectionError("shared client is closed".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+    use crate::RetryPolicy;
+
+    #[tokio::test]
+    async fn test_supervisor_reconnects_idle() {
+        let server = FakeServer::start().await;
+        let conn = server
+            .client()
+            .supervisor(RetryPolicy::new(3))
+            .connect()
+            .await
+            .unwrap();
+        let client = SharedClient::new(conn);
+        client.stats_tube("default").await.unwrap();
+        assert_eq!(1, server.connections());
+
+        server.drop_connections();
+        for _ in 0..50 {
+            if server.connections() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(2, server.connections());
+        assert_eq!(ConnectionState::Connected, *client.watch_state().borrow());
+
+        drop(client);
+        for _ in 0..50 {
+            if server.commands().last().map(String::as_str) == Some("quit") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(Some("quit"), server.commands().last().map(String::as_str));
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_limit() {
+        let client = SharedClient::new(Beanstalkc::new()).max_in_flight(10, Overflow::FailFast);
+        let mut permits = vec![];
+        for _ in 0..10 {
+            permits.push(client.acquire().await.unwrap());
+        }
+        assert!(matches!(
+            client.acquire().await,
+            Err(BeanstalkcError::Overloaded(_))
+        ));
+
+        permits.pop();
+        assert!(client.acquire().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_touch_during_put_burst() {
+        let server = FakeServer::start().await;
+        let id = server.put("default", b"job");
+        let conn = server
+            .client()
+            .reserve_connections(1)
+            .connect()
+            .await
+            .unwrap();
+        let client = SharedClient::new(conn);
+        let mut job = client.reserve().await.unwrap();
+        assert_eq!(id, job.id());
+
+        let puts: Vec<_> = (0..200)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.put(PutRequest::new(b"bulk".to_vec())).await })
+            })
+            .collect();
+        job.touch().await.unwrap();
+        job.delete().await.unwrap();
+        for put in puts {
+            put.await.unwrap().unwrap();
+        }
+
+        assert_eq!(None, server.state(id));
+        assert_eq!(200, server.jobs_in("ready").len());
+    }
+
+    #[tokio::test]
+    async fn test_put_in() {
+        let server = FakeServer::start().await;
+        let client = SharedClient::new(server.client().connect().await.unwrap());
+        client.use_tube("jobs").await.unwrap();
+
+        let emails = client.clone();
+        let reports = client.clone();
+        let (email, report) = tokio::join!(
+            emails.put_in("emails", PutRequest::new(b"welcome".to_vec())),
+            reports.put_in("reports", PutRequest::new(b"daily".to_vec())),
+        );
+        let job = client.put(PutRequest::new(b"job".to_vec())).await.unwrap();
+
+        assert_eq!("emails", server.job(email.unwrap()).unwrap().0);
+        assert_eq!("reports", server.job(report.unwrap()).unwrap().0);
+        assert_eq!("jobs", server.job(job).unwrap().0);
+    }
+}
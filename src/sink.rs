@@ -0,0 +1,173 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures::channel::mpsc;
+use futures::{ready, Sink, StreamExt};
+use tokio::task::JoinHandle;
+
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::put::PutRequest;
+use crate::Beanstalkc;
+
+/// `PutSink` puts jobs from a `futures::Sink` with backpressure.
+///
+/// Requests are queued into a bounded buffer and put in order by a background task owning
+/// a dedicated connection, so streaming pipelines can `forward()` work into beanstalkd
+/// without awaiting each put. Sending waits while the buffer is full.
+///
+/// Once a put fails the task stops and the sink returns the error. Flushing or closing the
+/// sink waits until every request sent has been put.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use beanstalkc::{Beanstalkc, PutRequest, PutSink};
+/// use futures::{stream, SinkExt, StreamExt};
+///
+/// let mut conn = Beanstalkc::new().connect().await.unwrap();
+/// conn.use_tube("jobs").await.unwrap();
+///
+/// let mut sink = PutSink::new(conn, 64);
+/// let mut jobs = stream::iter(0..1000).map(|i| Ok(PutRequest::new(format!("{}", i).into_bytes())));
+/// sink.send_all(&mut jobs).await.unwrap();
+/// sink.close().await.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PutSink {
+    sender: mpsc::Sender<PutRequest>,
+    progress: Arc<Mutex<Progress>>,
+    task: Option<JoinHandle<()>>,
+}
+
+/// Requests sent to the background task and put by it, shared to flush the sink.
+#[derive(Debug, Default)]
+struct Progress {
+    sent: u64,
+    put: u64,
+    error: Option<BeanstalkcError>,
+    flushing: Option<Waker>,
+}
+
+impl Progress {
+    /// Record the outcome of a put, waking a pending flush.
+    fn put(&mut self, put: BeanstalkcResult<u64>) {
+        match put {
+            Ok(_) => self.put += 1,
+            Err(err) => self.error = Some(err),
+        }
+        if let Some(waker) = self.flushing.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl PutSink {
+    /// Spawn the task putting jobs through `conn`, into the tube it uses, buffering up to
+    /// `buffer` requests. Must be called from within a tokio runtime.
+    pub fn new(mut conn: Beanstalkc, buffer: usize) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<PutRequest>(buffer);
+        let progress = Arc::new(Mutex::new(Progress::default()));
+        let putting = Arc::clone(&progress);
+        let task = tokio::spawn(async move {
+            while let Some(request) = receiver.next().await {
+                let put = conn.put_request(&request).await;
+                let failed = put.is_err();
+                putting.lock().unwrap().put(put);
+                if failed {
+                    return;
+                }
+            }
+            conn.close().await;
+        });
+
+        PutSink {
+            sender,
+            progress,
+            task: Some(task),
+        }
+    }
+
+    /// Return the error which stopped the background task.
+    fn failure(&self) -> BeanstalkcError {
+        self.progress
+            .lock()
+            .unwrap()
+            .error
+            .clone()
+            .unwrap_or_else(|| BeanstalkcError::ConnectionError("put sink is closed".to_string()))
+    }
+}
+
+impl Sink<PutRequest> for PutSink {
+    type Error = BeanstalkcError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<BeanstalkcResult<()>> {
+        let ready = ready!(self.sender.poll_ready(cx));
+        Poll::Ready(ready.map_err(|_| self.failure()))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, request: PutRequest) -> BeanstalkcResult<()> {
+        let sent = self.sender.start_send(request);
+        if sent.is_ok() {
+            self.progress.lock().unwrap().sent += 1;
+        }
+        sent.map_err(|_| self.failure())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<BeanstalkcResult<()>> {
+        let mut progress = self.progress.lock().unwrap();
+        if let Some(err) = &progress.error {
+            return Poll::Ready(Err(err.clone()));
+        }
+        if progress.put == progress.sent {
+            return Poll::Ready(Ok(()));
+        }
+        progress.flushing = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<BeanstalkcResult<()>> {
+        self.sender.close_channel();
+        if let Some(task) = self.task.as_mut() {
+            let joined = ready!(Pin::new(task).poll(cx));
+            self.task = None;
+            if let Err(err) = joined {
+                return Poll::Ready(Err(BeanstalkcError::ConnectionError(err.to_string())));
+            }
+        }
+        match self.progress.lock().unwrap().error.clone() {
+            Some(err) => Poll::Ready(Err(err)),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+    use futures::SinkExt;
+
+    #[tokio::test]
+    async fn test_flush() {
+        let server = FakeServer::start().await;
+        let conn = server.client().connect().await.unwrap();
+        let mut sink = PutSink::new(conn, 8);
+        for body in ["a", "b", "c"] {
+            sink.feed(PutRequest::new(body.as_bytes().to_vec()))
+                .await
+                .unwrap();
+        }
+        sink.flush().await.unwrap();
+        assert_eq!(3, server.jobs_in("ready").len());
+
+        server.reply("put 2147483648 0 120 1", "DRAINING");
+        sink.feed(PutRequest::new(b"d".to_vec())).await.unwrap();
+        assert!(sink.flush().await.is_err());
+    }
+}
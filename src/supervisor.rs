@@ -0,0 +1,15 @@
+/// `ConnectionState` is the state of the connection of a client, observed with
+/// [`Beanstalkc::watch_state`](crate::Beanstalkc::watch_state).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectionState {
+    /// Not connected yet, or closed by the client.
+    #[default]
+    Disconnected,
+    /// Connected to the server.
+    Connected,
+    /// Replacing a lost connection, with the attempt number starting at 1.
+    Reconnecting { attempt: u32 },
+    /// Every reconnection attempt allowed by the supervisor failed. The next command tries
+    /// again.
+    Failed,
+}
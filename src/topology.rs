@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use crate::config::RETRY_TUBE_INFIX;
+use crate::dead_letter::DeadLetterAction;
+use crate::tube_set::TubeSet;
+
+/// `RetryTopology` describes a family of retry tubes around a base tube, one per step of a
+/// backoff schedule: with a base tube `jobs` and a schedule of 1 and 10 minutes, failed
+/// jobs go from `jobs` to `jobs.retry.1m` after 1 minute, then to `jobs.retry.10m` after
+/// 10 minutes. Once the schedule is exhausted, jobs are buried, or handled by the
+/// configured dead-letter action.
+///
+/// Install a topology with [`Beanstalkc::retry_topology`](crate::Beanstalkc::retry_topology)
+/// to watch the whole family and route jobs failed through [`Job::fail`](crate::Job::fail).
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use beanstalkc::RetryTopology;
+///
+/// let topology = RetryTopology::new(
+///     "jobs",
+///     &[Duration::from_secs(60), Duration::from_secs(600)],
+/// );
+///
+/// assert_eq!(
+///     vec!["jobs", "jobs.retry.1m", "jobs.retry.10m"],
+///     topology.tubes().iter().collect::<Vec<_>>()
+/// );
+/// assert_eq!(
+///     Some(("jobs.retry.10m", Duration::from_secs(600))),
+///     topology.next_retry("jobs.retry.1m")
+/// );
+/// assert_eq!(None, topology.next_retry("jobs.retry.10m"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryTopology {
+    base: String,
+    retries: Vec<(String, Duration)>,
+    exhausted: DeadLetterAction,
+}
+
+impl RetryTopology {
+    /// Create the retry tubes of `base` for the delays of `backoff`, in order. Each tube is
+    /// named after its delay, with the attempt number appended to the delays appearing
+    /// more than once, e.g. `jobs.retry.1m.2`.
+    pub fn new(base: &str, backoff: &[Duration]) -> Self {
+        let mut retries: Vec<(String, Duration)> = vec![];
+        for (attempt, delay) in backoff.iter().enumerate() {
+            let mut tube = format!("{}{}{}", base, RETRY_TUBE_INFIX, label(*delay));
+            if retries.iter().any(|(name, _)| *name == tube) {
+                tube = format!("{}.{}", tube, attempt + 1);
+            }
+            retries.push((tube, *delay));
+        }
+        RetryTopology {
+            base: base.to_string(),
+            retries,
+            exhausted: DeadLetterAction::Bury,
+        }
+    }
+
+    /// Change what happens to jobs failing in the last retry tube. Default action is to
+    /// bury them in that tube.
+    pub fn exhausted(mut self, action: DeadLetterAction) -> Self {
+        self.exhausted = action;
+        self
+    }
+
+    /// Return the base tube.
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// Return the base tube followed by the retry tubes, in schedule order.
+    pub fn tubes(&self) -> TubeSet {
+        std::iter::once(self.base.as_str())
+            .chain(self.retries.iter().map(|(tube, _)| tube.as_str()))
+            .collect()
+    }
+
+    /// Return whether `tube` is the base tube or one of its retry tubes.
+    pub fn contains(&self, tube: &str) -> bool {
+        tube == self.base || self.retries.iter().any(|(name, _)| name == tube)
+    }
+
+    /// Return the tube and delay a job failed in `tube` should be retried with, or `None`
+    /// if `tube` is the last retry tube or is outside of the family.
+    pub fn next_retry(&self, tube: &str) -> Option<(&str, Duration)> {
+        let next = if tube == self.base {
+            0
+        } else {
+            self.retries.iter().position(|(name, _)| name == tube)? + 1
+        };
+        self.retries
+            .get(next)
+            .map(|(name, delay)| (name.as_str(), *delay))
+    }
+
+    /// Return the action applied to jobs failing in the last retry tube.
+    pub(crate) fn exhausted_action(&self) -> &DeadLetterAction {
+        &self.exhausted
+    }
+}
+
+/// Return `delay` in the largest unit dividing it, e.g. `90s`, `10m` or `1d`.
+fn label(delay: Duration) -> String {
+    let secs = delay.as_secs();
+    let unit = [(86_400, "d"), (3_600, "h"), (60, "m")]
+        .iter()
+        .find(|(size, _)| secs > 0 && secs % size == 0);
+    match unit {
+        Some((size, unit)) => format!("{}{}", secs / size, unit),
+        None => format!("{}s", secs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label() {
+        assert_eq!("0s", label(Duration::from_millis(500)));
+        assert_eq!("90s", label(Duration::from_secs(90)));
+        assert_eq!("10m", label(Duration::from_secs(600)));
+        assert_eq!("2h", label(Duration::from_secs(7_200)));
+        assert_eq!("1d", label(Duration::from_secs(86_400)));
+    }
+
+    #[test]
+    fn test_next_retry() {
+        let minute = Duration::from_secs(60);
+        let topology = RetryTopology::new("jobs", &[minute, minute, 10 * minute]);
+        assert_eq!(
+            vec!["jobs", "jobs.retry.1m", "jobs.retry.1m.2", "jobs.retry.10m"],
+            topology.tubes().iter().collect::<Vec<_>>()
+        );
+        assert_eq!(Some(("jobs.retry.1m", minute)), topology.next_retry("jobs"));
+        assert_eq!(
+            Some(("jobs.retry.1m.2", minute)),
+            topology.next_retry("jobs.retry.1m")
+        );
+        assert_eq!(None, topology.next_retry("jobs.retry.10m"));
+        assert_eq!(None, topology.next_retry("other"));
+        assert!(topology.contains("jobs.retry.10m"));
+        assert!(!topology.contains("other"));
+
+        let topology = RetryTopology::new("jobs", &[]);
+        assert!(topology.contains("jobs"));
+        assert_eq!(None, topology.next_retry("jobs"));
+    }
+}
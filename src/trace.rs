@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::{global, Context};
+
+use crate::envelope::Envelope;
+use crate::error::BeanstalkcResult;
+use crate::job::Job;
+use crate::Beanstalkc;
+
+impl Injector for Envelope {
+    fn set(&mut self, key: &str, value: String) {
+        self.set_header(key, &value);
+    }
+}
+
+impl Extractor for Envelope {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.get_header(key)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.headers().map(|(name, _)| name).collect()
+    }
+}
+
+/// `TracedJob` is a reserved job whose body was put by
+/// [`Beanstalkc::put_traced`], together with the trace context of the producer.
+///
+/// The trace context travels in the headers of an [`Envelope`] wrapping the job body,
+/// written and read with the globally registered OpenTelemetry text map propagator. Jobs
+/// without an envelope get an empty context.
+#[derive(Debug)]
+pub struct TracedJob<'a> {
+    job: Job<'a>,
+    envelope: Envelope,
+    context: Context,
+}
+
+impl<'a> TracedJob<'a> {
+    /// Decode the envelope of `job` and extract its trace context.
+    pub fn new(job: Job<'a>) -> BeanstalkcResult<Self> {
+        let envelope = Envelope::decode(job.body())?;
+        let context = global::get_text_map_propagator(|propagator| propagator.extract(&envelope));
+        Ok(TracedJob {
+            job,
+            envelope,
+            context,
+        })
+    }
+
+    /// Return the job body without the envelope.
+    pub fn body(&self) -> &[u8] {
+        self.envelope.body()
+    }
+
+    /// Return the envelope of the job.
+    pub fn envelope(&self) -> &Envelope {
+        &self.envelope
+    }
+
+    /// Return the trace context of the producer, to use as the parent of the consumer
+    /// spans.
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Return the underlying job, to finalize it.
+    pub fn job(&mut self) -> &mut Job<'a> {
+        &mut self.job
+    }
+
+    /// Return the underlying job.
+    pub fn into_job(self) -> Job<'a> {
+        self.job
+    }
+}
+
+impl Beanstalkc {
+    /// Put a job into the current tube, wrapping the body into an [`Envelope`] carrying the
+    /// current trace context. Return the job id.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::Beanstalkc;
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let job_id = conn
+    ///     .put_traced(b"Rust", 0, Duration::from_secs(0), Duration::from_secs(10))
+    ///     .await
+    ///     .unwrap();
+    /// }
+    /// ```
+    pub async fn put_traced(
+        &mut self,
+        body: &[u8],
+        priority: u32,
+        delay: Duration,
+        ttr: Duration,
+    ) -> BeanstalkcResult<u64> {
+        let mut envelope = Envelope::new(body.to_vec());
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&Context::current(), &mut envelope)
+        });
+        self.put(&envelope.encode(), priority, delay, ttr).await
+    }
+
+    /// Reserve a job and extract the trace context put along with it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::Beanstalkc;
+    /// use opentelemetry::global;
+    /// use opentelemetry::trace::{Span, Tracer};
+    ///
+    /// let mut conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut job = conn.reserve_traced().await.unwrap();
+    /// let mut span = global::tracer("worker").start_with_context("process", job.context());
+    /// // Execute job...
+    /// job.job().delete().await.unwrap();
+    /// span.end();
+    /// }
+    /// ```
+    pub async fn reserve_traced(&mut self) -> BeanstalkcResult<TracedJob<'_>> {
+        TracedJob::new(self.reserve().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::propagation::text_map_propagator::FieldIter;
+    use opentelemetry::propagation::TextMapPropagator;
+
+    use super::*;
+    use crate::fake::FakeServer;
+
+    const HEADER: &str = "test-id";
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct TestId(String);
+
+    /// Propagate a [`TestId`] context value in a single header.
+    #[derive(Debug)]
+    struct TestPropagator {
+        fields: Vec<String>,
+    }
+
+    impl TextMapPropagator for TestPropagator {
+        fn inject_context(&self, cx: &Context, injector: &mut dyn Injector) {
+            if let Some(TestId(id)) = cx.get::<TestId>() {
+                injector.set(HEADER, id.clone());
+            }
+        }
+
+        fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
+            match extractor.get(HEADER) {
+                Some(id) => cx.with_value(TestId(id.to_string())),
+                None => cx.clone(),
+            }
+        }
+
+        fn fields(&self) -> FieldIter<'_> {
+            FieldIter::new(&self.fields)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trace_context_round_trip() {
+        global::set_text_map_propagator(TestPropagator {
+            fields: vec![HEADER.to_string()],
+        });
+        let server = FakeServer::start().await;
+        let mut conn = server.client().connect().await.unwrap();
+
+        {
+            let _guard = Context::current_with_value(TestId("producer".to_string())).attach();
+            conn.put_traced(
+                b"traced",
+                0,
+                Duration::from_secs(0),
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        }
+
+        let job = conn.reserve_traced().await.unwrap();
+        assert_eq!(b"traced", job.body());
+        assert_eq!(Some("producer"), job.envelope().get_header(HEADER));
+        assert_eq!(
+            Some(&TestId("producer".to_string())),
+            job.context().get::<TestId>()
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::error::BeanstalkcResult;
+use crate::job::Job;
+use crate::Beanstalkc;
+
+/// `Tube` runs commands scoped to one tube, returned by [`Beanstalkc::tube`].
+///
+/// Commands acting on the used tube switch the connection to this tube first, unless it
+/// already uses it, and leave it used afterwards.
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use std::time::Duration;
+/// use beanstalkc::Beanstalkc;
+///
+/// let mut conn = Beanstalkc::new().connect().await.unwrap();
+///
+/// let mut emails = conn.tube("emails");
+/// emails.put_default(b"hello").await.unwrap();
+/// emails.kick(100).await.unwrap();
+/// dbg!(emails.stats().await.unwrap());
+/// emails.pause(Duration::from_secs(60)).await.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Tube<'a> {
+    conn: &'a mut Beanstalkc,
+    name: String,
+}
+
+impl<'a> Tube<'a> {
+    pub(crate) fn new(conn: &'a mut Beanstalkc, name: &str) -> Tube<'a> {
+        Tube {
+            conn,
+            name: name.to_string(),
+        }
+    }
+
+    /// Return the tube name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Put a job into the tube with the default priority, delay and TTR configured on the
+    /// connection. Return the job id.
+    pub async fn put_default(&mut self, body: &[u8]) -> BeanstalkcResult<u64> {
+        let (priority, delay, ttr) = self.conn.put_defaults();
+        self.put(body, priority, delay, ttr).await
+    }
+
+    /// Put a job into the tube. Return the job id.
+    pub async fn put(
+        &mut self,
+        body: &[u8],
+        priority: u32,
+        delay: Duration,
+        ttr: Duration,
+    ) -> BeanstalkcResult<u64> {
+        self.select().await?;
+        self.conn.put(body, priority, delay, ttr).await
+    }
+
+    /// Return the next ready job of the tube.
+    pub async fn peek_ready(&mut self) -> BeanstalkcResult<Job<'_>> {
+        self.select().await?;
+        self.conn.peek_ready().await
+    }
+
+    /// Return the delayed job of the tube with the shortest delay left.
+    pub async fn peek_delayed(&mut self) -> BeanstalkcResult<Job<'_>> {
+        self.select().await?;
+        self.conn.peek_delayed().await
+    }
+
+    /// Return the next buried job of the tube.
+    pub async fn peek_buried(&mut self) -> BeanstalkcResult<Job<'_>> {
+        self.select().await?;
+        self.conn.peek_buried().await
+    }
+
+    /// Return the next ready job of the tube, or `None` if there is no ready job.
+    pub async fn peek_ready_opt(&mut self) -> BeanstalkcResult<Option<Job<'_>>> {
+        self.select().await?;
+        self.conn.peek_ready_opt().await
+    }
+
+    /// Return the delayed job of the tube with the shortest delay left, or `None` if there
+    /// is no delayed job.
+    pub async fn peek_delayed_opt(&mut self) -> BeanstalkcResult<Option<Job<'_>>> {
+        self.select().await?;
+        self.conn.peek_delayed_opt().await
+    }
+
+    /// Return the next buried job of the tube, or `None` if there is no buried job.
+    pub async fn peek_buried_opt(&mut self) -> BeanstalkcResult<Option<Job<'_>>> {
+        self.select().await?;
+        self.conn.peek_buried_opt().await
+    }
+
+    /// Kick at most `bound` jobs of the tube into the ready queue. Return the number of
+    /// jobs kicked.
+    pub async fn kick(&mut self, bound: u32) -> BeanstalkcResult<u64> {
+        self.select().await?;
+        self.conn.kick(bound).await
+    }
+
+    /// Return a dict of statistical information about the tube.
+    pub async fn stats(&mut self) -> BeanstalkcResult<HashMap<String, String>> {
+        self.conn.stats_tube(&self.name).await
+    }
+
+    /// Pause the tube for `delay` time.
+    pub async fn pause(&mut self, delay: Duration) -> BeanstalkcResult<()> {
+        self.conn.pause_tube(&self.name, delay).await
+    }
+
+    /// Resume the tube.
+    pub async fn resume(&mut self) -> BeanstalkcResult<()> {
+        self.conn.resume_tube(&self.name).await
+    }
+
+    /// Use the tube unless the connection already does.
+    async fn select(&mut self) -> BeanstalkcResult<()> {
+        if self.conn.current_tube() != self.name {
+            self.conn.use_tube(&self.name).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use std::iter::FromIterator;
+
+/// `TubeSet` is a set of tube names, as returned by [`Beanstalkc::tubes`](crate::Beanstalkc::tubes)
+/// and [`Beanstalkc::watching`](crate::Beanstalkc::watching).
+///
+/// Tubes keep the order in which they were first inserted, and comparing sets ignores it.
+///
+/// # Example
+///
+/// ```
+/// use beanstalkc::TubeSet;
+///
+/// let watching: TubeSet = vec!["default", "emails"].into_iter().collect();
+/// let desired: TubeSet = vec!["emails", "reports"].into_iter().collect();
+///
+/// assert!(watching.contains("emails"));
+/// assert_eq!(vec!["reports"], desired.difference(&watching).collect::<Vec<_>>());
+/// assert_eq!(vec!["default"], watching.difference(&desired).collect::<Vec<_>>());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TubeSet {
+    tubes: Vec<String>,
+}
+
+impl TubeSet {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        TubeSet::default()
+    }
+
+    /// Return whether the set contains `tube`.
+    pub fn contains(&self, tube: &str) -> bool {
+        self.tubes.iter().any(|t| t == tube)
+    }
+
+    /// Add `tube` to the set. Return whether it was not in the set yet.
+    pub fn insert(&mut self, tube: &str) -> bool {
+        if self.contains(tube) {
+            return false;
+        }
+        self.tubes.push(tube.to_string());
+        true
+    }
+
+    /// Remove `tube` from the set. Return whether it was in the set.
+    pub fn remove(&mut self, tube: &str) -> bool {
+        let len = self.tubes.len();
+        self.tubes.retain(|t| t != tube);
+        self.tubes.len() != len
+    }
+
+    /// Return the number of tubes in the set.
+    pub fn len(&self) -> usize {
+        self.tubes.len()
+    }
+
+    /// Return whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.tubes.is_empty()
+    }
+
+    /// Return the tubes in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.tubes.iter().map(String::as_str)
+    }
+
+    /// Return the tubes of this set which are not in `other`, e.g. the tubes to watch to
+    /// go from the watched tubes `other` to the desired tubes `self`.
+    pub fn difference<'a>(&'a self, other: &'a TubeSet) -> impl Iterator<Item = &'a str> {
+        self.iter().filter(move |tube| !other.contains(tube))
+    }
+
+    /// Consume the set and return the tubes in insertion order.
+    pub fn into_vec(self) -> Vec<String> {
+        self.tubes
+    }
+}
+
+impl PartialEq for TubeSet {
+    fn eq(&self, other: &TubeSet) -> bool {
+        self.len() == other.len() && self.iter().all(|tube| other.contains(tube))
+    }
+}
+
+impl Eq for TubeSet {}
+
+impl From<Vec<String>> for TubeSet {
+    fn from(tubes: Vec<String>) -> Self {
+        tubes.into_iter().collect()
+    }
+}
+
+impl From<TubeSet> for Vec<String> {
+    fn from(set: TubeSet) -> Self {
+        set.into_vec()
+    }
+}
+
+impl FromIterator<String> for TubeSet {
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        let mut set = TubeSet::new();
+        for tube in iter {
+            if !set.contains(&tube) {
+                set.tubes.push(tube);
+            }
+        }
+        set
+    }
+}
+
+impl<'a> FromIterator<&'a str> for TubeSet {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        iter.into_iter().map(str::to_string).collect()
+    }
+}
+
+impl IntoIterator for TubeSet {
+    type Item = String;
+    type IntoIter = std::vec::IntoIter<String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tubes.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tube_set() {
+        let mut set: TubeSet = vec!["b", "a", "b"].into_iter().collect();
+        assert_eq!(vec!["b", "a"], set.iter().collect::<Vec<_>>());
+        assert!(!set.insert("a"));
+        assert!(set.insert("c"));
+        assert!(set.remove("b"));
+        assert!(!set.remove("b"));
+        assert_eq!(
+            vec!["a".to_string(), "c".to_string()],
+            set.clone().into_vec()
+        );
+
+        let other: TubeSet = vec!["c", "a"].into_iter().collect();
+        assert_eq!(set, other);
+        assert_eq!(0, set.difference(&other).count());
+        assert_ne!(set, TubeSet::from(vec!["a".to_string()]));
+    }
+}
@@ -0,0 +1,106 @@
+/// Tracing and log target of wire-level protocol logs.
+#[cfg_attr(not(any(feature = "tracing", feature = "log")), allow(dead_code))]
+pub(crate) const TARGET: &str = "beanstalkc::wire";
+
+/// Maximum number of body bytes included in wire-level logs.
+const PREVIEW_LEN: usize = 32;
+
+/// Log an outbound command line, with the size and a preview of its body if any.
+pub(crate) fn outbound(message: &[u8]) {
+    emit(&outbound_line(message));
+}
+
+/// Log an inbound response line, with the size and a preview of its body if any.
+pub(crate) fn inbound(line: &str, body: Option<&[u8]>) {
+    emit(&inbound_line(line, body));
+}
+
+/// Emit a wire-level log line through `tracing`, or through `log` without the `tracing`
+/// feature.
+fn emit(line: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(target: TARGET, "{}", line);
+    #[cfg(not(feature = "tracing"))]
+    crate::logging::wire(line);
+}
+
+fn outbound_line(message: &[u8]) -> String {
+    let (line, body) = split_frame(message);
+    format!(
+        "> {} ({} bytes){}",
+        String::from_utf8_lossy(line),
+        message.len(),
+        body.map(|b| format!(" body: {}", preview(b)))
+            .unwrap_or_default()
+    )
+}
+
+fn inbound_line(line: &str, body: Option<&[u8]>) -> String {
+    format!(
+        "< {} ({} bytes){}",
+        line.trim_end(),
+        line.len() + body.map_or(0, |b| b.len() + 2),
+        body.map(|b| format!(" body: {}", preview(b)))
+            .unwrap_or_default()
+    )
+}
+
+/// Split a command into its line and its body, without line breaks.
+fn split_frame(message: &[u8]) -> (&[u8], Option<&[u8]>) {
+    let end = message
+        .windows(2)
+        .position(|w| w == b"\r\n")
+        .unwrap_or(message.len());
+    let body = message
+        .get(end + 2..)
+        .filter(|rest| !rest.is_empty())
+        .map(|rest| rest.strip_suffix(b"\r\n").unwrap_or(rest));
+    (&message[..end], body)
+}
+
+/// Render the beginning of a body with non-printable bytes escaped, followed by `...` if
+/// it is cut.
+pub(crate) fn preview(body: &[u8]) -> String {
+    let shown = &body[..body.len().min(PREVIEW_LEN)];
+    let mut s = format!("\"{}\"", shown.escape_ascii());
+    if shown.len() < body.len() {
+        s.push_str("...");
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_frame() {
+        let (line, body) = split_frame(b"put 0 0 10 4\r\nRust\r\n");
+        assert_eq!(b"put 0 0 10 4", line);
+        assert_eq!(Some(&b"Rust"[..]), body);
+
+        let (line, body) = split_frame(b"reserve\r\n");
+        assert_eq!(b"reserve", line);
+        assert_eq!(None, body);
+    }
+
+    #[test]
+    fn test_lines() {
+        assert_eq!(
+            "> put 0 0 10 4 (20 bytes) body: \"Rust\"",
+            outbound_line(b"put 0 0 10 4\r\nRust\r\n")
+        );
+        assert_eq!("> reserve (9 bytes)", outbound_line(b"reserve\r\n"));
+        assert_eq!(
+            "< RESERVED 1 4 (20 bytes) body: \"Rust\"",
+            inbound_line("RESERVED 1 4\r\n", Some(b"Rust"))
+        );
+        assert_eq!("< DELETED (9 bytes)", inbound_line("DELETED\r\n", None));
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!("\"a\\x00\\n\"", preview(b"a\x00\n"));
+        assert_eq!(format!("\"{}\"...", "x".repeat(32)), preview(&[b'x'; 40]));
+    }
+}
@@ -0,0 +1,959 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{watch, Semaphore};
+use tokio::task::{Id, JoinError, JoinSet};
+use tokio::time::Instant;
+
+use crate::command::Status;
+use crate::config::{DEFAULT_WORKER_DRAIN_TIMEOUT, DEFAULT_WORKER_POLL_INTERVAL};
+use crate::error::{BeanstalkcError, BeanstalkcResult};
+use crate::job::Job;
+use crate::logging;
+use crate::process::{panic_message, Outcome, ReservedJob, TimeoutAction};
+use crate::tube_set::TubeSet;
+use crate::Beanstalkc;
+
+/// Jobs whose handler is running, by task: job id, body, and deadline of the handler if it
+/// has a TTR timeout.
+type InFlight = HashMap<Id, (u64, Vec<u8>, Option<Instant>)>;
+
+/// `Worker` runs a job handler over the jobs reserved from a set of tubes.
+///
+/// Handlers run as tokio tasks, so several jobs can be in flight at once, while the worker
+/// keeps ownership of the connection and finalizes each job from its handler result just
+/// like [`Job::run`](crate::Job::run).
+///
+/// # Example
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use beanstalkc::{Beanstalkc, Outcome, Worker};
+///
+/// let conn = Beanstalkc::new().connect().await.unwrap();
+///
+/// let mut worker = Worker::new(conn)
+///     .concurrency(8)
+///     .tube_concurrency("emails", 4)
+///     .tube_concurrency("reports", 1);
+///
+/// worker
+///     .run(|job| async move {
+///         // Execute job...
+///         dbg!(job.id());
+///         Ok::<_, String>(Outcome::Delete)
+///     })
+///     .await
+///     .unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Worker {
+    conn: Beanstalkc,
+    tubes: Vec<String>,
+    concurrency: usize,
+    tube_concurrency: HashMap<String, usize>,
+    tube_weights: HashMap<String, u32>,
+    fair: bool,
+    ttr_timeout: Option<(Duration, TimeoutAction)>,
+    poll_interval: Duration,
+    drain_timeout: Duration,
+    drain: Arc<watch::Sender<bool>>,
+}
+
+impl Worker {
+    /// Create a new `Worker` on a connected client. The worker processes one job at a time
+    /// from the tubes the connection is watching unless configured otherwise.
+    pub fn new(conn: Beanstalkc) -> Self {
+        Worker {
+            conn,
+            tubes: vec![],
+            concurrency: 1,
+            tube_concurrency: HashMap::new(),
+            tube_weights: HashMap::new(),
+            fair: false,
+            ttr_timeout: None,
+            poll_interval: DEFAULT_WORKER_POLL_INTERVAL,
+            drain_timeout: DEFAULT_WORKER_DRAIN_TIMEOUT,
+            drain: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    /// Watch a specific tube. Once any tube is configured, the worker ignores the tubes
+    /// which are not configured.
+    pub fn watch(mut self, tube: &str) -> Self {
+        if !self.tubes.iter().any(|t| t == tube) {
+            self.tubes.push(tube.to_string());
+        }
+        self
+    }
+
+    /// Set the maximum number of jobs in flight across all tubes. Default concurrency is `1`.
+    pub fn concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = limit.max(1);
+        self
+    }
+
+    /// Watch a specific tube and limit the number of its jobs in flight, so one heavy tube
+    /// cannot starve the others or overload downstream services. The worker stops watching
+    /// a tube while it is at its limit, so its jobs stay ready rather than being reserved
+    /// and released again.
+    pub fn tube_concurrency(mut self, tube: &str, limit: usize) -> Self {
+        self.tube_concurrency.insert(tube.to_string(), limit.max(1));
+        self.watch(tube)
+    }
+
+    /// Watch a specific tube with a weight. Once any weight is configured, the worker
+    /// switches to weighted mode: before each reserve it picks a tube by smooth weighted
+    /// round-robin and tries it first, then the other tubes by descending weight, so
+    /// high-priority tubes are drained preferentially while low-priority tubes still make
+    /// progress. Tubes without a weight have weight `1`.
+    ///
+    /// Weighted mode costs extra `watch`/`ignore` round trips per reserved job.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, Worker};
+    ///
+    /// let conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut worker = Worker::new(conn)
+    ///     .tube_weight("critical", 10)
+    ///     .tube_weight("bulk", 1);
+    /// }
+    /// ```
+    pub fn tube_weight(mut self, tube: &str, weight: u32) -> Self {
+        self.tube_weights.insert(tube.to_string(), weight.max(1));
+        self.watch(tube)
+    }
+
+    /// Reserve from one watched tube at a time, rotating through the tubes in turn, since
+    /// beanstalkd makes no fairness guarantee when reserving from several tubes: a busy
+    /// tube with urgent jobs can starve the others. Before each reserve, the worker tries
+    /// every tube without blocking, starting from the tube after the one which served the
+    /// last job, so tubes with very different volumes are served alternately. When they are
+    /// all empty it waits on every tube at once. Disabled by default, and ignored once tube
+    /// weights are configured.
+    ///
+    /// Fairness mode costs extra `watch`/`ignore` round trips per reserved job.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, Worker};
+    ///
+    /// let conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut worker = Worker::new(conn)
+    ///     .watch("emails")
+    ///     .watch("reports")
+    ///     .fair(true);
+    /// }
+    /// ```
+    pub fn fair(mut self, enabled: bool) -> Self {
+        self.fair = enabled;
+        self
+    }
+
+    /// Stop each handler `margin` before the TTR of its job expires, and finalize the job
+    /// according to `action`, instead of letting beanstalkd hand the job to another worker
+    /// while the handler keeps running. The time left is read from the job stats once it is
+    /// reserved, with a precision of one second, or taken from the default TTR of the
+    /// connection if the stats cannot be read. Reserves are shortened so that a timed out
+    /// job is finalized within a second of its deadline. Disabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::{Beanstalkc, TimeoutAction, Worker};
+    ///
+    /// let conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut worker = Worker::new(conn).ttr_timeout(Duration::from_secs(2), TimeoutAction::Bury);
+    /// }
+    /// ```
+    pub fn ttr_timeout(mut self, margin: Duration, action: TimeoutAction) -> Self {
+        self.ttr_timeout = Some((margin, action));
+        self
+    }
+
+    /// Set the timeout of each reserve, which bounds how long a finished job waits before it
+    /// is finalized. Default poll interval is `1s`.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Set how long a draining worker waits for in-flight jobs before it releases them.
+    /// Default drain timeout is `30s`.
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Return a handle which asks this worker to drain, see [`DrainHandle::drain`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use std::time::Duration;
+    /// use beanstalkc::{Beanstalkc, Outcome, Worker};
+    ///
+    /// let conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// let mut worker = Worker::new(conn).drain_timeout(Duration::from_secs(25));
+    /// let drain = worker.drain_handle();
+    /// tokio::spawn(async move {
+    ///     tokio::signal::ctrl_c().await.unwrap();
+    ///     drain.drain();
+    /// });
+    ///
+    /// worker
+    ///     .run(|_job| async { Ok::<_, String>(Outcome::Delete) })
+    ///     .await
+    ///     .unwrap();
+    /// }
+    /// ```
+    pub fn drain_handle(&self) -> DrainHandle {
+        DrainHandle {
+            sender: self.drain.clone(),
+        }
+    }
+
+    /// Reserve jobs and run `handler` against them until the worker is drained or a
+    /// connection error occurs.
+    ///
+    /// Handler results are applied as in [`Job::run`](crate::Job::run); a panicking handler
+    /// is finalized according to the configured [`PanicAction`](crate::PanicAction), and
+    /// its panic message is logged with the `log` feature.
+    ///
+    /// With the `metrics` feature, the worker records histograms in seconds of how long
+    /// each job waited between its reservation and the start of its handler
+    /// (`beanstalkc_job_start_delay_seconds`), how long the handler ran
+    /// (`beanstalkc_job_run_seconds`), and, for jobs put with an `enqueued-at` header, how
+    /// long the job was queued (`beanstalkc_job_queue_seconds`).
+    pub async fn run<F, Fut, E>(&mut self, handler: F) -> BeanstalkcResult<()>
+    where
+        F: Fn(ReservedJob) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Outcome, E>> + Send + 'static,
+        E: Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let global = Arc::new(Semaphore::new(self.concurrency));
+        let semaphores: HashMap<String, Arc<Semaphore>> = self
+            .tube_concurrency
+            .iter()
+            .map(|(tube, limit)| (tube.clone(), Arc::new(Semaphore::new(*limit))))
+            .collect();
+
+        let mut draining = self.drain.subscribe();
+        let mut watched = self.init_watched().await?;
+        let mut picker = WeightedPicker::new(&self.tube_weights);
+        let mut round_robin = RoundRobin::default();
+        let mut tasks = JoinSet::new();
+        let mut in_flight = InFlight::new();
+
+        loop {
+            while let Some(done) = tasks.try_join_next_with_id() {
+                self.complete(&mut in_flight, done).await?;
+            }
+
+            if *draining.borrow() {
+                return self.shutdown(&mut tasks, &mut in_flight).await;
+            }
+
+            let available: Vec<String> = self
+                .tubes
+                .iter()
+                .filter(|t| {
+                    semaphores
+                        .get(*t)
+                        .map_or(true, |s| s.available_permits() > 0)
+                })
+                .cloned()
+                .collect();
+            if global.available_permits() == 0 || available.is_empty() {
+                tokio::select! {
+                    Some(done) = tasks.join_next_with_id() => {
+                        self.complete(&mut in_flight, done).await?;
+                    }
+                    _ = draining.changed() => {}
+                }
+                continue;
+            }
+
+            let wait = self.reserve_timeout(&in_flight);
+            let reserved = self
+                .reserve_next(
+                    &mut watched,
+                    &available,
+                    &mut picker,
+                    &mut round_robin,
+                    wait,
+                )
+                .await;
+            let reserved = match reserved {
+                Err(err) if err.is_job_error() => {
+                    logging::job_skipped(&err);
+                    continue;
+                }
+                Err(err) => return Err(err),
+                Ok(reserved) => reserved,
+            };
+            let (id, body, tube) = match reserved {
+                Ok(job) => job,
+                Err(Status::DeadlineSoon) => {
+                    // Give in-flight handlers a chance to finish before reserving again.
+                    let wait = tokio::time::timeout(self.poll_interval, tasks.join_next_with_id());
+                    if let Ok(Some(done)) = wait.await {
+                        self.complete(&mut in_flight, done).await?;
+                    }
+                    continue;
+                }
+                Err(_) => continue,
+            };
+
+            // The tube of the job, unless it was reserved from a single tube, and the time
+            // left before its TTR come from a single stats-job round trip, when needed.
+            let stats = if (tube.is_none() && !semaphores.is_empty()) || self.ttr_timeout.is_some()
+            {
+                self.conn.stats_job(id).await.ok()
+            } else {
+                None
+            };
+            let tube = tube.or_else(|| {
+                let tube = stats.as_ref()?.get("tube")?;
+                Some(self.conn.strip_namespace(tube).to_string())
+            });
+            // Reserves only watch the tubes with free permits, so the permit is available
+            // at once and jobs are never released for lack of one.
+            let tube_permit = match tube.and_then(|tube| semaphores.get(&tube)) {
+                Some(semaphore) => Some(semaphore.clone().acquire_owned().await.unwrap()),
+                None => None,
+            };
+            let permit = global.clone().acquire_owned().await.unwrap();
+
+            let job = ReservedJob::new(id, body.clone());
+            #[cfg(feature = "metrics")]
+            if let Some(latency) = job.queue_latency() {
+                record_latency("beanstalkc_job_queue_seconds", latency);
+            }
+            let handler = handler.clone();
+            let reserved_at = Instant::now();
+            let timeout = self.handler_timeout(stats.as_ref());
+            let deadline = timeout.map(|(limit, _)| reserved_at + limit);
+            let (_, release_delay) = self.conn.release_defaults();
+            let task = tasks.spawn(async move {
+                let _permits = (permit, tube_permit);
+                record_latency("beanstalkc_job_start_delay_seconds", reserved_at.elapsed());
+                let started_at = Instant::now();
+                let result = match timeout {
+                    Some((limit, action)) => tokio::time::timeout(limit, handler(job))
+                        .await
+                        .unwrap_or_else(|_| Ok(action.outcome(release_delay))),
+                    None => handler(job).await,
+                };
+                record_latency("beanstalkc_job_run_seconds", started_at.elapsed());
+                result
+            });
+            in_flight.insert(task.id(), (id, body, deadline));
+        }
+    }
+
+    /// Run the worker like [`Worker::run`] and drain it once `shutdown` completes, e.g. with
+    /// a `CancellationToken::cancelled()` future or a signal.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// #[tokio::main]
+    /// async fn main() {
+    /// use beanstalkc::{Beanstalkc, Outcome, Worker};
+    ///
+    /// let conn = Beanstalkc::new().connect().await.unwrap();
+    ///
+    /// Worker::new(conn)
+    ///     .run_until(
+    ///         |_job| async { Ok::<_, String>(Outcome::Delete) },
+    ///         tokio::signal::ctrl_c(),
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    /// }
+    /// ```
+    pub async fn run_until<F, Fut, E, S>(&mut self, handler: F, shutdown: S) -> BeanstalkcResult<()>
+    where
+        F: Fn(ReservedJob) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Outcome, E>> + Send + 'static,
+        E: Send + 'static,
+        S: Future,
+    {
+        let drain = self.drain_handle();
+        let run = self.run(handler);
+        tokio::pin!(run);
+
+        tokio::select! {
+            result = &mut run => return result,
+            _ = shutdown => drain.drain(),
+        }
+        run.await
+    }
+
+    /// Reserve the next job from the `available` tubes, waiting at most `wait`, returning
+    /// the status instead when the server replies with `TIMED_OUT` or `DEADLINE_SOON`. In
+    /// weighted and fairness modes the tubes are first tried one at a time without blocking.
+    /// The tube the job was reserved from is returned when a single tube was watched.
+    async fn reserve_next(
+        &mut self,
+        watched: &mut TubeSet,
+        available: &[String],
+        picker: &mut WeightedPicker,
+        round_robin: &mut RoundRobin,
+        wait: Duration,
+    ) -> BeanstalkcResult<Result<(u64, Vec<u8>, Option<String>), Status>> {
+        let order = if !self.tube_weights.is_empty() {
+            picker.order(available)
+        } else if self.fair {
+            round_robin.order(&self.tubes, available)
+        } else {
+            vec![]
+        };
+        for tube in order {
+            self.sync_watched(watched, std::slice::from_ref(&tube))
+                .await?;
+            match self
+                .conn
+                .reserve_status(Some(Duration::from_secs(0)))
+                .await?
+            {
+                Ok((id, body)) => {
+                    round_robin.served(&tube);
+                    return Ok(Ok((id, body, Some(tube))));
+                }
+                Err(Status::TimedOut) => continue,
+                Err(status) => return Ok(Err(status)),
+            }
+        }
+
+        self.sync_watched(watched, available).await?;
+        let tube = match available {
+            [tube] => Some(tube.clone()),
+            _ => None,
+        };
+        let reserved = self.conn.reserve_status(Some(wait)).await?;
+        Ok(reserved.map(|(id, body)| (id, body, tube)))
+    }
+
+    /// Return how long a reserve may wait: the poll interval, shortened to end past the
+    /// next deadline of a handler, so that its job is finalized on time. The server counts
+    /// reserve timeouts in whole seconds.
+    fn reserve_timeout(&self, in_flight: &InFlight) -> Duration {
+        let now = Instant::now();
+        let next = in_flight
+            .values()
+            .filter_map(|(_, _, deadline)| *deadline)
+            .filter(|deadline| *deadline > now)
+            .min();
+        match next {
+            Some(deadline) => {
+                let left = deadline - now;
+                let secs = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+                self.poll_interval.min(Duration::from_secs(secs))
+            }
+            None => self.poll_interval,
+        }
+    }
+
+    /// Return how long the handler of a reserved job may run, and what to do with the job
+    /// once it times out, when a TTR timeout is configured. The time left is read from the
+    /// job stats, falling back to the default TTR of the connection.
+    fn handler_timeout(
+        &self,
+        stats: Option<&HashMap<String, String>>,
+    ) -> Option<(Duration, TimeoutAction)> {
+        let (margin, action) = self.ttr_timeout?;
+        let time_left = stats
+            .and_then(|stats| stats.get("time-left")?.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| self.conn.put_defaults().2);
+        Some((time_left.saturating_sub(margin), action))
+    }
+
+    /// Apply the configured tubes to the connection and return the watched tubes.
+    async fn init_watched(&mut self) -> BeanstalkcResult<TubeSet> {
+        let current = self.conn.watching().await?;
+        if self.tubes.is_empty() {
+            self.tubes = current.clone().into_vec();
+            return Ok(current);
+        }
+
+        let desired: TubeSet = self.tubes.iter().cloned().collect();
+        for tube in desired.iter() {
+            self.conn.watch(tube).await?;
+        }
+        for tube in current.difference(&desired) {
+            self.conn.ignore(tube).await?;
+        }
+        Ok(desired)
+    }
+
+    /// Watch the `available` tubes and ignore the others. Tubes are watched before any
+    /// is ignored, so the connection never ends up watching nothing.
+    async fn sync_watched(
+        &mut self,
+        watched: &mut TubeSet,
+        available: &[String],
+    ) -> BeanstalkcResult<()> {
+        let desired: TubeSet = available.iter().cloned().collect();
+        for tube in desired.difference(watched) {
+            self.conn.watch(tube).await?;
+        }
+
+        let stale: Vec<String> = watched.difference(&desired).map(str::to_string).collect();
+        for tube in stale {
+            self.conn.ignore(&tube).await?;
+        }
+        *watched = desired;
+        Ok(())
+    }
+
+    /// Stop reserving, wait for in-flight jobs until the drain timeout, release the jobs
+    /// which are still running and close the connection.
+    async fn shutdown<E: 'static>(
+        &mut self,
+        tasks: &mut JoinSet<Result<Outcome, E>>,
+        in_flight: &mut InFlight,
+    ) -> BeanstalkcResult<()> {
+        let deadline = Instant::now() + self.drain_timeout;
+        loop {
+            match tokio::time::timeout_at(deadline, tasks.join_next_with_id()).await {
+                Ok(Some(done)) => self.complete(in_flight, done).await?,
+                Ok(None) => break,
+                Err(_) => {
+                    tasks.abort_all();
+                    while let Some(done) = tasks.join_next_with_id().await {
+                        self.complete(in_flight, done).await?;
+                    }
+                    break;
+                }
+            }
+        }
+
+        self.conn.close().await;
+        Ok(())
+    }
+
+    /// Finalize the job of a finished handler task.
+    async fn complete<E>(
+        &mut self,
+        in_flight: &mut InFlight,
+        done: Result<(Id, Result<Outcome, E>), JoinError>,
+    ) -> BeanstalkcResult<()> {
+        let task_id = match &done {
+            Ok((task_id, _)) => *task_id,
+            Err(err) => err.id(),
+        };
+        let (id, body, _) = match in_flight.remove(&task_id) {
+            Some(job) => job,
+            None => return Ok(()),
+        };
+
+        let mut job = Job::new(&mut self.conn, id, body, true);
+        let finalized = match done {
+            Ok((_, result)) => job.finalize(&result).await,
+            Err(err) if err.is_cancelled() => job.release_default().await,
+            Err(err) => {
+                if let Ok(payload) = err.try_into_panic() {
+                    logging::handler_panicked(id, &panic_message(payload.as_ref()));
+                }
+                job.finalize_panicked().await
+            }
+        };
+
+        match finalized {
+            // The job may have expired and been handed to another worker meanwhile.
+            Err(BeanstalkcError::CommandFailed(_)) => Ok(()),
+            other => other,
+        }
+    }
+}
+
+/// Record a latency of the jobs run by workers as a histogram in seconds, through the
+/// `metrics` facade when the `metrics` feature is enabled.
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+fn record_latency(name: &'static str, latency: Duration) {
+    #[cfg(feature = "metrics")]
+    metrics::histogram!(name).record(latency.as_secs_f64());
+}
+
+/// `DrainHandle` asks a running [`Worker`] to wind down gracefully.
+///
+/// A draining worker stops issuing new reserves, lets in-flight jobs finish until the drain
+/// timeout, releases the jobs which are still running, then closes its connection and
+/// returns from [`Worker::run`].
+#[derive(Debug, Clone)]
+pub struct DrainHandle {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl DrainHandle {
+    /// Begin draining the worker. The worker notices the request within its poll interval.
+    pub fn drain(&self) {
+        self.sender.send_replace(true);
+    }
+
+    /// Return whether draining has begun.
+    pub fn is_draining(&self) -> bool {
+        *self.sender.borrow()
+    }
+}
+
+/// `RoundRobin` orders tubes starting from the tube after the one which served the last job.
+#[derive(Debug, Default)]
+struct RoundRobin {
+    last: Option<String>,
+}
+
+impl RoundRobin {
+    /// Return the `available` tubes, rotated along the configured `tubes` order.
+    fn order(&self, tubes: &[String], available: &[String]) -> Vec<String> {
+        let start = self
+            .last
+            .as_ref()
+            .and_then(|last| tubes.iter().position(|t| t == last))
+            .map_or(0, |i| i + 1);
+        tubes[start..]
+            .iter()
+            .chain(&tubes[..start])
+            .filter(|t| available.contains(t))
+            .cloned()
+            .collect()
+    }
+
+    /// Record that `tube` served the last job.
+    fn served(&mut self, tube: &str) {
+        self.last = Some(tube.to_string());
+    }
+}
+
+/// `WeightedPicker` orders tubes by smooth weighted round-robin, as used by nginx upstreams.
+#[derive(Debug)]
+struct WeightedPicker {
+    weights: HashMap<String, u32>,
+    current: HashMap<String, i64>,
+}
+
+impl WeightedPicker {
+    fn new(weights: &HashMap<String, u32>) -> Self {
+        WeightedPicker {
+            weights: weights.clone(),
+            current: HashMap::new(),
+        }
+    }
+
+    fn weight(&self, tube: &str) -> i64 {
+        i64::from(self.weights.get(tube).copied().unwrap_or(1))
+    }
+
+    /// Return `tubes` ordered for the next reserve: the tube picked by weight first,
+    /// followed by the others by descending weight.
+    fn order(&mut self, tubes: &[String]) -> Vec<String> {
+        let total: i64 = tubes.iter().map(|t| self.weight(t)).sum();
+        let mut picked: Option<(&String, i64)> = None;
+        for tube in tubes {
+            let weight = self.weight(tube);
+            let current = self.current.entry(tube.clone()).or_insert(0);
+            *current += weight;
+            if picked.map_or(true, |(_, max)| *current > max) {
+                picked = Some((tube, *current));
+            }
+        }
+
+        let picked = match picked {
+            Some((tube, _)) => tube.clone(),
+            None => return vec![],
+        };
+        *self.current.get_mut(&picked).unwrap() -= total;
+
+        let mut rest: Vec<String> = tubes.iter().filter(|t| **t != picked).cloned().collect();
+        rest.sort_by_key(|t| Reverse(self.weight(t)));
+        rest.insert(0, picked);
+        rest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeServer;
+    use crate::process::{OversizeAction, PanicAction};
+
+    #[cfg(feature = "log")]
+    struct CaptureLogger(std::sync::Mutex<Vec<String>>);
+
+    #[cfg(feature = "log")]
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record<'_>) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[tokio::test]
+    async fn test_run_finalizes_panicked_job() {
+        #[cfg(feature = "log")]
+        static LOGGER: CaptureLogger = CaptureLogger(std::sync::Mutex::new(Vec::new()));
+        #[cfg(feature = "log")]
+        {
+            log::set_logger(&LOGGER).unwrap();
+            log::set_max_level(log::LevelFilter::Error);
+        }
+
+        let server = FakeServer::start().await;
+        let id = server.put("default", b"boom");
+        let conn = server
+            .client()
+            .panic_action(PanicAction::Bury)
+            .connect()
+            .await
+            .unwrap();
+
+        let mut worker = Worker::new(conn).poll_interval(Duration::from_secs(1));
+        let drain = worker.drain_handle();
+        worker
+            .run(move |job| {
+                drain.drain();
+                async move {
+                    if job.body() == b"boom" {
+                        panic!("handler of {} failed", job.id());
+                    }
+                    Ok::<_, String>(Outcome::Delete)
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(Some("buried"), server.state(id));
+        #[cfg(feature = "log")]
+        assert!(LOGGER.0.lock().unwrap().contains(&format!(
+            "handler of job {} panicked: handler of {} failed",
+            id, id
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_run_skips_oversized_job() {
+        let server = FakeServer::start().await;
+        let oversized = server.put("default", &[b'x'; 64]);
+        let small = server.put("default", b"small");
+        let conn = server
+            .client()
+            .max_body_size(16, OversizeAction::Bury)
+            .connect()
+            .await
+            .unwrap();
+
+        let mut worker = Worker::new(conn).poll_interval(Duration::from_secs(1));
+        let drain = worker.drain_handle();
+        worker
+            .run(move |job| {
+                assert_eq!(b"small", job.body());
+                drain.drain();
+                async { Ok::<_, String>(Outcome::Delete) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(Some("buried"), server.state(oversized));
+        assert_eq!(None, server.state(small));
+    }
+
+    #[tokio::test]
+    async fn test_tube_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let server = FakeServer::start().await;
+        let jobs = [
+            server.put("heavy", b"heavy"),
+            server.put("heavy", b"heavy"),
+            server.put("heavy", b"heavy"),
+            server.put("light", b"light"),
+        ];
+        let conn = server.client().connect().await.unwrap();
+
+        let mut worker = Worker::new(conn)
+            .tube_concurrency("heavy", 1)
+            .watch("light")
+            .concurrency(4)
+            .poll_interval(Duration::from_secs(1));
+        let drain = worker.drain_handle();
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let done = Arc::new(AtomicUsize::new(0));
+        let (max, finished) = (max_running.clone(), done.clone());
+        worker
+            .run(move |job| {
+                let heavy = job.body() == b"heavy";
+                let (running, max_running, done) =
+                    (running.clone(), max_running.clone(), done.clone());
+                let drain = drain.clone();
+                async move {
+                    if heavy {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_running.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                    }
+                    if done.fetch_add(1, Ordering::SeqCst) + 1 == 4 {
+                        drain.drain();
+                    }
+                    Ok::<_, String>(Outcome::Delete)
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(4, finished.load(Ordering::SeqCst));
+        assert_eq!(1, max.load(Ordering::SeqCst));
+        assert!(jobs.iter().all(|id| server.state(*id).is_none()));
+        assert!(!server.commands().iter().any(|c| c.starts_with("release")));
+    }
+
+    #[tokio::test]
+    async fn test_drain() {
+        let server = FakeServer::start().await;
+        let quick = server.put("default", b"quick");
+        let stuck = server.put("default", b"stuck");
+        let later = server.put("default", b"later");
+        let conn = server.client().connect().await.unwrap();
+
+        let mut worker = Worker::new(conn)
+            .concurrency(2)
+            .poll_interval(Duration::from_secs(1))
+            .drain_timeout(Duration::from_millis(500));
+        let drain = worker.drain_handle();
+        worker
+            .run(move |job| {
+                let stuck = job.body() == b"stuck";
+                if stuck {
+                    drain.drain();
+                }
+                async move {
+                    if stuck {
+                        futures::future::pending::<()>().await;
+                    }
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    Ok::<_, String>(Outcome::Delete)
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(None, server.state(quick));
+        assert_eq!(Some("ready"), server.state(stuck));
+        assert_eq!(Some("ready"), server.state(later));
+        let commands = server.commands();
+        let reserves = commands.iter().filter(|c| c.starts_with("reserve")).count();
+        assert_eq!(2, reserves);
+    }
+
+    #[tokio::test]
+    async fn test_ttr_timeout_without_stats() {
+        let server = FakeServer::start().await;
+        let id = server.put("default", b"stuck");
+        server.reply(&format!("stats-job {}", id), "NOT_FOUND");
+        let conn = server
+            .client()
+            .default_ttr(Duration::from_secs(3))
+            .connect()
+            .await
+            .unwrap();
+
+        let mut worker = Worker::new(conn)
+            .poll_interval(Duration::from_secs(30))
+            .ttr_timeout(Duration::from_secs(1), TimeoutAction::Bury);
+        let run = worker.run(|_job| futures::future::pending::<Result<Outcome, String>>());
+        assert!(tokio::time::timeout(Duration::from_secs(4), run)
+            .await
+            .is_err());
+        assert_eq!(Some("buried"), server.state(id));
+    }
+
+    #[tokio::test]
+    async fn test_ttr_timeout_releases_with_default_delay() {
+        let server = FakeServer::start().await;
+        let id = server.put("default", b"stuck");
+        server.reply(&format!("stats-job {}", id), "NOT_FOUND");
+        let conn = server
+            .client()
+            .default_ttr(Duration::from_secs(3))
+            .default_delay(Duration::from_secs(60))
+            .connect()
+            .await
+            .unwrap();
+
+        let mut worker = Worker::new(conn)
+            .poll_interval(Duration::from_secs(30))
+            .ttr_timeout(Duration::from_secs(1), TimeoutAction::Release);
+        let run = worker.run(|_job| futures::future::pending::<Result<Outcome, String>>());
+        assert!(tokio::time::timeout(Duration::from_secs(4), run)
+            .await
+            .is_err());
+        assert_eq!(Some("delayed"), server.state(id));
+    }
+
+    #[test]
+    fn test_weighted_picker() {
+        let weights = vec![("a".to_string(), 3), ("b".to_string(), 1)]
+            .into_iter()
+            .collect();
+        let mut picker = WeightedPicker::new(&weights);
+        let tubes = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+
+        let picks: Vec<String> = (0..5).map(|_| picker.order(&tubes).remove(0)).collect();
+        assert_eq!(vec!["a", "b", "a", "c", "a"], picks);
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let tubes: Vec<String> = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let mut round_robin = RoundRobin::default();
+        assert_eq!(tubes, round_robin.order(&tubes, &tubes));
+
+        round_robin.served("b");
+        assert_eq!(vec!["c", "a", "b"], round_robin.order(&tubes, &tubes));
+        assert_eq!(vec!["c", "b"], round_robin.order(&tubes, &tubes[1..]));
+
+        round_robin.served("c");
+        assert_eq!(vec!["a", "b", "c"], round_robin.order(&tubes, &tubes));
+    }
+
+    #[test]
+    fn test_weighted_picker_order() {
+        let weights = vec![("b".to_string(), 5)].into_iter().collect();
+        let mut picker = WeightedPicker::new(&weights);
+        let tubes = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+
+        assert_eq!(vec!["b", "a", "c"], picker.order(&tubes));
+        assert!(picker.order(&[]).is_empty());
+    }
+}